@@ -1,5 +1,7 @@
 mod model;
 mod io;
+mod error;
 
 pub use model::*;
-pub use io::*;
\ No newline at end of file
+pub use io::*;
+pub use error::*;
@@ -0,0 +1,210 @@
+use std::{fmt, io};
+
+///
+/// An error encountered while decoding or encoding a bitmap.
+/// 
+#[derive(Debug)]
+pub enum BmpError {
+    ///
+    /// The source of the bitmap could not be read.
+    /// 
+    Io(io::Error),
+    ///
+    /// The bitmap signature was not BM.
+    /// 
+    BadSignature(u16),
+    ///
+    /// The info header is of a size that does not match any
+    /// known header.
+    /// 
+    UnsupportedHeaderSize(u32),
+    ///
+    /// The width and height are too large to represent
+    /// the image in memory.
+    /// 
+    InvalidDimensions {
+        width: i32,
+        height: i32
+    },
+    ///
+    /// The bit depth is not 1, 4, 8, 16, 24 or 32.
+    /// 
+    UnsupportedBitDepth(u16),
+    ///
+    /// The compression method is unknown, or is not valid for
+    /// the bit depth.
+    /// 
+    UnsupportedCompression(u32),
+    ///
+    /// The data ended before all of the expected bytes could be read.
+    /// 
+    Truncated {
+        needed: usize,
+        available: usize
+    },
+    ///
+    /// The bitmap exceeds one of the DecodeLimits.
+    /// 
+    LimitExceeded {
+        limit: DecodeLimit,
+        value: u64,
+        max: u64
+    },
+    ///
+    /// A pixel referred to an entry past the end of the color table.
+    /// 
+    PaletteIndexOutOfRange {
+        index: usize,
+        palette_size: usize
+    },
+    ///
+    /// The number of pixels does not match the width and height
+    /// of the bitmap being encoded.
+    /// 
+    PixelCountMismatch {
+        expected: usize,
+        actual: usize
+    },
+    ///
+    /// A channel mask overlaps another mask, is not contiguous, or
+    /// does not fit in the bit depth of the bitmap being encoded.
+    /// 
+    InvalidChannelMask(u32),
+    ///
+    /// The header of a non-bitmap image is malformed.
+    /// 
+    MalformedHeader(String),
+    ///
+    /// The compressed or filtered image data of a non-bitmap image is corrupt.
+    /// 
+    CorruptData(String),
+    ///
+    /// No registered codec can read or write the image.
+    /// 
+    UnsupportedFormat(String),
+    ///
+    /// An image was requested from a file holding several images,
+    /// such as an icon, but the file has no image at that index.
+    /// 
+    EntryOutOfRange {
+        index: usize,
+        count: usize
+    }
+}
+
+///
+/// One of the DecodeLimits.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeLimit {
+    Width,
+    Height,
+    Pixels,
+    AllocBytes
+}
+
+impl fmt::Display for DecodeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeLimit::Width => write!(f, "width"),
+            DecodeLimit::Height => write!(f, "height"),
+            DecodeLimit::Pixels => write!(f, "pixel count"),
+            DecodeLimit::AllocBytes => write!(f, "allocation size")
+        }
+    }
+}
+
+impl fmt::Display for BmpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BmpError::Io(e) => write!(f, "Failed to read bitmap: {e}"),
+            BmpError::BadSignature(signature) => write!(f, "{signature:#06X} is not a valid bitmap signature."),
+            BmpError::UnsupportedHeaderSize(size) => write!(f, "{size} is not a supported info header size."),
+            BmpError::InvalidDimensions { width, height } => write!(f, "{width}x{height} is not a valid size for a bitmap."),
+            BmpError::UnsupportedBitDepth(bits_per_pixel) => write!(f, "{bits_per_pixel} is not a valid value for 'bits_per_pixel'."),
+            BmpError::UnsupportedCompression(compression) => write!(f, "{compression} is not a supported compression method for this bitmap."),
+            BmpError::Truncated { needed, available } => write!(f, "Bitmap is truncated: needed {needed} bytes, but only {available} were available."),
+            BmpError::LimitExceeded { limit, value, max } => write!(f, "Bitmap {limit} of {value} exceeds the limit of {max}."),
+            BmpError::PaletteIndexOutOfRange { index, palette_size } => write!(f, "Tried to access index {index} of palette, which only has {palette_size} entries!"),
+            BmpError::PixelCountMismatch { expected, actual } => write!(f, "Bitmap should have {expected} pixels, but has {actual}."),
+            BmpError::InvalidChannelMask(mask) => write!(f, "{mask:#010X} is not a valid channel mask for this bitmap."),
+            BmpError::MalformedHeader(reason) => write!(f, "Image header is malformed: {reason}"),
+            BmpError::CorruptData(reason) => write!(f, "Image data is corrupt: {reason}"),
+            BmpError::UnsupportedFormat(reason) => write!(f, "Image format is not supported: {reason}"),
+            BmpError::EntryOutOfRange { index, count } => write!(f, "Tried to read image {index}, but there are only {count} images.")
+        }
+    }
+}
+
+impl std::error::Error for BmpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BmpError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for BmpError {
+    fn from(e: io::Error) -> Self {
+        BmpError::Io(e)
+    }
+}
+
+///
+/// A problem found while decoding a bitmap which did not
+/// prevent it from being decoded.
+/// 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeWarning {
+    ///
+    /// The pixel data ended early. The missing pixels were
+    /// replaced with the fill color.
+    /// 
+    TruncatedPixelData {
+        missing_pixels: usize
+    },
+    ///
+    /// The file size in the header does not match the number
+    /// of bytes actually available.
+    /// 
+    FileSizeMismatch {
+        declared: u32,
+        actual: usize
+    },
+    ///
+    /// The pixel data offset points inside the headers or color table.
+    /// The pixel data was read from the end of the color table instead.
+    /// 
+    DataOffsetOverlapsHeader {
+        data_offset: u32,
+        header_end: usize
+    },
+    ///
+    /// Pixels referred to entries past the end of the color table. Those
+    /// pixels were replaced with the fill color.
+    /// 
+    PaletteTooShort {
+        palette_size: usize,
+        out_of_range_pixels: usize
+    },
+    ///
+    /// The AND mask of an icon or cursor ended early. The rows
+    /// missing from the mask were left opaque.
+    /// 
+    TruncatedMask {
+        missing_rows: usize
+    }
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeWarning::TruncatedPixelData { missing_pixels } => write!(f, "Pixel data is truncated: {missing_pixels} pixels are missing."),
+            DecodeWarning::FileSizeMismatch { declared, actual } => write!(f, "Header declares a file size of {declared} bytes, but {actual} bytes were read."),
+            DecodeWarning::DataOffsetOverlapsHeader { data_offset, header_end } => write!(f, "Pixel data offset {data_offset} is inside the headers, which end at {header_end}."),
+            DecodeWarning::PaletteTooShort { palette_size, out_of_range_pixels } => write!(f, "{out_of_range_pixels} pixels referred to entries past the end of the palette, which only has {palette_size} entries."),
+            DecodeWarning::TruncatedMask { missing_rows } => write!(f, "Icon mask is truncated: {missing_rows} rows are missing.")
+        }
+    }
+}
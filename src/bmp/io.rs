@@ -1,5 +1,21 @@
 mod read;
+mod write;
+mod netpbm;
+mod png;
+mod qoi;
+mod tga;
+mod gif;
+mod ico;
+mod codec;
 mod console;
 
 pub use read::*;
+pub use write::*;
+pub use netpbm::*;
+pub use png::*;
+pub use qoi::*;
+pub use tga::*;
+pub use gif::*;
+pub use ico::*;
+pub use codec::*;
 pub use console::*;
\ No newline at end of file
@@ -0,0 +1,316 @@
+mod builtin;
+
+pub use builtin::*;
+
+use std::io::{Write, BufReader, BufWriter};
+use std::fs::File;
+use std::fmt;
+use super::super::*;
+
+/// 
+/// An image format which can be recognized by the bytes it begins with
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// 
+    /// A bitmap, beginning with BM
+    /// 
+    Bmp,
+    /// 
+    /// An OS/2 bitmap array, beginning with BA
+    /// 
+    BitmapArray,
+    /// 
+    /// A PNG image
+    /// 
+    Png,
+    /// 
+    /// A GIF image, beginning with GIF
+    /// 
+    Gif,
+    /// 
+    /// A Netpbm image, beginning with P1 to P7
+    /// 
+    Netpbm,
+    /// 
+    /// A QOI image, beginning with qoif
+    /// 
+    Qoi,
+    /// 
+    /// A Windows icon or cursor, beginning with a reserved 0, a type of 1 or 2, and at least one image
+    /// 
+    Ico
+}
+
+impl ImageFormat {
+    /// 
+    /// Get the format of the image with the given bytes, from the bytes it begins with
+    /// 
+    pub fn from_magic(magic: &[u8]) -> Option<Self> {
+        match magic {
+            [b'B', b'M', ..] => Some(Self::Bmp),
+            [b'B', b'A', ..] => Some(Self::BitmapArray),
+            [b'G', b'I', b'F', ..] => Some(Self::Gif),
+            [b'P', b'1'..=b'7', ..] => Some(Self::Netpbm),
+            [b'q', b'o', b'i', b'f', ..] => Some(Self::Qoi),
+            [0, 0, 1 | 2, 0, count_low, count_high, ..] if *count_low != 0 || *count_high != 0 => Some(Self::Ico),
+            _ if magic.starts_with(&BitMapRaw::PNG_SIGNATURE) => Some(Self::Png),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageFormat::Bmp => write!(f, "bitmap"),
+            ImageFormat::BitmapArray => write!(f, "OS/2 bitmap array"),
+            ImageFormat::Png => write!(f, "PNG"),
+            ImageFormat::Gif => write!(f, "GIF"),
+            ImageFormat::Netpbm => write!(f, "Netpbm"),
+            ImageFormat::Qoi => write!(f, "QOI"),
+            ImageFormat::Ico => write!(f, "ICO")
+        }
+    }
+}
+
+/// 
+/// Reads images of a format. Implement this to add a format to a CodecRegistry.
+/// 
+pub trait Decoder {
+    /// 
+    /// The name of the format, for messages
+    /// 
+    fn name(&self) -> &str;
+
+    /// 
+    /// Whether the image with the given bytes is of this format, from the bytes it begins with
+    /// 
+    fn can_decode(&self, buffer: &[u8]) -> bool;
+
+    /// 
+    /// Lower case extensions, without the dot, of files which are of this format even if
+    /// no decoder recognizes their bytes. Only needed for formats without a signature.
+    /// 
+    fn extensions(&self) -> &[&str] {
+        &[]
+    }
+
+    /// 
+    /// Read the image from the given bytes, with the given settings. Animated formats give their first frame.
+    /// 
+    fn decode(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError>;
+
+    /// 
+    /// Read every frame of the image from the given bytes, with the given settings. By default,
+    /// the image is a single frame.
+    /// 
+    fn decode_frames(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapAnimation, BmpError> {
+        self.decode(buffer, settings).map(BitMapAnimation::from)
+    }
+}
+
+/// 
+/// Writes images in a format. Implement this to add a format to a CodecRegistry.
+/// 
+pub trait Encoder {
+    /// 
+    /// The name of the format, for messages
+    /// 
+    fn name(&self) -> &str;
+
+    /// 
+    /// Lower case extensions, without the dot, of the files this encoder writes
+    /// 
+    fn extensions(&self) -> &[&str];
+
+    /// 
+    /// Write the image to the given destination
+    /// 
+    fn encode(&self, bitmap: &BitMapRaw, writer: &mut dyn Write) -> Result<(), BmpError>;
+}
+
+/// 
+/// The decoders and encoders to read and write images with. Decoders are chosen by the bytes an
+/// image begins with, and encoders by the extension of the file to write.
+/// 
+/// Codecs registered later are tried first, so they can replace the built in codecs.
+/// 
+pub struct CodecRegistry {
+    decoders: Vec<Box<dyn Decoder>>,
+    encoders: Vec<Box<dyn Encoder>>
+}
+
+impl CodecRegistry {
+    /// 
+    /// Create a registry without any codecs
+    /// 
+    pub fn new() -> Self {
+        CodecRegistry {
+            decoders: Vec::new(),
+            encoders: Vec::new()
+        }
+    }
+
+    pub fn register_decoder(&mut self, decoder: Box<dyn Decoder>) -> &Self {
+        self.decoders.push(decoder);
+        self
+    }
+
+    pub fn register_encoder(&mut self, encoder: Box<dyn Encoder>) -> &Self {
+        self.encoders.push(encoder);
+        self
+    }
+
+    /// 
+    /// Get the decoder for the image with the given bytes: the last registered which recognizes
+    /// the bytes, or if none do, the last registered which reads files with the given extension.
+    /// 
+    pub fn find_decoder(&self, buffer: &[u8], extension: Option<&str>) -> Option<&dyn Decoder> {
+        self.decoders.iter().rev()
+            .find(|decoder| decoder.can_decode(buffer))
+            .or_else(|| {
+                let extension = extension?.to_lowercase();
+
+                self.decoders.iter().rev()
+                    .find(|decoder| decoder.extensions().contains(&extension.as_str()))
+            })
+            .map(|decoder| decoder.as_ref())
+    }
+
+    /// 
+    /// Get the last registered encoder which writes files with the given extension
+    /// 
+    pub fn find_encoder(&self, extension: &str) -> Option<&dyn Encoder> {
+        let extension = extension.to_lowercase();
+
+        self.encoders.iter().rev()
+            .find(|encoder| encoder.extensions().contains(&extension.as_str()))
+            .map(|encoder| encoder.as_ref())
+    }
+
+    /// 
+    /// Get every extension which an encoder writes, in the order the encoders were registered
+    /// 
+    pub fn encoder_extensions(&self) -> Vec<&str> {
+        let mut extensions: Vec<&str> = Vec::new();
+
+        for extension in self.encoders.iter().flat_map(|encoder| encoder.extensions()) {
+            if !extensions.contains(extension) {
+                extensions.push(extension);
+            }
+        }
+
+        extensions
+    }
+
+    /// 
+    /// Get the decoder for the image with the given bytes, or an error naming the format if no decoder reads it
+    /// 
+    fn require_decoder(&self, buffer: &[u8], extension: Option<&str>) -> Result<&dyn Decoder, BmpError> {
+        self.find_decoder(buffer, extension).ok_or_else(|| BmpError::UnsupportedFormat(match ImageFormat::from_magic(buffer) {
+            Some(format) => format!("No decoder is registered for {format} images."),
+            None => String::from("The format of the image was not recognized.")
+        }))
+    }
+
+    /// 
+    /// Read an image from the given bytes, in whatever format it is, with the given settings.
+    /// Animated formats give their first frame. The extension is only used for formats without a signature.
+    /// 
+    pub fn decode(&self, buffer: &[u8], extension: Option<&str>, settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        self.require_decoder(buffer, extension)?.decode(buffer, settings)
+    }
+
+    /// 
+    /// Read every frame of an image from the given bytes, in whatever format it is, with the given settings.
+    /// Still images are a single frame. The extension is only used for formats without a signature.
+    /// 
+    pub fn decode_frames(&self, buffer: &[u8], extension: Option<&str>, settings: &BitMapRawReadSettings) -> Result<BitMapAnimation, BmpError> {
+        self.require_decoder(buffer, extension)?.decode_frames(buffer, settings)
+    }
+
+    /// 
+    /// Read an image from the file at the given path, in whatever format it is, with the given settings
+    /// 
+    pub fn read_from_file(&self, path: &str, settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        let buffer = BitMapRaw::read_to_buffer(&mut BufReader::new(File::open(path)?), settings)?;
+
+        self.decode(&buffer, Self::extension(path).as_deref(), settings)
+    }
+
+    /// 
+    /// Read every frame of an image from the file at the given path, in whatever format it is, with the given settings
+    /// 
+    pub fn read_frames_from_file(&self, path: &str, settings: &BitMapRawReadSettings) -> Result<BitMapAnimation, BmpError> {
+        let buffer = BitMapRaw::read_to_buffer(&mut BufReader::new(File::open(path)?), settings)?;
+
+        self.decode_frames(&buffer, Self::extension(path).as_deref(), settings)
+    }
+
+    /// 
+    /// Write an image to the given destination, with the encoder for the given extension
+    /// 
+    pub fn encode(&self, bitmap: &BitMapRaw, extension: &str, writer: &mut dyn Write) -> Result<(), BmpError> {
+        match self.find_encoder(extension) {
+            Some(encoder) => encoder.encode(bitmap, writer),
+            None => Err(BmpError::UnsupportedFormat(format!("No encoder is registered for the extension '{extension}'. The extension must be one of {}.", self.encoder_extensions().join(", "))))
+        }
+    }
+
+    /// 
+    /// Write an image to the file at the given path, in the format of its extension
+    /// 
+    pub fn write_to_file(&self, bitmap: &BitMapRaw, path: &str) -> Result<(), BmpError> {
+        let extension = Self::extension(path).unwrap_or_default();
+
+        //Check for an encoder before creating the file
+        if self.find_encoder(&extension).is_none() {
+            return self.encode(bitmap, &extension, &mut std::io::sink());
+        }
+
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        self.encode(bitmap, &extension, &mut writer)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// 
+    /// Get the lower case extension of the file at the given path, if it has one
+    /// 
+    fn extension(path: &str) -> Option<String> {
+        std::path::Path::new(path).extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+    }
+}
+
+impl Default for CodecRegistry {
+    /// 
+    /// A registry of every built in codec: bitmaps, PNG, GIF, ICO and OS/2 bitmap arrays (read only), QOI, TGA and Netpbm
+    /// 
+    fn default() -> Self {
+        let mut registry = Self::new();
+
+        registry.register_decoder(Box::new(BmpCodec::default()));
+        registry.register_decoder(Box::new(BitmapArrayCodec::default()));
+        registry.register_decoder(Box::new(PngCodec::default()));
+        registry.register_decoder(Box::new(GifCodec));
+        registry.register_decoder(Box::new(IcoCodec::default()));
+        registry.register_decoder(Box::new(QoiCodec::default()));
+        registry.register_decoder(Box::new(TgaCodec::default()));
+        registry.register_decoder(Box::new(NetpbmCodec::default()));
+
+        registry.register_encoder(Box::new(BmpCodec::default()));
+        registry.register_encoder(Box::new(PngCodec::default()));
+        registry.register_encoder(Box::new(QoiCodec::default()));
+        registry.register_encoder(Box::new(TgaCodec::default()));
+
+        for format in [NetpbmFormat::Pbm, NetpbmFormat::Pgm, NetpbmFormat::Ppm, NetpbmFormat::Pam] {
+            registry.register_encoder(Box::new(NetpbmCodec::new(format, NetpbmEncoding::Binary, 0xFF)));
+        }
+
+        registry
+    }
+}
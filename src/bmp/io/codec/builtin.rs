@@ -0,0 +1,380 @@
+use std::io::Write;
+use super::*;
+
+/// 
+/// Reads and writes bitmaps
+/// 
+#[derive(Clone, Default)]
+pub struct BmpCodec {
+    /// 
+    /// The settings to write with. If not given, the defaults are used, except that 32 bpp
+    /// images stay 32 bpp, and images with an alpha channel are written with BI_BITFIELDS
+    /// and an alpha mask.
+    /// 
+    settings: Option<BitMapRawWriteSettings>
+}
+
+impl BmpCodec {
+    pub fn new(settings: Option<BitMapRawWriteSettings>) -> Self {
+        BmpCodec {
+            settings
+        }
+    }
+}
+
+impl Decoder for BmpCodec {
+    fn name(&self) -> &str {
+        "bitmap"
+    }
+
+    fn can_decode(&self, buffer: &[u8]) -> bool {
+        ImageFormat::from_magic(buffer) == Some(ImageFormat::Bmp)
+    }
+
+    fn decode(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        BitMapRaw::from_bytes_with_settings(buffer, settings)
+    }
+}
+
+impl Encoder for BmpCodec {
+    fn name(&self) -> &str {
+        "bitmap"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bmp"]
+    }
+
+    fn encode(&self, bitmap: &BitMapRaw, mut writer: &mut dyn Write) -> Result<(), BmpError> {
+        let settings = match &self.settings {
+            Some(settings) => settings.clone(),
+            None => {
+                let mut settings = BitMapRawWriteSettings::default();
+
+                if bitmap.has_alpha {
+                    settings.with_bits_per_pixel(32);
+                    settings.with_compression(BitMapInfoHeader::BI_BITFIELDS);
+                    settings.with_channel_masks(Some(BitMapChannelMasks::ARGB8888));
+                }
+                else if bitmap.info_header.bits_per_pixel == 32 {
+                    settings.with_bits_per_pixel(32);
+                }
+
+                settings
+            }
+        };
+
+        bitmap.write_to_with_settings(&mut writer, &settings).map(|_| ())
+    }
+}
+
+/// 
+/// Reads and writes PNG images
+/// 
+#[derive(Clone, Default)]
+pub struct PngCodec {
+    settings: PngWriteSettings
+}
+
+impl PngCodec {
+    pub fn new(settings: PngWriteSettings) -> Self {
+        PngCodec {
+            settings
+        }
+    }
+}
+
+impl Decoder for PngCodec {
+    fn name(&self) -> &str {
+        "PNG"
+    }
+
+    fn can_decode(&self, buffer: &[u8]) -> bool {
+        ImageFormat::from_magic(buffer) == Some(ImageFormat::Png)
+    }
+
+    fn decode(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        BitMapRaw::from_png_bytes_with_settings(buffer, settings)
+    }
+}
+
+impl Encoder for PngCodec {
+    fn name(&self) -> &str {
+        "PNG"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["png"]
+    }
+
+    fn encode(&self, bitmap: &BitMapRaw, mut writer: &mut dyn Write) -> Result<(), BmpError> {
+        bitmap.write_png_to(&mut writer, &self.settings)
+    }
+}
+
+/// 
+/// Reads GIF images, and every frame of animated GIFs
+/// 
+#[derive(Clone, Default)]
+pub struct GifCodec;
+
+impl Decoder for GifCodec {
+    fn name(&self) -> &str {
+        "GIF"
+    }
+
+    fn can_decode(&self, buffer: &[u8]) -> bool {
+        ImageFormat::from_magic(buffer) == Some(ImageFormat::Gif)
+    }
+
+    fn decode(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        BitMapRaw::from_gif_bytes_with_settings(buffer, settings)
+    }
+
+    fn decode_frames(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapAnimation, BmpError> {
+        BitMapAnimation::from_gif_bytes_with_settings(buffer, settings)
+    }
+}
+
+/// 
+/// Reads an image of an icon or cursor
+/// 
+#[derive(Clone, Default)]
+pub struct IcoCodec {
+    /// 
+    /// The index of the image to read. If not given, the largest image is read.
+    /// 
+    entry: Option<usize>
+}
+
+impl IcoCodec {
+    pub fn new(entry: Option<usize>) -> Self {
+        IcoCodec {
+            entry
+        }
+    }
+}
+
+impl Decoder for IcoCodec {
+    fn name(&self) -> &str {
+        "ICO"
+    }
+
+    fn can_decode(&self, buffer: &[u8]) -> bool {
+        ImageFormat::from_magic(buffer) == Some(ImageFormat::Ico)
+    }
+
+    fn decode(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        match self.entry {
+            Some(entry) => BitMapRaw::from_ico_entry_bytes_with_settings(buffer, entry, settings),
+            None => BitMapRaw::from_ico_bytes_with_settings(buffer, settings)
+        }
+    }
+}
+
+/// 
+/// Reads a bitmap of an OS/2 bitmap array
+/// 
+#[derive(Clone, Default)]
+pub struct BitmapArrayCodec {
+    /// 
+    /// The index of the bitmap to read
+    /// 
+    entry: Option<usize>,
+    /// 
+    /// If no index is given, the largest bitmap which fits in this width and height, in pixels, is read.
+    /// If neither is given, the largest bitmap is read.
+    /// 
+    fit: Option<(u32, u32)>
+}
+
+impl BitmapArrayCodec {
+    pub fn new(entry: Option<usize>, fit: Option<(u32, u32)>) -> Self {
+        BitmapArrayCodec {
+            entry,
+            fit
+        }
+    }
+}
+
+impl Decoder for BitmapArrayCodec {
+    fn name(&self) -> &str {
+        "OS/2 bitmap array"
+    }
+
+    fn can_decode(&self, buffer: &[u8]) -> bool {
+        ImageFormat::from_magic(buffer) == Some(ImageFormat::BitmapArray)
+    }
+
+    fn decode(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        let array = BitMapArray::from_bytes_with_settings(buffer, settings)?;
+
+        let index = match (self.entry, self.fit) {
+            (Some(entry), _) => Some(entry),
+            (None, Some((width, height))) => array.best_fit(width, height),
+            (None, None) => array.largest()
+        };
+
+        match index {
+            Some(index) => array.into_entry(index),
+            None => Err(BmpError::MalformedHeader(String::from("The bitmap array has no bitmaps.")))
+        }
+    }
+}
+
+/// 
+/// Reads and writes QOI images
+/// 
+#[derive(Clone, Default)]
+pub struct QoiCodec {
+    settings: QoiWriteSettings
+}
+
+impl QoiCodec {
+    pub fn new(settings: QoiWriteSettings) -> Self {
+        QoiCodec {
+            settings
+        }
+    }
+}
+
+impl Decoder for QoiCodec {
+    fn name(&self) -> &str {
+        "QOI"
+    }
+
+    fn can_decode(&self, buffer: &[u8]) -> bool {
+        ImageFormat::from_magic(buffer) == Some(ImageFormat::Qoi)
+    }
+
+    fn decode(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        BitMapRaw::from_qoi_bytes_with_settings(buffer, settings)
+    }
+}
+
+impl Encoder for QoiCodec {
+    fn name(&self) -> &str {
+        "QOI"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["qoi"]
+    }
+
+    fn encode(&self, bitmap: &BitMapRaw, mut writer: &mut dyn Write) -> Result<(), BmpError> {
+        bitmap.write_qoi_to(&mut writer, &self.settings)
+    }
+}
+
+/// 
+/// Reads and writes TGA images. TGA images have no signature, so are only read from files with a .tga extension.
+/// 
+#[derive(Clone, Default)]
+pub struct TgaCodec {
+    settings: TgaWriteSettings
+}
+
+impl TgaCodec {
+    pub fn new(settings: TgaWriteSettings) -> Self {
+        TgaCodec {
+            settings
+        }
+    }
+}
+
+impl Decoder for TgaCodec {
+    fn name(&self) -> &str {
+        "TGA"
+    }
+
+    fn can_decode(&self, _buffer: &[u8]) -> bool {
+        false
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tga"]
+    }
+
+    fn decode(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        BitMapRaw::from_tga_bytes_with_settings(buffer, settings)
+    }
+}
+
+impl Encoder for TgaCodec {
+    fn name(&self) -> &str {
+        "TGA"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tga"]
+    }
+
+    fn encode(&self, bitmap: &BitMapRaw, mut writer: &mut dyn Write) -> Result<(), BmpError> {
+        bitmap.write_tga_to(&mut writer, &self.settings)
+    }
+}
+
+/// 
+/// Reads every Netpbm format, and writes one of them
+/// 
+#[derive(Clone)]
+pub struct NetpbmCodec {
+    /// 
+    /// The format to write, which also sets the extension
+    /// 
+    format: NetpbmFormat,
+    encoding: NetpbmEncoding,
+    max_value: u16
+}
+
+impl NetpbmCodec {
+    pub fn new(format: NetpbmFormat, encoding: NetpbmEncoding, max_value: u16) -> Self {
+        NetpbmCodec {
+            format,
+            encoding,
+            max_value
+        }
+    }
+}
+
+impl Default for NetpbmCodec {
+    /// 
+    /// Writes binary PPM, with 1 byte per sample
+    /// 
+    fn default() -> Self {
+        Self::new(NetpbmFormat::Ppm, NetpbmEncoding::Binary, 0xFF)
+    }
+}
+
+impl Decoder for NetpbmCodec {
+    fn name(&self) -> &str {
+        "Netpbm"
+    }
+
+    fn can_decode(&self, buffer: &[u8]) -> bool {
+        ImageFormat::from_magic(buffer) == Some(ImageFormat::Netpbm)
+    }
+
+    fn decode(&self, buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        BitMapRaw::from_netpbm_bytes_with_settings(buffer, settings)
+    }
+}
+
+impl Encoder for NetpbmCodec {
+    fn name(&self) -> &str {
+        "Netpbm"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        match self.format {
+            NetpbmFormat::Pbm => &["pbm"],
+            NetpbmFormat::Pgm => &["pgm"],
+            NetpbmFormat::Ppm => &["ppm"],
+            NetpbmFormat::Pam => &["pam"]
+        }
+    }
+
+    fn encode(&self, bitmap: &BitMapRaw, mut writer: &mut dyn Write) -> Result<(), BmpError> {
+        bitmap.write_netpbm_to(&mut writer, &NetpbmWriteSettings::new(self.format, self.encoding, self.max_value))
+    }
+}
@@ -1,8 +1,63 @@
 use colored::{Colorize, ColoredString};
-use std::{collections::HashMap};
+use std::{collections::HashMap, io::Write, time::Duration};
 use unicode_segmentation::UnicodeSegmentation;
 use super::super::*;
 
+mod blocks;
+mod symbols;
+
+pub use symbols::*;
+
+///
+/// How the pixels of an image are drawn in the cells of the console
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleRenderMode {
+    ///
+    /// Each pixel is drawn as pixel_width copies of the string in opacity_levels for its opacity
+    /// 
+    Glyph,
+    ///
+    /// Each cell holds two pixels, one above the other, drawn as ▀ or ▄ with the color of one in the
+    /// foreground and the other in the background. Transparent halves show the background color, if given.
+    /// 
+    HalfBlock,
+    ///
+    /// Each cell holds 2x2 pixels, drawn as a quadrant block split between two colors
+    /// 
+    Quadrant,
+    ///
+    /// Each cell holds 2x3 pixels, drawn as a Unicode 13 sextant block split between two colors
+    /// 
+    Sextant,
+    ///
+    /// Each cell holds 2x4 pixels, drawn as Braille dots in one color over a background of another
+    /// 
+    Braille,
+    ///
+    /// Each cell holds 2x4 pixels, drawn as whichever of the symbols, with a foreground and background color,
+    /// is closest to them by the distance algorithm
+    /// 
+    Symbols
+}
+
+///
+/// How the pixels of a cell in the Quadrant, Sextant and Braille render modes are split between
+/// the foreground and background colors
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleCellSplit {
+    ///
+    /// Pixels at least as bright as the average of the cell are in the foreground, and the rest in the background
+    /// 
+    Threshold,
+    ///
+    /// Of every way to split the pixels, the one whose average colors are closest to the pixels,
+    /// by the distance algorithm
+    /// 
+    BestFit
+}
+
 ///
 /// Settings for BitMapRaw::draw_to_console
 /// 
@@ -33,13 +88,26 @@ pub struct BitMapRawDrawToConsoleSettings {
     ///
     /// The algorithm to use to find the nearest console color
     ///
-    algorithm: fn(&RGBColor, &RGBColor) -> f32
+    algorithm: fn(&RGBColor, &RGBColor) -> f32,
+    ///
+    /// How the pixels are drawn in the cells of the console
+    /// 
+    render_mode: ConsoleRenderMode,
+    ///
+    /// How the pixels of each cell are split between two colors,
+    /// for render modes with more than two pixels in a cell
+    /// 
+    cell_split: ConsoleCellSplit,
+    ///
+    /// The characters to choose from for each cell, in the Symbols render mode
+    /// 
+    symbols: Vec<ConsoleSymbol>
 }
 
 impl BitMapRawDrawToConsoleSettings {
     ///
     /// Create a new instance of BitMapRawDrawToConsoleSettings with the
-    /// given settings
+    /// given settings, drawing each pixel with the strings in opacity_levels
     /// 
     pub fn new(transparency: Option<u32>, use_truecolor: bool, pixel_width: u32, opacity_levels: Vec<String>, background: Option<u32>, algorithm: fn(&RGBColor, &RGBColor) -> f32) -> Self {
         BitMapRawDrawToConsoleSettings {
@@ -48,7 +116,10 @@ impl BitMapRawDrawToConsoleSettings {
             pixel_width,
             opacity_levels,
             background,
-            algorithm
+            algorithm,
+            render_mode: ConsoleRenderMode::Glyph,
+            cell_split: ConsoleCellSplit::BestFit,
+            symbols: [ConsoleSymbol::blocks(), ConsoleSymbol::wedges(), ConsoleSymbol::box_drawing()].concat()
         }
     }
 
@@ -82,6 +153,21 @@ impl BitMapRawDrawToConsoleSettings {
         self
     }
 
+    pub fn with_render_mode(&mut self, render_mode: ConsoleRenderMode) -> &Self {
+        self.render_mode = render_mode;
+        self
+    }
+
+    pub fn with_cell_split(&mut self, cell_split: ConsoleCellSplit) -> &Self {
+        self.cell_split = cell_split;
+        self
+    }
+
+    pub fn with_symbols(&mut self, symbols: Vec<ConsoleSymbol>) -> &Self {
+        self.symbols = symbols;
+        self
+    }
+
     pub fn clone_with_transparency(&self, transparency: Option<u32>) -> Self {
         let mut cloned = self.clone();
         cloned.with_transparency(transparency);
@@ -118,6 +204,24 @@ impl BitMapRawDrawToConsoleSettings {
         cloned
     }
 
+    pub fn clone_with_render_mode(&self, render_mode: ConsoleRenderMode) -> Self {
+        let mut cloned = self.clone();
+        cloned.with_render_mode(render_mode);
+        cloned
+    }
+
+    pub fn clone_with_cell_split(&self, cell_split: ConsoleCellSplit) -> Self {
+        let mut cloned = self.clone();
+        cloned.with_cell_split(cell_split);
+        cloned
+    }
+
+    pub fn clone_with_symbols(&self, symbols: Vec<ConsoleSymbol>) -> Self {
+        let mut cloned = self.clone();
+        cloned.with_symbols(symbols);
+        cloned
+    }
+
     ///
     /// Get the width of the longest string
     /// in opacity_levels
@@ -133,6 +237,20 @@ impl BitMapRawDrawToConsoleSettings {
             .unwrap_or(0)
         }
     }
+
+    ///
+    /// Get the width and height, in pixels, of the largest image which can be drawn
+    /// in the given number of columns and rows of the console
+    /// 
+    pub fn pixels_fitting(&self, columns: u32, rows: u32) -> (u32, u32) {
+        match self.render_mode {
+            ConsoleRenderMode::Glyph => (columns / u32::max(1, self.pixel_string_width() as u32 * self.pixel_width), rows),
+            ConsoleRenderMode::HalfBlock => (columns / u32::max(1, self.pixel_width), rows.saturating_mul(2)),
+            ConsoleRenderMode::Quadrant => (columns.saturating_mul(2) / u32::max(1, self.pixel_width), rows.saturating_mul(2)),
+            ConsoleRenderMode::Sextant => (columns.saturating_mul(2) / u32::max(1, self.pixel_width), rows.saturating_mul(3)),
+            ConsoleRenderMode::Braille | ConsoleRenderMode::Symbols => (columns.saturating_mul(2) / u32::max(1, self.pixel_width), rows.saturating_mul(4))
+        }
+    }
 }
 
 impl Clone for BitMapRawDrawToConsoleSettings {
@@ -141,15 +259,56 @@ impl Clone for BitMapRawDrawToConsoleSettings {
             .map(String::from)
             .collect();
 
-        Self::new(self.transparency, self.use_truecolor, self.pixel_width, cloned_opacity_levels, self.background, self.algorithm)
+        let mut cloned = Self::new(self.transparency, self.use_truecolor, self.pixel_width, cloned_opacity_levels, self.background, self.algorithm);
+        cloned.with_render_mode(self.render_mode);
+        cloned.with_cell_split(self.cell_split);
+        cloned.with_symbols(self.symbols.clone());
+        cloned
     }
 }
 
 impl BitMapRaw {
     const TRANSPARENT_STRING: &str = " ";
     const TRANSPARENT_STRING_W_BACKGROUND: &str = ".";
+    const UPPER_HALF_BLOCK: &str = "▀";
+    const LOWER_HALF_BLOCK: &str = "▄";
+
+    ///
+    /// The colors of the console, as RGBA, with the colors which draw them
+    /// 
+    const CONSOLE_COLORS: [(u32, colored::Color); 16] = [
+        (0x00000000, colored::Color::Black), //Black
+        (0x00008000, colored::Color::Blue), //Dark blue
+        (0x00800000, colored::Color::Green), //Dark green
+        (0x00808000, colored::Color::Cyan), //Cark cyan
+        (0x80000000, colored::Color::Red), //Dark red
+        (0x80008000, colored::Color::Magenta), //Dark magenta
+        (0x80800000, colored::Color::Yellow), //Dark yellow
+        (0x80808000, colored::Color::White), //Dark grey
+        (0x0000FF00, colored::Color::BrightBlue), //Blue
+        (0x00FF0000, colored::Color::BrightGreen), //Green
+        (0x00FFFF00, colored::Color::BrightCyan), //Cyan
+        (0xFF000000, colored::Color::BrightRed), //Red
+        (0xFF00FF00, colored::Color::BrightMagenta), //Magenta
+        (0xFFFF0000, colored::Color::BrightYellow), //Yellow
+        (0xC0C0C000, colored::Color::BrightBlack), //Grey
+        (0xFFFFFF00, colored::Color::BrightWhite) //White
+    ];
 
     pub fn draw_to_console(&self, settings: &BitMapRawDrawToConsoleSettings) {
+        let adjusted_settings = Self::draw_legend_to_console(settings);
+
+        self.draw_rows_to_console(&adjusted_settings);
+    }
+
+    ///
+    /// Write the top padding, and the transparent and background colors if given. Returns the settings
+    /// with the transparent and background colors adjusted to how they will be drawn.
+    /// 
+    fn draw_legend_to_console(settings: &BitMapRawDrawToConsoleSettings) -> BitMapRawDrawToConsoleSettings {
+        //colored only has set_virtual_terminal on Windows, where it turns on ANSI escape codes. Elsewhere they are always on,
+        //and calling it fails to build.
+        #[cfg(windows)]
         let _ = colored::control::set_virtual_terminal(true);
 
         //Write some top padding
@@ -246,7 +405,43 @@ impl BitMapRaw {
         }
 
         let adjusted_settings = settings.clone_with_transparency(*adjusted_transparency);
-        let adjusted_settings = adjusted_settings.clone_with_background(*adjusted_background);
+        adjusted_settings.clone_with_background(*adjusted_background)
+    }
+
+    ///
+    /// Write each row of the image, each starting on a new line, with settings from draw_legend_to_console.
+    /// Returns the number of lines written.
+    /// 
+    fn draw_rows_to_console(&self, settings: &BitMapRawDrawToConsoleSettings) -> usize {
+        let rows = self.to_console_rows(settings);
+
+        for row in &rows {
+            //Move to the next line
+            println!();
+            print!("{row}");
+        }
+
+        rows.len()
+    }
+
+    ///
+    /// Get the lines of colored text which draw the image in the console, from the top down
+    /// 
+    pub fn to_console_rows(&self, settings: &BitMapRawDrawToConsoleSettings) -> Vec<String> {
+        match settings.render_mode {
+            ConsoleRenderMode::Glyph => self.glyph_rows(settings),
+            ConsoleRenderMode::HalfBlock => self.half_block_rows(settings),
+            ConsoleRenderMode::Quadrant | ConsoleRenderMode::Sextant | ConsoleRenderMode::Braille => self.block_rows(settings),
+            ConsoleRenderMode::Symbols => self.symbol_rows(settings)
+        }
+    }
+
+    ///
+    /// Get the lines of the image with each pixel drawn as the string for its opacity
+    /// 
+    fn glyph_rows(&self, settings: &BitMapRawDrawToConsoleSettings) -> Vec<String> {
+        let adjusted_background = &settings.background;
+        let mut rows: Vec<String> = Vec::new();
 
         let m = i32::abs(self.info_header.height);
         let n = i32::abs(self.info_header.width);
@@ -259,8 +454,7 @@ impl BitMapRaw {
                 _ => j_temp
             };
 
-            //Move to the next line
-            println!();
+            let mut row = String::new();
 
             //Inner loop is columns
             for i_temp in 0..n {
@@ -273,8 +467,16 @@ impl BitMapRaw {
                 //Calculate index of next color
                 let index = (n * (m - j - 1)) + i;
                 
-                //Get color from index
-                let pixel = &self.pixel_data.pixels[index as usize].clone();
+                //Get color from index. A bitmap built by hand may be missing pixels; draw those as transparent.
+                let pixel = &match self.get_pixel(index as usize) {
+                    Some(pixel) => self.drawn_color(pixel),
+                    None => RGBColor {
+                        red: 0,
+                        green: 0,
+                        blue: 0,
+                        alpha: 0
+                    }
+                };
 
                 //Get background color
                 let background_color = &adjusted_background
@@ -286,7 +488,7 @@ impl BitMapRaw {
                 
 
                 //Get pixels string to use from opacity
-                let pixel_string_ndx: Option<usize> = Self::get_pixel_from_opacity(pixel, &adjusted_settings);
+                let pixel_string_ndx: Option<usize> = Self::get_pixel_from_opacity(pixel, settings);
 
                 let width = u32::min(usize::MAX as u32, (settings.pixel_string_width() as u32) * settings.pixel_width) as usize;
 
@@ -300,13 +502,110 @@ impl BitMapRaw {
                 };
 
                 //Apply ANSI coloring to the string so it is printed with color
-                let (to_print, _) = Self::color_string(pixel_string.as_str(), color, &adjusted_settings);
+                let (to_print, _) = Self::color_string(pixel_string.as_str(), color, settings);
+
+                //Add the next pixel to the row
+                row.push_str(&to_print.to_string());
+            }
+
+            rows.push(row);
+        }
+
+        rows
+    }
+
+    ///
+    /// Get the lines of the image with each cell holding two pixels, the upper in the foreground of ▀ and the
+    /// lower in its background. If only one is transparent, ▄ or ▀ is drawn in the color of the other, over
+    /// the background color, if given.
+    /// 
+    fn half_block_rows(&self, settings: &BitMapRawDrawToConsoleSettings) -> Vec<String> {
+        let width = self.info_header.width.unsigned_abs() as usize;
+        let height = self.info_header.height.unsigned_abs() as usize;
+
+        let background = settings.background.map(|n| RGBColor {
+            alpha: 0xFF,
+            ..RGBColor::from_u32(n, true)
+        });
+
+        let cell_width = settings.pixel_width as usize;
+
+        (0..height.div_ceil(2))
+            .map(|row| (0..width)
+                .map(|x| {
+                    let upper = self.get_cell_color(x, row * 2, &background, settings);
+                    let lower = self.get_cell_color(x, row * 2 + 1, &background, settings);
+
+                    let (cell_string, foreground, cell_background) = match (upper, lower) {
+                        (Some(upper), lower) => (Self::UPPER_HALF_BLOCK, Some(upper), lower.or(background.clone())),
+                        (None, Some(lower)) => (Self::LOWER_HALF_BLOCK, Some(lower), background.clone()),
+                        (None, None) => (Self::TRANSPARENT_STRING, None, background.clone())
+                    };
+
+                    Self::color_cell(&Self::repeat_string(cell_string, cell_width), &foreground, &cell_background, settings)
+                })
+                .collect::<String>())
+            .collect()
+    }
+
+    ///
+    /// Get the color with which to draw the pixel at the given column and row, counting from the top left, or None
+    /// if it is transparent or outside of the image. Partly transparent pixels are blended over the background, if given.
+    /// 
+    fn get_cell_color(&self, x: usize, y: usize, background: &Option<RGBColor>, settings: &BitMapRawDrawToConsoleSettings) -> Option<RGBColor> {
+        let pixel = self.drawn_color(self.get_pixel_at(x, y)?);
+
+        let (_, _, is_transparent) = Self::get_color_type(&pixel, settings);
+
+        if is_transparent || pixel.alpha == 0 {
+            return None;
+        }
+
+        match background {
+            Some(background) if pixel.alpha < 0xFF => {
+                let blend = |value: u8, under: u8| ((value as u32 * pixel.alpha as u32 + under as u32 * (0xFF - pixel.alpha as u32)) / 0xFF) as u8;
+
+                Some(RGBColor {
+                    red: blend(pixel.red, background.red),
+                    green: blend(pixel.green, background.green),
+                    blue: blend(pixel.blue, background.blue),
+                    alpha: 0xFF
+                })
+            },
+            _ => Some(pixel)
+        }
+    }
 
-                //Print the next pixel to the console
-                print!("{to_print}");
+    ///
+    /// Get the color with which to draw the given pixel of the image. Without an alpha channel, the fourth byte
+    /// of each pixel is unused rather than its opacity, so the pixel is drawn opaque.
+    /// 
+    fn drawn_color(&self, pixel: RGBColor) -> RGBColor {
+        match self.has_alpha {
+            true => pixel,
+            false => RGBColor {
+                alpha: 0xFF,
+                ..pixel
             }
         }
+    }
+
+    ///
+    /// Apply ANSI coloring to the given cell, with the given foreground and background colors, or the
+    /// console's own if not given
+    /// 
+    fn color_cell(value: &str, foreground: &Option<RGBColor>, background: &Option<RGBColor>, settings: &BitMapRawDrawToConsoleSettings) -> String {
+        let mut coloring = ColoredString::from(value);
+
+        if let Some((Some(fg), _, _)) = foreground.as_ref().map(|color| Self::get_color_type(color, settings)) {
+            coloring = coloring.color(fg);
+        }
 
+        if let Some((Some(bg), _, _)) = background.as_ref().map(|color| Self::get_color_type(color, settings)) {
+            coloring = coloring.on_color(bg);
+        }
+
+        coloring.to_string()
     }
 
     fn get_pixel_from_opacity(color: &RGBColor, settings: &BitMapRawDrawToConsoleSettings) -> Option<usize> {
@@ -385,24 +684,7 @@ impl BitMapRaw {
     /// Get a pointer to the function to set the fore/background color of the pixel
     /// 
     fn get_color_type(color: &RGBColor, settings: &BitMapRawDrawToConsoleSettings) -> (Option<colored::Color>, u32, bool) {
-        let allowed_colors = HashMap::from([
-            (0x00000000, colored::Color::Black), //Black
-            (0x00008000, colored::Color::Blue), //Dark blue
-            (0x00800000, colored::Color::Green), //Dark green
-            (0x00808000, colored::Color::Cyan), //Cark cyan
-            (0x80000000, colored::Color::Red), //Dark red
-            (0x80008000, colored::Color::Magenta), //Dark magenta
-            (0x80800000, colored::Color::Yellow), //Dark yellow
-            (0x80808000, colored::Color::White), //Dark grey
-            (0x0000FF00, colored::Color::BrightBlue), //Blue
-            (0x00FF0000, colored::Color::BrightGreen), //Green
-            (0x00FFFF00, colored::Color::BrightCyan), //Cyan
-            (0xFF000000, colored::Color::BrightRed), //Red
-            (0xFF00FF00, colored::Color::BrightMagenta), //Magenta
-            (0xFFFF0000, colored::Color::BrightYellow), //Yellow
-            (0xC0C0C000, colored::Color::BrightBlack), //Grey
-            (0xFFFFFF00, colored::Color::BrightWhite) //White
-        ]);
+        let allowed_colors = HashMap::from(Self::CONSOLE_COLORS);
 
         //Convert color to u32
         let color_u32 = color.to_u32(true);
@@ -485,3 +767,54 @@ impl BitMapRaw {
         }
     }
 }
+
+impl BitMapAnimation {
+    ///
+    /// Frames with a shorter delay than this are shown for DEFAULT_FRAME_DELAY instead, as browsers do
+    /// 
+    const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+
+    ///
+    /// How long to show a frame with a delay shorter than MIN_FRAME_DELAY
+    /// 
+    const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+    ///
+    /// Play the animation in the console, drawing each frame over the one before it and waiting for its delay.
+    /// The animation is repeated as many times as its loop count, so one which loops forever plays until the
+    /// process is stopped.
+    /// 
+    pub fn draw_to_console(&self, settings: &BitMapRawDrawToConsoleSettings) {
+        let adjusted_settings = BitMapRaw::draw_legend_to_console(settings);
+
+        let plays: Option<u32> = match self.loop_count {
+            None => Some(1),
+            Some(0) => None,
+            Some(loop_count) => Some(loop_count as u32 + 1)
+        };
+
+        let mut play: u32 = 0;
+        let mut previous_rows: Option<i32> = None;
+
+        while plays.is_none_or(|plays| play < plays) && !self.frames.is_empty() {
+            for frame in &self.frames {
+                //Move back up to the line above the previous frame, so this frame is drawn over it
+                if let Some(rows) = previous_rows.filter(|rows| *rows > 0) {
+                    print!("\x1b[{rows}A\r");
+                }
+
+                let rows = frame.bitmap.draw_rows_to_console(&adjusted_settings);
+                let _ = std::io::stdout().flush();
+
+                previous_rows = Some(rows as i32);
+
+                std::thread::sleep(match frame.delay {
+                    delay if delay < Self::MIN_FRAME_DELAY => Self::DEFAULT_FRAME_DELAY,
+                    delay => delay
+                });
+            }
+
+            play += 1;
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use super::*;
+
+impl BitMapRaw {
+    ///
+    /// Quadrant blocks, indexed by which of the upper left (1), upper right (2),
+    /// lower left (4) and lower right (8) quadrants are in the foreground
+    /// 
+    const QUADRANTS: [&str; 16] = [" ", "▘", "▝", "▀", "▖", "▌", "▞", "▛", "▗", "▚", "▐", "▜", "▄", "▙", "▟", "█"];
+
+    ///
+    /// The bit of each dot of a Braille pattern, by row and column
+    /// 
+    const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    ///
+    /// The first Braille pattern, with no dots
+    /// 
+    const BRAILLE_START: u32 = 0x2800;
+
+    ///
+    /// The first sextant block, with only the upper left sextant. The sextants follow in the order of their bits,
+    /// leaving out the left and right halves, which are ▌ and ▐.
+    /// 
+    const SEXTANT_START: u32 = 0x1FB00;
+
+    ///
+    /// Get the number of columns and rows of pixels held in each cell in the given render mode
+    /// 
+    pub(super) fn block_size(render_mode: ConsoleRenderMode) -> (usize, usize) {
+        match render_mode {
+            ConsoleRenderMode::Glyph => (1, 1),
+            ConsoleRenderMode::HalfBlock => (1, 2),
+            ConsoleRenderMode::Quadrant => (2, 2),
+            ConsoleRenderMode::Sextant => (2, 3),
+            ConsoleRenderMode::Braille | ConsoleRenderMode::Symbols => (2, 4)
+        }
+    }
+
+    ///
+    /// Get the lines of the image with each cell holding a block of pixels, split between a foreground and background color.
+    /// Transparent pixels show the background color, if given, and the rest of the cell is drawn in their average color.
+    /// 
+    pub(super) fn block_rows(&self, settings: &BitMapRawDrawToConsoleSettings) -> Vec<String> {
+        let width = self.info_header.width.unsigned_abs() as usize;
+        let height = self.info_header.height.unsigned_abs() as usize;
+
+        let (block_width, block_height) = Self::block_size(settings.render_mode);
+
+        let background = settings.background.map(|n| RGBColor {
+            alpha: 0xFF,
+            ..RGBColor::from_u32(n, true)
+        });
+
+        let cell_width = settings.pixel_width as usize;
+
+        (0..height.div_ceil(block_height))
+            .map(|row| (0..width.div_ceil(block_width))
+                .map(|column| {
+                    //The pixels of the block, from the top left, a row at a time
+                    let pixels: Vec<Option<RGBColor>> = (0..block_height)
+                        .flat_map(|y| (0..block_width).map(move |x| (x, y)))
+                        .map(|(x, y)| self.get_cell_color(column * block_width + x, row * block_height + y, &background, settings))
+                        .collect();
+
+                    let (mask, foreground, cell_background) = Self::split_block(&pixels, &background, settings);
+
+                    Self::color_cell(&Self::repeat_string(&Self::block_string(settings.render_mode, mask), cell_width), &foreground, &cell_background, settings)
+                })
+                .collect::<String>())
+            .collect()
+    }
+
+    ///
+    /// Split the given pixels of a block between two colors. Returns a mask with the bit of each pixel in the foreground set,
+    /// and the foreground and background colors.
+    /// 
+    fn split_block(pixels: &[Option<RGBColor>], background: &Option<RGBColor>, settings: &BitMapRawDrawToConsoleSettings) -> (u32, Option<RGBColor>, Option<RGBColor>) {
+        let opaque: Vec<(usize, &RGBColor)> = pixels.iter()
+            .enumerate()
+            .filter_map(|(index, pixel)| pixel.as_ref().map(|pixel| (index, pixel)))
+            .collect();
+
+        if opaque.is_empty() {
+            return (0, None, background.clone());
+        }
+
+        //Transparent pixels show the background, and the rest are drawn in one color
+        if opaque.len() < pixels.len() {
+            let mask = opaque.iter().fold(0, |mask, (index, _)| mask | (1 << index));
+            let colors: Vec<&RGBColor> = opaque.iter().map(|(_, pixel)| *pixel).collect();
+
+            return (mask, Some(Self::average_color(&colors)), background.clone());
+        }
+
+        let colors: Vec<&RGBColor> = opaque.iter().map(|(_, pixel)| *pixel).collect();
+        let count = colors.len();
+
+        let mask = match settings.cell_split {
+            ConsoleCellSplit::Threshold => {
+                let luminance = |color: &RGBColor| 0.2126 * color.red as f32 + 0.7152 * color.green as f32 + 0.0722 * color.blue as f32;
+                let threshold = colors.iter().map(|color| luminance(color)).sum::<f32>() / count as f32;
+
+                (0..count)
+                    .filter(|index| luminance(colors[*index]) >= threshold)
+                    .fold(0, |mask, index| mask | (1 << index))
+            },
+            ConsoleCellSplit::BestFit => {
+                //Splits and their inverses look the same, so only those with the last pixel in the foreground are tried.
+                //Of splits which fit as well, the one with the most pixels in the foreground is kept.
+                let last = 1 << (count - 1);
+
+                (0..last)
+                    .rev()
+                    .map(|mask| mask | last)
+                    .map(|mask| (mask, Self::split_error(&colors, mask, settings.algorithm)))
+                    .reduce(|(mask_a, error_a), (mask_b, error_b)| if error_a <= error_b { (mask_a, error_a) } else { (mask_b, error_b) })
+                    .map(|(mask, _)| mask)
+                    .unwrap_or(last)
+            }
+        };
+
+        let (foreground, background) = Self::split_colors(&colors, mask);
+
+        (mask, Some(foreground), Some(background))
+    }
+
+    ///
+    /// Get the average colors of the pixels in the foreground and background of the given mask.
+    /// If either is empty, it is given the color of the other.
+    /// 
+    fn split_colors(colors: &[&RGBColor], mask: u32) -> (RGBColor, RGBColor) {
+        let side = |in_foreground: bool| colors.iter()
+            .enumerate()
+            .filter(|(index, _)| (mask & (1 << index) != 0) == in_foreground)
+            .map(|(_, color)| *color)
+            .collect::<Vec<&RGBColor>>();
+
+        let (foreground, background) = (side(true), side(false));
+
+        match (foreground.is_empty(), background.is_empty()) {
+            (true, _) => (Self::average_color(&background), Self::average_color(&background)),
+            (_, true) => (Self::average_color(&foreground), Self::average_color(&foreground)),
+            _ => (Self::average_color(&foreground), Self::average_color(&background))
+        }
+    }
+
+    ///
+    /// Get the total distance of the given pixels from the average color of the side of the given mask they are on
+    /// 
+    fn split_error(colors: &[&RGBColor], mask: u32, algorithm: fn(&RGBColor, &RGBColor) -> f32) -> f32 {
+        let (foreground, background) = Self::split_colors(colors, mask);
+
+        colors.iter()
+            .enumerate()
+            .map(|(index, color)| match mask & (1 << index) {
+                0 => algorithm(color, &background),
+                _ => algorithm(color, &foreground)
+            })
+            .sum()
+    }
+
+    ///
+    /// Get the average of the given colors, which are opaque
+    /// 
+    fn average_color(colors: &[&RGBColor]) -> RGBColor {
+        let count = u32::max(1, colors.len() as u32);
+        let average = |channel: fn(&RGBColor) -> u8| (colors.iter().map(|color| channel(color) as u32).sum::<u32>() / count) as u8;
+
+        RGBColor {
+            red: average(|color| color.red),
+            green: average(|color| color.green),
+            blue: average(|color| color.blue),
+            alpha: 0xFF
+        }
+    }
+
+    ///
+    /// Get the character for a block of the given render mode, with the pixels of the given mask in the foreground
+    /// 
+    fn block_string(render_mode: ConsoleRenderMode, mask: u32) -> String {
+        if mask == 0 {
+            return String::from(Self::TRANSPARENT_STRING);
+        }
+
+        match render_mode {
+            ConsoleRenderMode::Quadrant => String::from(Self::QUADRANTS[mask as usize & 0xF]),
+            ConsoleRenderMode::Sextant => match mask {
+                0b010101 => String::from("▌"),
+                0b101010 => String::from("▐"),
+                0b111111 => String::from("█"),
+                //The left and right halves are skipped in the sextant block
+                mask => char::from_u32(Self::SEXTANT_START + mask - 1 - (mask > 0b010101) as u32 - (mask > 0b101010) as u32)
+                    .map(String::from)
+                    .unwrap_or_default()
+            },
+            ConsoleRenderMode::Braille => {
+                let dots = (0..8_usize)
+                    .filter(|index| mask & (1 << index) != 0)
+                    .fold(0, |dots, index| dots | Self::BRAILLE_DOTS[index / 2][index % 2]);
+
+                char::from_u32(Self::BRAILLE_START + dots)
+                    .map(String::from)
+                    .unwrap_or_default()
+            },
+            ConsoleRenderMode::Glyph | ConsoleRenderMode::HalfBlock | ConsoleRenderMode::Symbols => String::from(Self::UPPER_HALF_BLOCK)
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use super::*;
+
+///
+/// A character which can be drawn in a cell in the Symbols render mode, with the part of the cell it covers
+/// 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleSymbol {
+    ///
+    /// The character to draw
+    /// 
+    pub glyph: String,
+    ///
+    /// The parts of the cell the character covers, in a grid of 8x16, from the top left, a row at a time.
+    /// The lowest bit is the top left.
+    /// 
+    pub mask: u128
+}
+
+impl ConsoleSymbol {
+    ///
+    /// The number of columns in the grid of the mask
+    /// 
+    pub const MASK_WIDTH: usize = 8;
+
+    ///
+    /// The number of rows in the grid of the mask
+    /// 
+    pub const MASK_HEIGHT: usize = 16;
+
+    ///
+    /// The width of a light line in box drawing characters, as a part of the width of the cell.
+    /// Cells are about twice as tall as they are wide, so horizontal lines are half as thick, as a part of the height.
+    /// Lines are as thick as the grid of the mask allows, rather than as thin as most fonts draw them.
+    /// 
+    const LINE_WIDTH: f32 = 0.25;
+
+    ///
+    /// Create a symbol for the given character, covering the parts of the cell for which the given function is true.
+    /// The function is given the column and row of the center of each part of the grid, from 0 to 1, from the top left.
+    /// 
+    pub fn new(glyph: &str, covers: fn(f32, f32) -> bool) -> Self {
+        let mask = (0..Self::MASK_HEIGHT)
+            .flat_map(|y| (0..Self::MASK_WIDTH).map(move |x| (x, y)))
+            .filter(|(x, y)| covers((*x as f32 + 0.5) / Self::MASK_WIDTH as f32, (*y as f32 + 0.5) / Self::MASK_HEIGHT as f32))
+            .fold(0_u128, |mask, (x, y)| mask | (1 << (y * Self::MASK_WIDTH + x)));
+
+        ConsoleSymbol {
+            glyph: String::from(glyph),
+            mask
+        }
+    }
+
+    ///
+    /// The full block, and the half, eighth and quadrant blocks
+    /// 
+    pub fn blocks() -> Vec<Self> {
+        vec![
+            Self::new("█", |_, _| true),
+            Self::new("▀", |_, y| y < 0.5),
+            Self::new("▄", |_, y| y >= 0.5),
+            Self::new("▌", |x, _| x < 0.5),
+            Self::new("▐", |x, _| x >= 0.5),
+            Self::new("▔", |_, y| y < 0.125),
+            Self::new("▁", |_, y| y >= 0.875),
+            Self::new("▂", |_, y| y >= 0.75),
+            Self::new("▃", |_, y| y >= 0.625),
+            Self::new("▅", |_, y| y >= 0.375),
+            Self::new("▆", |_, y| y >= 0.25),
+            Self::new("▇", |_, y| y >= 0.125),
+            Self::new("▏", |x, _| x < 0.125),
+            Self::new("▎", |x, _| x < 0.25),
+            Self::new("▍", |x, _| x < 0.375),
+            Self::new("▋", |x, _| x < 0.625),
+            Self::new("▊", |x, _| x < 0.75),
+            Self::new("▉", |x, _| x < 0.875),
+            Self::new("▕", |x, _| x >= 0.875),
+            Self::new("▘", |x, y| x < 0.5 && y < 0.5),
+            Self::new("▝", |x, y| x >= 0.5 && y < 0.5),
+            Self::new("▖", |x, y| x < 0.5 && y >= 0.5),
+            Self::new("▗", |x, y| x >= 0.5 && y >= 0.5),
+            Self::new("▚", |x, y| (x < 0.5) == (y < 0.5)),
+            Self::new("▞", |x, y| (x < 0.5) != (y < 0.5)),
+            Self::new("▛", |x, y| x < 0.5 || y < 0.5),
+            Self::new("▜", |x, y| x >= 0.5 || y < 0.5),
+            Self::new("▙", |x, y| x < 0.5 || y >= 0.5),
+            Self::new("▟", |x, y| x >= 0.5 || y >= 0.5)
+        ]
+    }
+
+    ///
+    /// The triangles which fill half of the cell, split along a diagonal
+    /// 
+    pub fn wedges() -> Vec<Self> {
+        vec![
+            Self::new("◢", |x, y| x + y >= 1.0),
+            Self::new("◣", |x, y| y >= x),
+            Self::new("◤", |x, y| x + y < 1.0),
+            Self::new("◥", |x, y| y < x)
+        ]
+    }
+
+    ///
+    /// Light and heavy lines, corners, tees and crosses, and diagonals
+    /// 
+    pub fn box_drawing() -> Vec<Self> {
+        vec![
+            Self::new("─", |_, y| Self::is_horizontal(y, 1.0)),
+            Self::new("│", |x, _| Self::is_vertical(x, 1.0)),
+            Self::new("━", |_, y| Self::is_horizontal(y, 2.0)),
+            Self::new("┃", |x, _| Self::is_vertical(x, 2.0)),
+            Self::new("┼", |x, y| Self::is_horizontal(y, 1.0) || Self::is_vertical(x, 1.0)),
+            Self::new("╋", |x, y| Self::is_horizontal(y, 2.0) || Self::is_vertical(x, 2.0)),
+            Self::new("┌", |x, y| (Self::is_horizontal(y, 1.0) && x >= 0.5) || (Self::is_vertical(x, 1.0) && y >= 0.5)),
+            Self::new("┐", |x, y| (Self::is_horizontal(y, 1.0) && x < 0.5) || (Self::is_vertical(x, 1.0) && y >= 0.5)),
+            Self::new("└", |x, y| (Self::is_horizontal(y, 1.0) && x >= 0.5) || (Self::is_vertical(x, 1.0) && y < 0.5)),
+            Self::new("┘", |x, y| (Self::is_horizontal(y, 1.0) && x < 0.5) || (Self::is_vertical(x, 1.0) && y < 0.5)),
+            Self::new("├", |x, y| (Self::is_horizontal(y, 1.0) && x >= 0.5) || Self::is_vertical(x, 1.0)),
+            Self::new("┤", |x, y| (Self::is_horizontal(y, 1.0) && x < 0.5) || Self::is_vertical(x, 1.0)),
+            Self::new("┬", |x, y| Self::is_horizontal(y, 1.0) || (Self::is_vertical(x, 1.0) && y >= 0.5)),
+            Self::new("┴", |x, y| Self::is_horizontal(y, 1.0) || (Self::is_vertical(x, 1.0) && y < 0.5)),
+            Self::new("╱", |x, y| Self::is_diagonal(x, 1.0 - y)),
+            Self::new("╲", Self::is_diagonal),
+            Self::new("╳", |x, y| Self::is_diagonal(x, 1.0 - y) || Self::is_diagonal(x, y))
+        ]
+    }
+
+    ///
+    /// Whether the given row is on a horizontal line through the middle of the cell, of the given weight
+    /// 
+    fn is_horizontal(y: f32, weight: f32) -> bool {
+        (y - 0.5).abs() < Self::LINE_WIDTH * weight / 4.0
+    }
+
+    ///
+    /// Whether the given column is on a vertical line through the middle of the cell, of the given weight
+    /// 
+    fn is_vertical(x: f32, weight: f32) -> bool {
+        (x - 0.5).abs() < Self::LINE_WIDTH * weight / 2.0
+    }
+
+    ///
+    /// Whether the given column is on a light diagonal line, which is at the given column in the same row
+    /// 
+    fn is_diagonal(x: f32, line: f32) -> bool {
+        (x - line).abs() < Self::LINE_WIDTH / 2.0
+    }
+}
+
+impl BitMapRaw {
+    ///
+    /// Get the lines of the image with each cell holding 2x4 pixels, drawn as the symbol and pair of colors
+    /// which are closest to the pixels by the distance algorithm. Each pixel is compared to the foreground and
+    /// background colors mixed by how much of it the symbol covers. If not drawing in truecolor, the colors are
+    /// the closest console colors.
+    /// 
+    /// Cells with transparent pixels show the background color, if given, under the symbol which best covers
+    /// the rest of the pixels, drawn in their average color.
+    /// 
+    pub(super) fn symbol_rows(&self, settings: &BitMapRawDrawToConsoleSettings) -> Vec<String> {
+        let width = self.info_header.width.unsigned_abs() as usize;
+        let height = self.info_header.height.unsigned_abs() as usize;
+
+        let (block_width, block_height) = Self::block_size(settings.render_mode);
+
+        let background = settings.background.map(|n| RGBColor {
+            alpha: 0xFF,
+            ..RGBColor::from_u32(n, true)
+        });
+
+        let console_colors: Vec<RGBColor> = Self::CONSOLE_COLORS.iter()
+            .map(|(color, _)| RGBColor::from_u32(*color, true))
+            .collect();
+
+        //How much of each pixel each symbol covers, after a space, which covers none of the cell
+        let symbols: Vec<(&str, Vec<f32>)> = std::iter::once((Self::TRANSPARENT_STRING, vec![0_f32; block_width * block_height]))
+            .chain(settings.symbols.iter().map(|symbol| (symbol.glyph.as_str(), Self::symbol_coverage(symbol, block_width, block_height))))
+            .collect();
+
+        let cell_width = settings.pixel_width as usize;
+
+        (0..height.div_ceil(block_height))
+            .map(|row| (0..width.div_ceil(block_width))
+                .map(|column| {
+                    //The pixels of the block, from the top left, a row at a time
+                    let pixels: Vec<Option<RGBColor>> = (0..block_height)
+                        .flat_map(|y| (0..block_width).map(move |x| (x, y)))
+                        .map(|(x, y)| self.get_cell_color(column * block_width + x, row * block_height + y, &background, settings))
+                        .collect();
+
+                    let (glyph, foreground, cell_background) = match pixels.iter().all(|pixel| pixel.is_some()) {
+                        true => Self::closest_symbol(&pixels.into_iter().flatten().collect::<Vec<RGBColor>>(), &symbols, &console_colors, settings),
+                        false => Self::covering_symbol(&pixels, &symbols, &background)
+                    };
+
+                    Self::color_cell(&Self::repeat_string(glyph, cell_width), &foreground, &cell_background, settings)
+                })
+                .collect::<String>())
+            .collect()
+    }
+
+    ///
+    /// Get how much of each pixel of a block of the given size the given symbol covers, from 0 to 1,
+    /// from the top left, a row at a time
+    /// 
+    fn symbol_coverage(symbol: &ConsoleSymbol, block_width: usize, block_height: usize) -> Vec<f32> {
+        let (part_width, part_height) = (ConsoleSymbol::MASK_WIDTH / block_width, ConsoleSymbol::MASK_HEIGHT / block_height);
+
+        (0..block_height)
+            .flat_map(|y| (0..block_width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let covered = (0..part_height)
+                    .flat_map(|j| (0..part_width).map(move |i| (x * part_width + i, y * part_height + j)))
+                    .filter(|(i, j)| symbol.mask & (1 << (j * ConsoleSymbol::MASK_WIDTH + i)) != 0)
+                    .count();
+
+                covered as f32 / (part_width * part_height) as f32
+            })
+            .collect()
+    }
+
+    ///
+    /// Get the symbol, and foreground and background colors, closest to the given opaque pixels. The colors of each symbol
+    /// are the averages of the pixels, weighted by how much of them it covers and leaves uncovered.
+    /// 
+    fn closest_symbol<'a>(pixels: &[RGBColor], symbols: &[(&'a str, Vec<f32>)], console_colors: &[RGBColor], settings: &BitMapRawDrawToConsoleSettings) -> (&'a str, Option<RGBColor>, Option<RGBColor>) {
+        let closest = symbols.iter()
+            .map(|(glyph, coverage)| {
+                let foreground = Self::weighted_average(pixels, coverage.iter().copied());
+                let background = Self::weighted_average(pixels, coverage.iter().map(|covered| 1_f32 - covered));
+
+                //The colors are only as close as the console colors can get
+                let (foreground, background) = match settings.use_truecolor {
+                    true => (foreground, background),
+                    false => (
+                        foreground.and_then(|color| color.get_closest_in_set(console_colors, settings.algorithm).cloned()),
+                        background.and_then(|color| color.get_closest_in_set(console_colors, settings.algorithm).cloned())
+                    )
+                };
+
+                let error: f32 = pixels.iter()
+                    .zip(coverage)
+                    .map(|(pixel, covered)| (settings.algorithm)(pixel, &Self::mix_colors(&foreground, &background, *covered)))
+                    .sum();
+
+                (*glyph, foreground, background, error)
+            })
+            .reduce(|a, b| if a.3 <= b.3 { a } else { b });
+
+        match closest {
+            Some((glyph, foreground, background, _)) => (glyph, foreground, background),
+            None => (Self::TRANSPARENT_STRING, None, None)
+        }
+    }
+
+    ///
+    /// Get the symbol which covers the most of the opaque pixels, and the least of the transparent ones, of the given pixels.
+    /// It is drawn in the average color of the opaque pixels, over the given background.
+    /// 
+    fn covering_symbol<'a>(pixels: &[Option<RGBColor>], symbols: &[(&'a str, Vec<f32>)], background: &Option<RGBColor>) -> (&'a str, Option<RGBColor>, Option<RGBColor>) {
+        let opaque: Vec<RGBColor> = pixels.iter().flatten().cloned().collect();
+
+        if opaque.is_empty() {
+            return (Self::TRANSPARENT_STRING, None, background.clone());
+        }
+
+        let glyph = symbols.iter()
+            .map(|(glyph, coverage)| {
+                let error: f32 = pixels.iter()
+                    .zip(coverage)
+                    .map(|(pixel, covered)| match pixel {
+                        Some(_) => 1_f32 - covered,
+                        None => *covered
+                    })
+                    .sum();
+
+                (*glyph, error)
+            })
+            .reduce(|a, b| if a.1 <= b.1 { a } else { b })
+            .map(|(glyph, _)| glyph)
+            .unwrap_or(Self::TRANSPARENT_STRING);
+
+        (glyph, Self::weighted_average(&opaque, std::iter::repeat(1_f32)), background.clone())
+    }
+
+    ///
+    /// Get the average of the given colors, weighted by the given weights, or None if they add to 0
+    /// 
+    fn weighted_average(colors: &[RGBColor], weights: impl Iterator<Item = f32>) -> Option<RGBColor> {
+        let (red, green, blue, total) = colors.iter()
+            .zip(weights)
+            .fold((0_f32, 0_f32, 0_f32, 0_f32), |(red, green, blue, total), (color, weight)| (
+                red + color.red as f32 * weight,
+                green + color.green as f32 * weight,
+                blue + color.blue as f32 * weight,
+                total + weight
+            ));
+
+        (total > 0_f32).then(|| RGBColor {
+            red: (red / total).round() as u8,
+            green: (green / total).round() as u8,
+            blue: (blue / total).round() as u8,
+            alpha: 0xFF
+        })
+    }
+
+    ///
+    /// Mix the given foreground and background colors, with the given part of the foreground. If one is not given, the other is used.
+    /// 
+    fn mix_colors(foreground: &Option<RGBColor>, background: &Option<RGBColor>, covered: f32) -> RGBColor {
+        match (foreground, background) {
+            (Some(foreground), Some(background)) => {
+                let mix = |value: u8, under: u8| (value as f32 * covered + under as f32 * (1_f32 - covered)).round() as u8;
+
+                RGBColor {
+                    red: mix(foreground.red, background.red),
+                    green: mix(foreground.green, background.green),
+                    blue: mix(foreground.blue, background.blue),
+                    alpha: 0xFF
+                }
+            },
+            (Some(color), None) | (None, Some(color)) => color.clone(),
+            (None, None) => RGBColor {
+                red: 0,
+                green: 0,
+                blue: 0,
+                alpha: 0xFF
+            }
+        }
+    }
+}
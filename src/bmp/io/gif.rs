@@ -0,0 +1,245 @@
+mod read;
+
+use super::super::*;
+
+/// 
+/// What happens to the area of a GIF frame once it has been shown, before the next frame is drawn
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GifDisposal {
+    /// 
+    /// No disposal was given. Treated the same as Keep.
+    /// 
+    #[default]
+    Unspecified,
+    /// 
+    /// The frame is left in place, and the next frame is drawn over it
+    /// 
+    Keep,
+    /// 
+    /// The area of the frame is cleared to transparent
+    /// 
+    RestoreBackground,
+    /// 
+    /// The area of the frame is restored to what it was before the frame was drawn
+    /// 
+    RestorePrevious
+}
+
+impl GifDisposal {
+    /// 
+    /// Get the disposal method with the given value in a graphic control extension.
+    /// The reserved values 4 to 7 are treated as unspecified.
+    /// 
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Keep,
+            2 => Self::RestoreBackground,
+            3 => Self::RestorePrevious,
+            _ => Self::Unspecified
+        }
+    }
+}
+
+impl BitMapRaw {
+    /// 
+    /// The 3 bytes every GIF file begins with, before the version
+    /// 
+    pub const GIF_SIGNATURE: [u8; 3] = *b"GIF";
+
+    /// 
+    /// The versions of GIF which can be read
+    /// 
+    const GIF_VERSIONS: [&'static [u8; 3]; 2] = [b"87a", b"89a"];
+
+    /// 
+    /// The size of the header and logical screen descriptor
+    /// 
+    const GIF_HEADER_SIZE: usize = 13;
+
+    /// 
+    /// The size of an image descriptor, after its separator
+    /// 
+    const GIF_IMAGE_DESCRIPTOR_SIZE: usize = 9;
+
+    const GIF_EXTENSION_INTRODUCER: u8 = 0x21;
+    const GIF_IMAGE_SEPARATOR: u8 = 0x2C;
+    const GIF_TRAILER: u8 = 0x3B;
+
+    const GIF_GRAPHIC_CONTROL_LABEL: u8 = 0xF9;
+    const GIF_APPLICATION_LABEL: u8 = 0xFF;
+
+    /// 
+    /// The application identifier and authentication code of the extension giving the loop count
+    /// 
+    const GIF_NETSCAPE_APPLICATION: &'static [u8; 11] = b"NETSCAPE2.0";
+
+    /// 
+    /// The most codes an LZW code table can hold, which keeps codes to 12 bits
+    /// 
+    const GIF_MAX_LZW_CODES: usize = 4096;
+
+    /// 
+    /// Read a sequence of data sub-blocks starting at the given offset, each a length byte followed by
+    /// that many bytes, up to the empty block which ends them. Returns the data of every block, the
+    /// offset after the sequence, and whether the sequence was complete before the end of the buffer.
+    /// 
+    fn read_gif_sub_blocks(buffer: &[u8], offset: usize) -> (Vec<u8>, usize, bool) {
+        let mut data: Vec<u8> = Vec::new();
+        let mut offset = offset;
+
+        loop {
+            let length = match buffer.get(offset) {
+                Some(length) => *length as usize,
+                None => return (data, offset, false)
+            };
+
+            offset += 1;
+
+            if length == 0 {
+                return (data, offset, true);
+            }
+
+            match buffer.get(offset..(offset + length)) {
+                Some(block) => data.extend_from_slice(block),
+                None => {
+                    data.extend_from_slice(&buffer[offset..]);
+                    return (data, buffer.len(), false);
+                }
+            };
+
+            offset += length;
+        }
+    }
+
+    /// 
+    /// Decompress GIF LZW data into at most the given number of color indices. Codes are read from the
+    /// least significant bit of each byte, starting one bit wider than the minimum code size and growing
+    /// as the code table fills, up to 12 bits. Once the table is full, codes are read at 12 bits until
+    /// the next clear code.
+    /// 
+    /// Returns the indices decoded, along with an error if an invalid code, or an index past the end of the color
+    /// table of the given size, was found before the end.
+    /// 
+    fn decode_gif_lzw(data: &[u8], min_code_size: u8, pixel_count: usize, color_count: usize) -> (Vec<u8>, Option<BmpError>) {
+        let clear_code = 1_usize << min_code_size;
+        let end_code = clear_code + 1;
+
+        //For each code, the code before its last index, its last index, its first index, and its length
+        let mut prefixes: Vec<u16> = vec![0; Self::GIF_MAX_LZW_CODES];
+        let mut suffixes: Vec<u8> = (0..Self::GIF_MAX_LZW_CODES).map(|code| code as u8).collect();
+        let mut firsts: Vec<u8> = suffixes.clone();
+        let mut lengths: Vec<usize> = vec![1; Self::GIF_MAX_LZW_CODES];
+
+        let mut code_size = min_code_size as u32 + 1;
+        let mut next_code = end_code + 1;
+        let mut previous: Option<usize> = None;
+
+        let mut indices: Vec<u8> = Vec::with_capacity(pixel_count);
+
+        let mut bits: u32 = 0;
+        let mut bit_count: u32 = 0;
+        let mut bytes = data.iter();
+
+        while indices.len() < pixel_count {
+            //Fill the bit buffer with enough bits for the next code
+            while bit_count < code_size {
+                match bytes.next() {
+                    Some(byte) => {
+                        bits |= (*byte as u32) << bit_count;
+                        bit_count += 8;
+                    },
+                    None => return (indices, None)
+                }
+            }
+
+            let code = (bits & ((1 << code_size) - 1)) as usize;
+            bits >>= code_size;
+            bit_count -= code_size;
+
+            if code == clear_code {
+                code_size = min_code_size as u32 + 1;
+                next_code = end_code + 1;
+                previous = None;
+                continue;
+            }
+
+            if code == end_code {
+                break;
+            }
+
+            //Codes below the clear code are single indices, which must fit in a byte and be within the color table
+            if code < clear_code && code >= usize::min(color_count, 256) {
+                return (indices, Some(BmpError::CorruptData(format!("LZW code {code} is an index past the end of the color table, which has {color_count} colors."))));
+            }
+
+            let previous_code = match previous {
+                //The first code after a clear must be a single index
+                None if code < clear_code => {
+                    indices.push(code as u8);
+                    previous = Some(code);
+                    continue;
+                },
+                None => return (indices, Some(BmpError::CorruptData(format!("LZW code {code} came before any index was decoded.")))),
+                Some(previous_code) => previous_code
+            };
+
+            //A code one past the end of the table is the previous code, followed by its own first index
+            let first = match code.cmp(&next_code) {
+                std::cmp::Ordering::Less => firsts[code],
+                std::cmp::Ordering::Equal => firsts[previous_code],
+                std::cmp::Ordering::Greater => return (indices, Some(BmpError::CorruptData(format!("LZW code {code} is past the end of the code table, which has {next_code} codes."))))
+            };
+
+            if next_code < Self::GIF_MAX_LZW_CODES {
+                prefixes[next_code] = previous_code as u16;
+                suffixes[next_code] = first;
+                firsts[next_code] = firsts[previous_code];
+                lengths[next_code] = lengths[previous_code] + 1;
+                next_code += 1;
+
+                if next_code == (1 << code_size) && code_size < 12 {
+                    code_size += 1;
+                }
+            }
+
+            //Write the indices of the code from the last back to the first, leaving out any past the end of the image
+            let length = lengths[code];
+            let start = indices.len();
+            indices.resize(start + length, 0);
+
+            let mut current = code;
+
+            for position in (start..(start + length)).rev() {
+                indices[position] = suffixes[current];
+                current = prefixes[current] as usize;
+            }
+
+            indices.truncate(pixel_count);
+            previous = Some(code);
+        }
+
+        (indices, None)
+    }
+
+    /// 
+    /// Get the row of the image which the given row of interlaced pixel data belongs to. Interlaced
+    /// images store every 8th row from 0, then every 8th row from 4, every 4th row from 2, and
+    /// every 2nd row from 1.
+    /// 
+    fn gif_interlaced_row(row: usize, height: usize) -> usize {
+        let mut row = row;
+
+        for (start, step) in [(0, 8), (4, 8), (2, 4), (1, 2)] {
+            let rows_in_pass = height.saturating_sub(start).div_ceil(step);
+
+            if row < rows_in_pass {
+                return start + (row * step);
+            }
+
+            row -= rows_in_pass;
+        }
+
+        row
+    }
+}
@@ -0,0 +1,358 @@
+use std::io::BufReader;
+use std::fs::File;
+use std::time::Duration;
+use super::*;
+
+impl BitMapRaw {
+    /// 
+    /// Read the first frame of a GIF image from the file at the given path
+    /// 
+    pub fn read_gif_from_file(path: &str) -> Result<Self, BmpError> {
+        Self::read_gif_from_file_with_settings(path, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read the first frame of a GIF image from the file at the given path, with the given settings
+    /// 
+    pub fn read_gif_from_file_with_settings(path: &str, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let buffer = Self::read_to_buffer(&mut BufReader::new(File::open(path)?), settings)?;
+
+        Self::from_gif_bytes_with_settings(&buffer, settings)
+    }
+
+    /// 
+    /// Read the first frame of a GIF image from the given bytes
+    /// 
+    pub fn from_gif_bytes(buffer: &[u8]) -> Result<Self, BmpError> {
+        Self::from_gif_bytes_with_settings(buffer, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read the first frame of a GIF image from the given bytes, with the given settings.
+    /// The frame is drawn onto a transparent image the size of the logical screen, and is
+    /// 32 bpp if any of it is transparent, and 24 bpp otherwise. The rest of the file is not read.
+    /// 
+    pub fn from_gif_bytes_with_settings(buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        Self::decode_gif(buffer, settings, true)?
+            .into_first_frame()
+            .ok_or(BmpError::MalformedHeader(String::from("The GIF has no images.")))
+    }
+
+    /// 
+    /// Decode the frames of a GIF image, or only the first if first_frame_only is set.
+    /// 
+    /// Each frame is drawn over the frames before it on an image the size of the logical screen, which starts
+    /// out transparent. Pixels of a frame with the transparent index are left as they were. Once a frame has
+    /// been shown, its disposal method decides what the next frame is drawn over: the frame itself, the frame
+    /// with its area cleared to transparent, or the image from before the frame was drawn.
+    /// 
+    /// In lenient mode, pixels missing from truncated or corrupt image data, including indices outside of the
+    /// color table, are given the fill color, and a file which ends early keeps the frames read before the end.
+    /// 
+    fn decode_gif(buffer: &[u8], settings: &BitMapRawReadSettings, first_frame_only: bool) -> Result<BitMapAnimation, BmpError> {
+        let is_lenient = settings.mode == DecodeMode::Lenient;
+
+        if buffer.len() < Self::GIF_HEADER_SIZE {
+            return Err(BmpError::Truncated {
+                needed: Self::GIF_HEADER_SIZE,
+                available: buffer.len()
+            });
+        }
+
+        if !buffer.starts_with(&Self::GIF_SIGNATURE) {
+            return Err(BmpError::BadSignature(u16::from_le_bytes([buffer[0], buffer[1]])));
+        }
+
+        if !Self::GIF_VERSIONS.iter().any(|version| buffer[3..6] == version[..]) {
+            return Err(BmpError::MalformedHeader(format!("{} is not a supported GIF version.", String::from_utf8_lossy(&buffer[3..6]))));
+        }
+
+        let read_u16 = |offset: usize| u16::from_le_bytes([buffer[offset], buffer[offset + 1]]) as usize;
+
+        let width = read_u16(6);
+        let height = read_u16(8);
+        let screen_flags = buffer[10];
+
+        if width == 0 || height == 0 {
+            return Err(BmpError::InvalidDimensions {
+                width: width as i32,
+                height: height as i32
+            });
+        }
+
+        let pixel_count = width * height;
+        let frame_bytes = (pixel_count as u64).saturating_mul(std::mem::size_of::<RGBColor>() as u64);
+
+        DecodeLimits::check(DecodeLimit::Width, width as u64, settings.limits.max_width as u64)?;
+        DecodeLimits::check(DecodeLimit::Height, height as u64, settings.limits.max_height as u64)?;
+        DecodeLimits::check(DecodeLimit::Pixels, pixel_count as u64, settings.limits.max_pixels)?;
+        DecodeLimits::check(DecodeLimit::AllocBytes, frame_bytes, settings.limits.max_alloc_bytes)?;
+
+        //
+        // Read a color table at the given offset, if the flags of its descriptor say there is one. The size
+        // is in the low 3 bits of the flags, as one less than the power of 2 of the number of colors.
+        //
+        let read_color_table = |offset: usize, flags: u8| -> Result<(Vec<RGBColor>, usize), BmpError> {
+            if flags & 0x80 == 0 {
+                return Ok((Vec::new(), offset));
+            }
+
+            let end = offset + (3 << ((flags & 0x07) + 1));
+
+            match buffer.get(offset..end) {
+                Some(entries) => Ok((entries
+                    .chunks_exact(3)
+                    .map(|entry| RGBColor { red: entry[0], green: entry[1], blue: entry[2], alpha: 0xFF })
+                    .collect(), end)),
+                None => Err(BmpError::Truncated {
+                    needed: end,
+                    available: buffer.len()
+                })
+            }
+        };
+
+        let (global_color_table, mut offset) = read_color_table(Self::GIF_HEADER_SIZE, screen_flags)?;
+
+        let transparent = RGBColor {
+            red: 0,
+            green: 0,
+            blue: 0,
+            alpha: 0
+        };
+
+        let mut canvas: Vec<RGBColor> = vec![transparent.clone(); pixel_count];
+        let mut frames: Vec<BitMapFrame> = Vec::new();
+        let mut loop_count: Option<u16> = None;
+        let mut warnings: Vec<DecodeWarning> = Vec::new();
+
+        //The disposal, delay and transparent index from the graphic control extension of the next image
+        let mut disposal = GifDisposal::Unspecified;
+        let mut delay: u16 = 0;
+        let mut transparent_index: Option<u8> = None;
+
+        let truncated_at = |offset: usize| BmpError::Truncated {
+            needed: offset + 1,
+            available: buffer.len()
+        };
+
+        //Read blocks until the trailer, or the first problem
+        let error: Option<BmpError> = loop {
+            let introducer = match buffer.get(offset) {
+                Some(introducer) => *introducer,
+                None => break Some(truncated_at(offset))
+            };
+
+            offset += 1;
+
+            match introducer {
+                Self::GIF_TRAILER => break None,
+                Self::GIF_EXTENSION_INTRODUCER => {
+                    let label = match buffer.get(offset) {
+                        Some(label) => *label,
+                        None => break Some(truncated_at(offset))
+                    };
+
+                    let (data, next, complete) = Self::read_gif_sub_blocks(buffer, offset + 1);
+
+                    if !complete {
+                        break Some(truncated_at(next));
+                    }
+
+                    offset = next;
+
+                    match label {
+                        Self::GIF_GRAPHIC_CONTROL_LABEL if data.len() >= 4 => {
+                            disposal = GifDisposal::from_u8((data[0] >> 2) & 0x07);
+                            delay = u16::from_le_bytes([data[1], data[2]]);
+                            transparent_index = (data[0] & 0x01 != 0).then_some(data[3]);
+                        },
+                        //The loop count is the second and third bytes of a sub-block starting with 1
+                        Self::GIF_APPLICATION_LABEL if data.len() >= 14 && data.starts_with(Self::GIF_NETSCAPE_APPLICATION) && data[11] == 1 => {
+                            loop_count = Some(u16::from_le_bytes([data[12], data[13]]));
+                        },
+                        _ => {}
+                    };
+                },
+                Self::GIF_IMAGE_SEPARATOR => {
+                    if buffer.len() < offset + Self::GIF_IMAGE_DESCRIPTOR_SIZE + 1 {
+                        break Some(truncated_at(offset + Self::GIF_IMAGE_DESCRIPTOR_SIZE));
+                    }
+
+                    let left = read_u16(offset);
+                    let top = read_u16(offset + 2);
+                    let frame_width = read_u16(offset + 4);
+                    let frame_height = read_u16(offset + 6);
+                    let image_flags = buffer[offset + 8];
+                    let is_interlaced = image_flags & 0x40 != 0;
+
+                    let (local_color_table, next) = match read_color_table(offset + Self::GIF_IMAGE_DESCRIPTOR_SIZE, image_flags) {
+                        Ok(table) => table,
+                        Err(error) => break Some(error)
+                    };
+
+                    offset = next;
+
+                    let color_table = match image_flags & 0x80 {
+                        0 => &global_color_table,
+                        _ => &local_color_table
+                    };
+
+                    let min_code_size = match buffer.get(offset) {
+                        Some(min_code_size) => *min_code_size,
+                        None => break Some(truncated_at(offset))
+                    };
+
+                    if !(1..=11).contains(&min_code_size) {
+                        break Some(BmpError::CorruptData(format!("{min_code_size} is not a valid LZW minimum code size.")));
+                    }
+
+                    //
+                    // The indices of the frame are decoded before it is clipped to the logical screen, so the frame
+                    // is checked against the limits as well, and its indices count towards the allocation.
+                    //
+                    let frame_pixel_count = frame_width * frame_height;
+
+                    DecodeLimits::check(DecodeLimit::Width, frame_width as u64, settings.limits.max_width as u64)?;
+                    DecodeLimits::check(DecodeLimit::Height, frame_height as u64, settings.limits.max_height as u64)?;
+                    DecodeLimits::check(DecodeLimit::Pixels, frame_pixel_count as u64, settings.limits.max_pixels)?;
+                    DecodeLimits::check(DecodeLimit::AllocBytes, frame_bytes.saturating_mul(frames.len() as u64 + 1).saturating_add(frame_pixel_count as u64), settings.limits.max_alloc_bytes)?;
+
+                    let (data, next, complete) = Self::read_gif_sub_blocks(buffer, offset + 1);
+                    offset = next;
+
+                    let (indices, lzw_error) = Self::decode_gif_lzw(&data, min_code_size, frame_pixel_count, color_table.len());
+
+                    if !is_lenient {
+                        if !complete {
+                            break Some(truncated_at(offset));
+                        }
+
+                        if let Some(error) = lzw_error {
+                            break Some(error);
+                        }
+                    }
+
+                    let missing_pixels = frame_pixel_count - indices.len();
+
+                    if missing_pixels > 0 && !is_lenient {
+                        break Some(BmpError::CorruptData(format!("The image data of a frame ends {missing_pixels} pixels early.")));
+                    }
+
+                    let previous_canvas = match disposal {
+                        GifDisposal::RestorePrevious => Some(canvas.clone()),
+                        _ => None
+                    };
+
+                    //Draw the frame over the canvas, leaving out anything outside of the logical screen
+                    for row in 0..frame_height {
+                        let y = top + match is_interlaced {
+                            true => Self::gif_interlaced_row(row, frame_height),
+                            false => row
+                        };
+
+                        for column in (0..frame_width).filter(|column| y < height && left + column < width) {
+                            let color = match indices.get((row * frame_width) + column) {
+                                None => settings.fill_color.clone(),
+                                Some(index) if Some(*index) == transparent_index => continue,
+                                //The LZW decoder only gives indices within the color table
+                                Some(index) => color_table[*index as usize].clone()
+                            };
+
+                            canvas[(y * width) + left + column] = color;
+                        }
+                    }
+
+                    if missing_pixels > 0 {
+                        warnings.push(DecodeWarning::TruncatedPixelData { missing_pixels });
+                    }
+
+                    let bits_per_pixel = match canvas.iter().any(|color| color.alpha != 0xFF) {
+                        true => 32,
+                        false => 24
+                    };
+
+                    let bitmap = Self::from_pixels(width as u32, height as u32, bits_per_pixel, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(canvas.clone()))?;
+
+                    frames.push(BitMapFrame {
+                        bitmap,
+                        delay: Duration::from_millis(delay as u64 * 10)
+                    });
+
+                    if first_frame_only || !complete {
+                        break None;
+                    }
+
+                    //Dispose of the frame before the next one is drawn
+                    match (disposal, previous_canvas) {
+                        (GifDisposal::RestoreBackground, _) => {
+                            for y in top..usize::min(top + frame_height, height) {
+                                for x in left..usize::min(left + frame_width, width) {
+                                    canvas[(y * width) + x] = transparent.clone();
+                                }
+                            }
+                        },
+                        (GifDisposal::RestorePrevious, Some(previous_canvas)) => canvas = previous_canvas,
+                        _ => {}
+                    };
+
+                    //A graphic control extension only applies to the image after it
+                    disposal = GifDisposal::Unspecified;
+                    delay = 0;
+                    transparent_index = None;
+                },
+                _ => break Some(BmpError::MalformedHeader(format!("{introducer:#04X} is not a valid GIF block.")))
+            };
+        };
+
+        //In lenient mode, a file which ends early, or has a bad block, keeps the frames before it
+        if let Some(error) = error {
+            if !is_lenient || frames.is_empty() {
+                return Err(error);
+            }
+        }
+
+        if frames.is_empty() {
+            return Err(BmpError::MalformedHeader(String::from("The GIF has no images.")));
+        }
+
+        Ok(BitMapAnimation {
+            frames,
+            loop_count,
+            warnings
+        })
+    }
+}
+
+impl BitMapAnimation {
+    /// 
+    /// Read every frame of a GIF image from the file at the given path
+    /// 
+    pub fn read_gif_from_file(path: &str) -> Result<Self, BmpError> {
+        Self::read_gif_from_file_with_settings(path, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read every frame of a GIF image from the file at the given path, with the given settings
+    /// 
+    pub fn read_gif_from_file_with_settings(path: &str, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let buffer = BitMapRaw::read_to_buffer(&mut BufReader::new(File::open(path)?), settings)?;
+
+        Self::from_gif_bytes_with_settings(&buffer, settings)
+    }
+
+    /// 
+    /// Read every frame of a GIF image from the given bytes
+    /// 
+    pub fn from_gif_bytes(buffer: &[u8]) -> Result<Self, BmpError> {
+        Self::from_gif_bytes_with_settings(buffer, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read every frame of a GIF image from the given bytes, with the given settings. Each frame is the
+    /// whole logical screen, with the frames before it drawn underneath. The limit on allocation covers
+    /// every frame together.
+    /// 
+    pub fn from_gif_bytes_with_settings(buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        BitMapRaw::decode_gif(buffer, settings, false)
+    }
+}
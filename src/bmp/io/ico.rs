@@ -0,0 +1,103 @@
+mod read;
+
+use std::fmt;
+use super::super::*;
+
+/// 
+/// One of the images in an icon or cursor, as described by its directory
+/// 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconEntry {
+    /// 
+    /// The width of the image, in pixels
+    /// 
+    pub width: u32,
+    /// 
+    /// The height of the image, in pixels
+    /// 
+    pub height: u32,
+    /// 
+    /// The number of colors in the color table of the image, or 0 if it has none, or has 256 or more
+    /// 
+    pub color_count: u8,
+    /// 
+    /// The bit depth of the image. Taken from the image itself if the directory doesn't give it,
+    /// or 0 if it can't be found.
+    /// 
+    pub bits_per_pixel: u16,
+    /// 
+    /// The column and row of the pixel which is the point of a cursor, counting from the top left.
+    /// None for icons.
+    /// 
+    pub hotspot: Option<(u16, u16)>,
+    /// 
+    /// Whether the image is a PNG, rather than a DIB
+    /// 
+    pub is_png: bool,
+    /// 
+    /// The size of the image, in bytes
+    /// 
+    pub size: u32,
+    /// 
+    /// The offset of the image from the start of the file
+    /// 
+    pub offset: u32
+}
+
+impl fmt::Display for IconEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}, {} bpp", self.width, self.height, self.bits_per_pixel)?;
+
+        if let Some((x, y)) = self.hotspot {
+            write!(f, ", hotspot at ({x}, {y})")?;
+        }
+
+        if self.is_png {
+            write!(f, ", PNG")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl BitMapRaw {
+    /// 
+    /// The size of the icon directory header: reserved, type and image count
+    /// 
+    const ICO_HEADER_SIZE: usize = 6;
+
+    /// 
+    /// The size of each entry in the icon directory
+    /// 
+    const ICO_ENTRY_SIZE: usize = 16;
+
+    /// 
+    /// The type of an icon in the icon directory header
+    /// 
+    const ICO_TYPE_ICON: u16 = 1;
+
+    /// 
+    /// The type of a cursor in the icon directory header
+    /// 
+    const ICO_TYPE_CURSOR: u16 = 2;
+
+    /// 
+    /// Get the bit depth of the given icon image from its own header, or 0 if it can't be found
+    /// 
+    fn ico_image_bits_per_pixel(image: &[u8]) -> u16 {
+        if image.starts_with(&Self::PNG_SIGNATURE) {
+            //The bit depth and color type follow the signature, the IHDR chunk's length and type, and the width and height
+            match (image.get(24), image.get(25).and_then(|color_type| PngColorType::from_u8(*color_type))) {
+                (Some(bit_depth), Some(color_type)) => *bit_depth as u16 * color_type.channels() as u16,
+                _ => 0
+            }
+        }
+        else {
+            //The bit depth follows the info header's size, width, height and planes
+            match image.get(14..16) {
+                Some(bits) => u16::from_le_bytes([bits[0], bits[1]]),
+                None => 0
+            }
+        }
+    }
+}
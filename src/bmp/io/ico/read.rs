@@ -0,0 +1,244 @@
+use std::io::BufReader;
+use std::fs::File;
+use super::*;
+
+impl BitMapRaw {
+    /// 
+    /// Read the largest image of an icon or cursor from the file at the given path
+    /// 
+    pub fn read_ico_from_file(path: &str) -> Result<Self, BmpError> {
+        Self::read_ico_from_file_with_settings(path, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read the largest image of an icon or cursor from the file at the given path, with the given settings
+    /// 
+    pub fn read_ico_from_file_with_settings(path: &str, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let buffer = Self::read_to_buffer(&mut BufReader::new(File::open(path)?), settings)?;
+
+        Self::from_ico_bytes_with_settings(&buffer, settings)
+    }
+
+    /// 
+    /// Read the images in an icon or cursor from the file at the given path
+    /// 
+    pub fn read_ico_entries_from_file(path: &str) -> Result<Vec<IconEntry>, BmpError> {
+        let buffer = Self::read_to_buffer(&mut BufReader::new(File::open(path)?), &BitMapRawReadSettings::default())?;
+
+        Self::ico_entries_from_bytes(&buffer)
+    }
+
+    /// 
+    /// Read the largest image of an icon or cursor from the given bytes
+    /// 
+    pub fn from_ico_bytes(buffer: &[u8]) -> Result<Self, BmpError> {
+        Self::from_ico_bytes_with_settings(buffer, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read the largest image of an icon or cursor from the given bytes, with the given settings.
+    /// Of images the same size, the one with the most bits per pixel is read.
+    /// 
+    pub fn from_ico_bytes_with_settings(buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let entries = Self::ico_entries_from_bytes(buffer)?;
+
+        match Self::best_ico_entry(&entries) {
+            Some(index) => Self::decode_ico_entry(buffer, &entries[index], settings),
+            None => Err(BmpError::MalformedHeader(String::from("The icon has no images.")))
+        }
+    }
+
+    /// 
+    /// Read the image at the given index of the directory of an icon or cursor from the given bytes, with the given settings.
+    /// 
+    pub fn from_ico_entry_bytes_with_settings(buffer: &[u8], index: usize, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let entries = Self::ico_entries_from_bytes(buffer)?;
+
+        match entries.get(index) {
+            Some(entry) => Self::decode_ico_entry(buffer, entry, settings),
+            None => Err(BmpError::EntryOutOfRange {
+                index,
+                count: entries.len()
+            })
+        }
+    }
+
+    /// 
+    /// Get the index of the largest of the given images, preferring the most bits per pixel,
+    /// and then the first. None if there are no images.
+    /// 
+    pub fn best_ico_entry(entries: &[IconEntry]) -> Option<usize> {
+        entries.iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, entry)| (entry.width as u64 * entry.height as u64, entry.bits_per_pixel))
+            .map(|(index, _)| index)
+    }
+
+    /// 
+    /// Read the images in an icon or cursor from its directory in the given bytes
+    /// 
+    pub fn ico_entries_from_bytes(buffer: &[u8]) -> Result<Vec<IconEntry>, BmpError> {
+        if buffer.len() < Self::ICO_HEADER_SIZE {
+            return Err(BmpError::Truncated {
+                needed: Self::ICO_HEADER_SIZE,
+                available: buffer.len()
+            });
+        }
+
+        let read_u16 = |offset: usize| u16::from_le_bytes([buffer[offset], buffer[offset + 1]]);
+        let read_u32 = |offset: usize| u32::from_le_bytes([buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]]);
+
+        let reserved = read_u16(0);
+        let kind = read_u16(2);
+        let count = read_u16(4) as usize;
+
+        if reserved != 0 {
+            return Err(BmpError::BadSignature(reserved));
+        }
+
+        if kind != Self::ICO_TYPE_ICON && kind != Self::ICO_TYPE_CURSOR {
+            return Err(BmpError::MalformedHeader(format!("{kind} is not the type of an icon or a cursor.")));
+        }
+
+        let end = Self::ICO_HEADER_SIZE + count * Self::ICO_ENTRY_SIZE;
+
+        if buffer.len() < end {
+            return Err(BmpError::Truncated {
+                needed: end,
+                available: buffer.len()
+            });
+        }
+
+        let entries = (Self::ICO_HEADER_SIZE..end)
+            .step_by(Self::ICO_ENTRY_SIZE)
+            .map(|entry| {
+                let size = read_u32(entry + 8);
+                let offset = read_u32(entry + 12);
+                let image = buffer.get(offset as usize..).unwrap_or(&[]);
+
+                //Cursors keep their hotspot where icons keep their planes and bit depth
+                let (planes_or_x, bits_or_y) = (read_u16(entry + 4), read_u16(entry + 6));
+
+                let bits_per_pixel = match bits_or_y {
+                    bits if kind == Self::ICO_TYPE_ICON && bits != 0 => bits,
+                    _ => Self::ico_image_bits_per_pixel(image)
+                };
+
+                IconEntry {
+                    //A width or height of 0 is 256
+                    width: match buffer[entry] {
+                        0 => 256,
+                        width => width as u32
+                    },
+                    height: match buffer[entry + 1] {
+                        0 => 256,
+                        height => height as u32
+                    },
+                    color_count: buffer[entry + 2],
+                    bits_per_pixel,
+                    hotspot: (kind == Self::ICO_TYPE_CURSOR).then_some((planes_or_x, bits_or_y)),
+                    is_png: image.starts_with(&Self::PNG_SIGNATURE),
+                    size,
+                    offset
+                }
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// 
+    /// Decode the given image, from the directory of the icon or cursor in the given bytes. PNG images are read as they are.
+    /// 
+    /// DIBs are followed by an AND mask, with a bit for each pixel which is set if the pixel is transparent,
+    /// or inverts what is behind it, which is treated as transparent. If any pixel is transparent, the image
+    /// is 32 bpp. 32 bpp DIBs use their own alpha channel instead, unless every pixel of it is 0.
+    /// 
+    /// In lenient mode, images which end early are read as far as they go, and rows missing from the mask are opaque.
+    /// 
+    pub fn decode_ico_entry(buffer: &[u8], entry: &IconEntry, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let is_lenient = settings.mode == DecodeMode::Lenient;
+
+        let start = entry.offset as usize;
+        let end = start.saturating_add(entry.size as usize);
+
+        let image = match buffer.get(start..end) {
+            Some(image) => image,
+            None if is_lenient && start < buffer.len() => &buffer[start..],
+            None => return Err(BmpError::Truncated {
+                needed: end,
+                available: buffer.len()
+            })
+        };
+
+        if entry.is_png {
+            return Self::from_png_bytes_with_settings(image, settings);
+        }
+
+        let header = BitMapHeader {
+            signature: BitMapHeader::SIGNATURE,
+            file_size: 0,
+            reserved: 0,
+            data_offset: 0
+        };
+
+        let (mut bitmap, mask_offset) = Self::read_dib(image, 0, header, Vec::new(), true, settings)?;
+
+        let width = bitmap.info_header.width.unsigned_abs() as usize;
+        let height = bitmap.info_header.height.unsigned_abs() as usize;
+        let pixel_count = width * height;
+
+        let pixels = bitmap.pixel_data.to_rgba(&bitmap.color_table)?;
+
+        //The mask isn't needed if the image has an alpha channel
+        if bitmap.info_header.bits_per_pixel == 32 && pixels.iter().any(|pixel| pixel.alpha != 0) {
+            return Ok(bitmap);
+        }
+
+        //Each row of the mask is 1 bit per pixel, padded to a multiple of 4 bytes, with the rows in the same order as the pixel data
+        let mask_row_width = width.div_ceil(32) * 4;
+        let mask = image.get(mask_offset..).unwrap_or(&[]);
+        let mask_rows = mask.len().checked_div(mask_row_width).unwrap_or(height).min(height);
+
+        if mask_rows < height {
+            if !is_lenient {
+                return Err(BmpError::Truncated {
+                    needed: start + mask_offset + height * mask_row_width,
+                    available: start + image.len()
+                });
+            }
+
+            bitmap.warnings.push(DecodeWarning::TruncatedMask {
+                missing_rows: height - mask_rows
+            });
+        }
+
+        let is_masked = |index: usize| {
+            let (row, x) = (index / width, index % width);
+            row < mask_rows && (mask[row * mask_row_width + x / 8] >> (7 - x % 8)) & 1 == 1
+        };
+
+        //Images without any transparent pixels are kept as they are, other than 32 bpp images, whose alpha channel is unused
+        if bitmap.info_header.bits_per_pixel != 32 && !(0..pixel_count).any(is_masked) {
+            return Ok(bitmap);
+        }
+
+        //Rows of bottom-up images are reversed, as the new image is top-down
+        let is_bottom_up = bitmap.info_header.height > 0;
+
+        let colors: Vec<RGBColor> = (0..height)
+            .map(|y| if is_bottom_up { height - 1 - y } else { y })
+            .flat_map(|row| (row * width)..((row + 1) * width))
+            .map(|index| RGBColor {
+                alpha: if is_masked(index) { 0 } else { 0xFF },
+                ..pixels[index].clone()
+            })
+            .collect();
+
+        let mut masked = Self::from_pixels(width as u32, height as u32, 32, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(colors))?;
+        masked.warnings = bitmap.warnings;
+
+        Ok(masked)
+    }
+}
@@ -0,0 +1,560 @@
+use std::io::{Write, BufReader, BufWriter};
+use std::fs::File;
+use super::super::*;
+
+/// 
+/// A Netpbm image format
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetpbmFormat {
+    /// 
+    /// Portable bitmap: black and white (P1/P4)
+    /// 
+    Pbm,
+    /// 
+    /// Portable graymap: greyscale (P2/P5)
+    /// 
+    Pgm,
+    /// 
+    /// Portable pixmap: RGB (P3/P6)
+    /// 
+    Ppm,
+    /// 
+    /// Portable arbitrary map: RGB with alpha (P7). Always binary.
+    /// 
+    Pam
+}
+
+/// 
+/// Whether the samples of a Netpbm image are written as text or bytes
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NetpbmEncoding {
+    /// 
+    /// Samples are decimal numbers, separated by whitespace (P1/P2/P3)
+    /// 
+    Ascii,
+    /// 
+    /// Samples are 1 or 2 bytes each, or for PBM, 1 bit each (P4/P5/P6/P7)
+    /// 
+    #[default]
+    Binary
+}
+
+/// 
+/// Settings for BitMapRaw::write_netpbm_to
+/// 
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct NetpbmWriteSettings {
+    /// 
+    /// The format to write
+    /// 
+    format: NetpbmFormat,
+    /// 
+    /// Whether to write the samples as text or bytes
+    /// 
+    encoding: NetpbmEncoding,
+    /// 
+    /// The value of a sample at full intensity. Samples are 2 bytes in binary
+    /// images if this is over 255. Ignored for PBM.
+    /// 
+    max_value: u16
+}
+
+impl NetpbmWriteSettings {
+    /// 
+    /// Create a new instance of NetpbmWriteSettings with the
+    /// given settings
+    /// 
+    pub fn new(format: NetpbmFormat, encoding: NetpbmEncoding, max_value: u16) -> Self {
+        NetpbmWriteSettings {
+            format,
+            encoding,
+            max_value
+        }
+    }
+
+    pub fn with_format(&mut self, format: NetpbmFormat) -> &Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_encoding(&mut self, encoding: NetpbmEncoding) -> &Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn with_max_value(&mut self, max_value: u16) -> &Self {
+        self.max_value = max_value;
+        self
+    }
+}
+
+impl Default for NetpbmWriteSettings {
+    /// 
+    /// Binary PPM, with 1 byte per sample
+    /// 
+    fn default() -> Self {
+        Self::new(NetpbmFormat::Ppm, NetpbmEncoding::Binary, 0xFF)
+    }
+}
+
+/// 
+/// Reads the whitespace separated tokens of a Netpbm header, skipping comments
+/// 
+struct NetpbmCursor<'a> {
+    buffer: &'a [u8],
+    offset: usize
+}
+
+impl<'a> NetpbmCursor<'a> {
+    /// 
+    /// Skip whitespace, and comments from # to the end of the line
+    /// 
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.buffer.get(self.offset) {
+            match byte {
+                b'#' => {
+                    while let Some(byte) = self.buffer.get(self.offset) {
+                        if *byte == b'\n' || *byte == b'\r' {
+                            break;
+                        }
+
+                        self.offset += 1;
+                    }
+                },
+                byte if byte.is_ascii_whitespace() => self.offset += 1,
+                _ => break
+            }
+        }
+    }
+
+    /// 
+    /// Get the next token, or None if there are no more
+    /// 
+    fn next_token(&mut self) -> Option<&'a [u8]> {
+        self.skip_whitespace();
+
+        let start = self.offset;
+
+        while let Some(byte) = self.buffer.get(self.offset) {
+            if byte.is_ascii_whitespace() || *byte == b'#' {
+                break;
+            }
+
+            self.offset += 1;
+        }
+
+        match self.offset > start {
+            true => Some(&self.buffer[start..self.offset]),
+            false => None
+        }
+    }
+
+    /// 
+    /// Get the next token as a number. The name of the value is used in the error if it is missing or invalid.
+    /// 
+    fn next_number(&mut self, name: &str) -> Result<u32, BmpError> {
+        let token = self.next_token().ok_or_else(|| BmpError::MalformedHeader(format!("{name} is missing.")))?;
+
+        std::str::from_utf8(token).ok()
+            .and_then(|text| text.parse::<u32>().ok())
+            .ok_or_else(|| BmpError::MalformedHeader(format!("{name} '{}' is not a number.", String::from_utf8_lossy(token))))
+    }
+}
+
+impl BitMapRaw {
+    /// 
+    /// Read a Netpbm image from the file at the given path
+    /// 
+    pub fn read_netpbm_from_file(path: &str) -> Result<Self, BmpError> {
+        Self::read_netpbm_from_file_with_settings(path, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read a Netpbm image from the file at the given path, with the given settings
+    /// 
+    pub fn read_netpbm_from_file_with_settings(path: &str, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let buffer = Self::read_to_buffer(&mut BufReader::new(File::open(path)?), settings)?;
+
+        Self::from_netpbm_bytes_with_settings(&buffer, settings)
+    }
+
+    /// 
+    /// Read a Netpbm image from the given bytes
+    /// 
+    pub fn from_netpbm_bytes(buffer: &[u8]) -> Result<Self, BmpError> {
+        Self::from_netpbm_bytes_with_settings(buffer, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read a Netpbm image (PBM, PGM, PPM or PAM, in ASCII or binary) from the given bytes, with the given settings.
+    /// Only the first image of a file with several is read.
+    /// 
+    pub fn from_netpbm_bytes_with_settings(buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let magic = match buffer.get(0..2) {
+            Some(magic) => magic,
+            None => return Err(BmpError::Truncated {
+                needed: 2,
+                available: buffer.len()
+            })
+        };
+
+        let kind = match magic {
+            [b'P', kind @ b'1'..=b'7'] => *kind,
+            _ => return Err(BmpError::BadSignature(u16::from_le_bytes([magic[0], magic[1]])))
+        };
+
+        let mut cursor = NetpbmCursor {
+            buffer,
+            offset: 2
+        };
+
+        //
+        // Read the header. PAM has a header of KEY VALUE lines, ending with ENDHDR.
+        // The others have the width, height, and the maximum value of a sample, other than PBM.
+        //
+        let (width, height, depth, max_value) = match kind {
+            b'7' => Self::read_pam_header(&mut cursor)?,
+            _ => {
+                let width = cursor.next_number("Width")?;
+                let height = cursor.next_number("Height")?;
+
+                let (depth, max_value) = match kind {
+                    b'1' | b'4' => (1, 1),
+                    b'2' | b'5' => (1, cursor.next_number("Maximum value")?),
+                    _ => (3, cursor.next_number("Maximum value")?)
+                };
+
+                (width, height, depth, max_value)
+            }
+        };
+
+        if max_value == 0 || max_value > 0xFFFF {
+            return Err(BmpError::MalformedHeader(format!("{max_value} is not a valid maximum value.")));
+        }
+
+        if !(1..=4).contains(&depth) {
+            return Err(BmpError::MalformedHeader(format!("{depth} is not a supported depth.")));
+        }
+
+        let pixel_count = (width as usize).checked_mul(height as usize).ok_or(BmpError::InvalidDimensions {
+            width: width.min(i32::MAX as u32) as i32,
+            height: height.min(i32::MAX as u32) as i32
+        })?;
+
+        DecodeLimits::check(DecodeLimit::Width, width as u64, settings.limits.max_width as u64)?;
+        DecodeLimits::check(DecodeLimit::Height, height as u64, settings.limits.max_height as u64)?;
+        DecodeLimits::check(DecodeLimit::Pixels, pixel_count as u64, settings.limits.max_pixels)?;
+        DecodeLimits::check(DecodeLimit::AllocBytes, (pixel_count as u64).saturating_mul(std::mem::size_of::<RGBColor>() as u64), settings.limits.max_alloc_bytes)?;
+
+        //Binary samples begin after a single whitespace character
+        let is_binary = kind >= b'4';
+
+        if is_binary {
+            cursor.offset += 1;
+        }
+
+        let samples_per_pixel = depth as usize;
+        let mut samples: Vec<u32> = Vec::with_capacity(pixel_count.saturating_mul(samples_per_pixel));
+
+        match kind {
+            //Each pixel is a 0 or 1, which may or may not be separated by whitespace
+            b'1' => {
+                while samples.len() < pixel_count {
+                    cursor.skip_whitespace();
+
+                    match buffer.get(cursor.offset) {
+                        Some(b'0') => samples.push(0),
+                        Some(b'1') => samples.push(1),
+                        _ => break
+                    }
+
+                    cursor.offset += 1;
+                }
+            },
+            //Each pixel is a bit, with each row padded to a whole byte
+            b'4' => {
+                let row_width = (width as usize).div_ceil(8);
+
+                for _ in 0..height {
+                    let row = match buffer.get(cursor.offset..) {
+                        Some(rest) => &rest[..usize::min(row_width, rest.len())],
+                        None => &[]
+                    };
+
+                    cursor.offset += row.len();
+
+                    for x in 0..(width as usize) {
+                        match row.get(x / 8) {
+                            Some(byte) => samples.push(((byte >> (7 - (x % 8))) & 1) as u32),
+                            None => break
+                        }
+                    }
+
+                    if row.len() < row_width {
+                        break;
+                    }
+                }
+            },
+            //Each sample is a decimal number
+            b'2' | b'3' => {
+                while samples.len() < pixel_count * samples_per_pixel {
+                    match cursor.next_token().and_then(|token| std::str::from_utf8(token).ok()).and_then(|text| text.parse::<u32>().ok()) {
+                        Some(sample) => samples.push(sample),
+                        None => break
+                    }
+                }
+            },
+            //Each sample is 1 byte, or 2 big-endian bytes if the maximum value is over 255
+            _ => {
+                let sample_size = match max_value {
+                    0..=0xFF => 1,
+                    _ => 2
+                };
+
+                let raster = buffer.get(cursor.offset..).unwrap_or(&[]);
+
+                raster.chunks_exact(sample_size)
+                    .take(pixel_count * samples_per_pixel)
+                    .for_each(|chunk| samples.push(match chunk {
+                        [high, low] => u16::from_be_bytes([*high, *low]) as u32,
+                        _ => chunk[0] as u32
+                    }));
+            }
+        }
+
+        let missing_pixels = pixel_count - (samples.len() / samples_per_pixel);
+
+        if missing_pixels > 0 && settings.mode == DecodeMode::Strict {
+            return Err(BmpError::Truncated {
+                needed: pixel_count * samples_per_pixel,
+                available: samples.len()
+            });
+        }
+
+        //PBM is indexed, with white as 0 and black as 1
+        if kind == b'1' || kind == b'4' {
+            let color_table = BitMapColorTable {
+                colors: vec![
+                    RGBColor { red: 0xFF, green: 0xFF, blue: 0xFF, alpha: 0xFF },
+                    RGBColor { red: 0, green: 0, blue: 0, alpha: 0xFF }
+                ]
+            };
+
+            let mut bitmap = match missing_pixels {
+                0 => Self::from_pixels(width, height, 1, color_table, BitMapPixelData::Indexed(samples.iter().map(|sample| *sample as u8).collect()))?,
+                _ => {
+                    let mut pixels: Vec<RGBColor> = samples.iter().map(|sample| color_table.colors[*sample as usize].clone()).collect();
+                    pixels.resize(pixel_count, settings.fill_color.clone());
+
+                    Self::from_pixels(width, height, 1, color_table, BitMapPixelData::Color(pixels))?
+                }
+            };
+
+            if missing_pixels > 0 {
+                bitmap.warnings.push(DecodeWarning::TruncatedPixelData { missing_pixels });
+            }
+
+            return Ok(bitmap);
+        }
+
+        //Scale each sample from the maximum value to 8 bits
+        let scale = |sample: u32| -> u8 {
+            let sample = u32::min(sample, max_value) as u64;
+            ((sample * 255 + (max_value as u64 / 2)) / max_value as u64) as u8
+        };
+
+        let mut pixels: Vec<RGBColor> = samples.chunks_exact(samples_per_pixel)
+            .map(|pixel| match pixel {
+                [grey] => RGBColor { red: scale(*grey), green: scale(*grey), blue: scale(*grey), alpha: 0xFF },
+                [grey, alpha] => RGBColor { red: scale(*grey), green: scale(*grey), blue: scale(*grey), alpha: scale(*alpha) },
+                [red, green, blue] => RGBColor { red: scale(*red), green: scale(*green), blue: scale(*blue), alpha: 0xFF },
+                _ => RGBColor { red: scale(pixel[0]), green: scale(pixel[1]), blue: scale(pixel[2]), alpha: scale(pixel[3]) }
+            })
+            .collect();
+
+        pixels.resize(pixel_count, settings.fill_color.clone());
+
+        //Only PAM can have an alpha channel
+        let bits_per_pixel = match depth {
+            2 | 4 => 32,
+            _ => 24
+        };
+
+        let mut bitmap = Self::from_pixels(width, height, bits_per_pixel, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(pixels))?;
+
+        if missing_pixels > 0 {
+            bitmap.warnings.push(DecodeWarning::TruncatedPixelData { missing_pixels });
+        }
+
+        Ok(bitmap)
+    }
+
+    /// 
+    /// Read the KEY VALUE lines of a PAM header, up to and including ENDHDR, returning
+    /// the width, height, depth and maximum value
+    /// 
+    fn read_pam_header(cursor: &mut NetpbmCursor) -> Result<(u32, u32, u32, u32), BmpError> {
+        let (mut width, mut height, mut depth, mut max_value) = (None, None, None, None);
+
+        loop {
+            let key = cursor.next_token().ok_or_else(|| BmpError::MalformedHeader("ENDHDR is missing.".to_string()))?;
+
+            match key {
+                b"ENDHDR" => break,
+                b"WIDTH" => width = Some(cursor.next_number("WIDTH")?),
+                b"HEIGHT" => height = Some(cursor.next_number("HEIGHT")?),
+                b"DEPTH" => depth = Some(cursor.next_number("DEPTH")?),
+                b"MAXVAL" => max_value = Some(cursor.next_number("MAXVAL")?),
+                //The tuple type only names the channels given by the depth
+                _ => {
+                    while let Some(byte) = cursor.buffer.get(cursor.offset) {
+                        if *byte == b'\n' {
+                            break;
+                        }
+
+                        cursor.offset += 1;
+                    }
+                }
+            }
+        }
+
+        //ENDHDR is followed by a newline, which is skipped along with the whitespace after binary headers
+        match (width, height, depth, max_value) {
+            (Some(width), Some(height), Some(depth), Some(max_value)) => Ok((width, height, depth, max_value)),
+            _ => Err(BmpError::MalformedHeader("WIDTH, HEIGHT, DEPTH and MAXVAL are required.".to_string()))
+        }
+    }
+
+    /// 
+    /// Write the image to the file at the given path as a Netpbm image, with the given settings
+    /// 
+    pub fn write_netpbm_to_file(&self, path: &str, settings: &NetpbmWriteSettings) -> Result<(), BmpError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        self.write_netpbm_to(&mut writer, settings)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// 
+    /// Write the image to the given destination as a Netpbm image, with the given settings.
+    /// Greyscale is the luminance of each pixel, and PBM pixels are black if their luminance is under half.
+    /// Alpha is only written to PAM.
+    /// 
+    pub fn write_netpbm_to<W: Write>(&self, writer: &mut W, settings: &NetpbmWriteSettings) -> Result<(), BmpError> {
+        let width = self.info_header.width.unsigned_abs() as usize;
+        let height = self.info_header.height.unsigned_abs() as usize;
+
+        let pixel_count = width.checked_mul(height).ok_or(BmpError::InvalidDimensions {
+            width: self.info_header.width,
+            height: self.info_header.height
+        })?;
+
+        if self.pixel_data.len() != pixel_count {
+            return Err(BmpError::PixelCountMismatch {
+                expected: pixel_count,
+                actual: self.pixel_data.len()
+            });
+        }
+
+        let max_value = match settings.format {
+            NetpbmFormat::Pbm => 1,
+            _ => u16::max(settings.max_value, 1)
+        };
+
+        let is_ascii = settings.encoding == NetpbmEncoding::Ascii && settings.format != NetpbmFormat::Pam;
+
+        let magic = match (settings.format, is_ascii) {
+            (NetpbmFormat::Pbm, true) => "P1",
+            (NetpbmFormat::Pgm, true) => "P2",
+            (NetpbmFormat::Ppm, true) => "P3",
+            (NetpbmFormat::Pbm, false) => "P4",
+            (NetpbmFormat::Pgm, false) => "P5",
+            (NetpbmFormat::Ppm, false) => "P6",
+            (NetpbmFormat::Pam, _) => "P7"
+        };
+
+        match settings.format {
+            NetpbmFormat::Pam => write!(writer, "{magic}\nWIDTH {width}\nHEIGHT {height}\nDEPTH 4\nMAXVAL {max_value}\nTUPLTYPE RGB_ALPHA\nENDHDR\n")?,
+            NetpbmFormat::Pbm => write!(writer, "{magic}\n{width} {height}\n")?,
+            _ => write!(writer, "{magic}\n{width} {height}\n{max_value}\n")?
+        }
+
+        //Scale a channel from 8 bits to the maximum value
+        let scale = |channel: u8| -> u16 {
+            ((channel as u32 * max_value as u32 + 127) / 255) as u16
+        };
+
+        //ASCII lines should be no longer than 70 characters
+        let mut line = String::new();
+        let mut bytes: Vec<u8> = Vec::new();
+
+        for y in 0..height {
+            //PBM rows are padded to a whole byte
+            let mut bits: u8 = 0;
+
+            for x in 0..width {
+                let pixel = self.get_pixel_at(x, y).unwrap_or(RGBColor {
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                    alpha: 0
+                });
+
+                let samples: Vec<u16> = match settings.format {
+                    //1 is black
+                    NetpbmFormat::Pbm => vec![(pixel.get_luminance() < 0x80) as u16],
+                    NetpbmFormat::Pgm => vec![scale(pixel.get_luminance())],
+                    NetpbmFormat::Ppm => vec![scale(pixel.red), scale(pixel.green), scale(pixel.blue)],
+                    NetpbmFormat::Pam => vec![scale(pixel.red), scale(pixel.green), scale(pixel.blue), scale(pixel.alpha)]
+                };
+
+                for sample in samples {
+                    if is_ascii {
+                        let text = sample.to_string();
+
+                        if !line.is_empty() && line.len() + 1 + text.len() > 70 {
+                            writeln!(writer, "{line}")?;
+                            line.clear();
+                        }
+
+                        if !line.is_empty() {
+                            line.push(' ');
+                        }
+
+                        line.push_str(&text);
+                    }
+                    else if settings.format == NetpbmFormat::Pbm {
+                        bits |= (sample as u8) << (7 - (x % 8));
+
+                        if x % 8 == 7 || x + 1 == width {
+                            bytes.push(bits);
+                            bits = 0;
+                        }
+                    }
+                    else if max_value > 0xFF {
+                        bytes.extend_from_slice(&sample.to_be_bytes());
+                    }
+                    else {
+                        bytes.push(sample as u8);
+                    }
+                }
+            }
+
+            //Start each row of an ASCII image on a new line
+            if is_ascii && !line.is_empty() {
+                writeln!(writer, "{line}")?;
+                line.clear();
+            }
+
+            writer.write_all(&bytes)?;
+            bytes.clear();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+mod read;
+mod write;
+
+pub use write::*;
+
+use super::super::*;
+
+/// 
+/// The color type of a PNG image, which sets the channels of each pixel
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PngColorType {
+    /// 
+    /// A single grey channel, at 1, 2, 4, 8 or 16 bits
+    /// 
+    Greyscale,
+    /// 
+    /// Red, green and blue channels, at 8 or 16 bits
+    /// 
+    Rgb,
+    /// 
+    /// An index into the palette, at 1, 2, 4 or 8 bits
+    /// 
+    Palette,
+    /// 
+    /// Grey and alpha channels, at 8 or 16 bits
+    /// 
+    GreyscaleAlpha,
+    /// 
+    /// Red, green, blue and alpha channels, at 8 or 16 bits
+    /// 
+    Rgba
+}
+
+impl PngColorType {
+    /// 
+    /// Get the color type with the given value in the IHDR chunk
+    /// 
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Greyscale),
+            2 => Some(Self::Rgb),
+            3 => Some(Self::Palette),
+            4 => Some(Self::GreyscaleAlpha),
+            6 => Some(Self::Rgba),
+            _ => None
+        }
+    }
+
+    /// 
+    /// Get the value of the color type in the IHDR chunk
+    /// 
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            Self::Greyscale => 0,
+            Self::Rgb => 2,
+            Self::Palette => 3,
+            Self::GreyscaleAlpha => 4,
+            Self::Rgba => 6
+        }
+    }
+
+    /// 
+    /// The number of samples in each pixel
+    /// 
+    pub fn channels(&self) -> usize {
+        match self {
+            Self::Greyscale | Self::Palette => 1,
+            Self::GreyscaleAlpha => 2,
+            Self::Rgb => 3,
+            Self::Rgba => 4
+        }
+    }
+
+    /// 
+    /// Whether the color type allows the given bit depth
+    /// 
+    pub fn allows_bit_depth(&self, bit_depth: u8) -> bool {
+        match self {
+            Self::Greyscale => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+            Self::Palette => matches!(bit_depth, 1 | 2 | 4 | 8),
+            _ => matches!(bit_depth, 8 | 16)
+        }
+    }
+}
+
+impl BitMapRaw {
+    /// 
+    /// The 8 bytes every PNG file begins with
+    /// 
+    pub const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+    /// 
+    /// The first column, first row, column step and row step of each of the 7 Adam7 passes
+    /// 
+    const ADAM7_PASSES: [(usize, usize, usize, usize); 7] = [
+        (0, 0, 8, 8),
+        (4, 0, 8, 8),
+        (0, 4, 4, 8),
+        (2, 0, 4, 4),
+        (0, 2, 2, 4),
+        (1, 0, 2, 2),
+        (0, 1, 1, 2)
+    ];
+
+    /// 
+    /// Get the first column, first row, column step and row step of each pass of a PNG image.
+    /// A non-interlaced image is a single pass over every pixel.
+    /// 
+    fn png_passes(interlaced: bool) -> Vec<(usize, usize, usize, usize)> {
+        match interlaced {
+            true => Self::ADAM7_PASSES.to_vec(),
+            false => vec![(0, 0, 1, 1)]
+        }
+    }
+
+    /// 
+    /// Get the number of columns or rows of an image of the given size that are in a pass
+    /// starting at the given position, with the given step
+    /// 
+    fn png_pass_size(size: usize, start: usize, step: usize) -> usize {
+        match size > start {
+            true => (size - start).div_ceil(step),
+            false => 0
+        }
+    }
+
+    /// 
+    /// Get the CRC-32 of the given bytes, as used by PNG chunks
+    /// 
+    fn png_crc(parts: &[&[u8]]) -> u32 {
+        //The CRC of each possible byte value, using the reversed polynomial 0xEDB88320
+        const TABLE: [u32; 256] = {
+            let mut table = [0_u32; 256];
+            let mut n = 0;
+
+            while n < 256 {
+                let mut c = n as u32;
+                let mut k = 0;
+
+                while k < 8 {
+                    c = match c & 1 {
+                        1 => 0xEDB88320 ^ (c >> 1),
+                        _ => c >> 1
+                    };
+
+                    k += 1;
+                }
+
+                table[n] = c;
+                n += 1;
+            }
+
+            table
+        };
+
+        let mut crc = 0xFFFFFFFF_u32;
+
+        for byte in parts.iter().flat_map(|part| part.iter()) {
+            crc = TABLE[((crc ^ (*byte as u32)) & 0xFF) as usize] ^ (crc >> 8);
+        }
+
+        crc ^ 0xFFFFFFFF
+    }
+
+    /// 
+    /// The Paeth predictor: whichever of the pixel to the left, above, or above and to the left,
+    /// is closest to left + above - upper left
+    /// 
+    fn png_paeth(left: u8, above: u8, upper_left: u8) -> u8 {
+        let estimate = left as i16 + above as i16 - upper_left as i16;
+
+        let distance_left = (estimate - left as i16).abs();
+        let distance_above = (estimate - above as i16).abs();
+        let distance_upper_left = (estimate - upper_left as i16).abs();
+
+        if distance_left <= distance_above && distance_left <= distance_upper_left {
+            left
+        }
+        else if distance_above <= distance_upper_left {
+            above
+        }
+        else {
+            upper_left
+        }
+    }
+}
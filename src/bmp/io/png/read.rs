@@ -0,0 +1,506 @@
+use std::io::BufReader;
+use std::fs::File;
+use miniz_oxide::inflate::stream::{InflateState, inflate};
+use miniz_oxide::{DataFormat, MZError, MZFlush};
+use super::*;
+
+/// 
+/// The contents of an IHDR chunk
+/// 
+struct PngHeader {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: PngColorType,
+    interlaced: bool
+}
+
+impl BitMapRaw {
+    /// 
+    /// Read a PNG image from the file at the given path
+    /// 
+    pub fn read_png_from_file(path: &str) -> Result<Self, BmpError> {
+        Self::read_png_from_file_with_settings(path, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read a PNG image from the file at the given path, with the given settings
+    /// 
+    pub fn read_png_from_file_with_settings(path: &str, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let buffer = Self::read_to_buffer(&mut BufReader::new(File::open(path)?), settings)?;
+
+        Self::from_png_bytes_with_settings(&buffer, settings)
+    }
+
+    /// 
+    /// Read a PNG image from the given bytes
+    /// 
+    pub fn from_png_bytes(buffer: &[u8]) -> Result<Self, BmpError> {
+        Self::from_png_bytes_with_settings(buffer, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read a PNG image from the given bytes, with the given settings.
+    /// 
+    /// Palette images keep their palette as the color table, with any transparency from the tRNS chunk.
+    /// Other images are 32 bpp if they have an alpha channel or a transparent color, and 24 bpp otherwise.
+    /// 16-bit channels are rounded to 8 bits.
+    /// 
+    /// In lenient mode, bad chunk CRCs and unknown critical chunks are ignored, and pixels missing from
+    /// truncated or corrupt image data are given the fill color.
+    /// 
+    pub fn from_png_bytes_with_settings(buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let is_lenient = settings.mode == DecodeMode::Lenient;
+
+        if buffer.len() < Self::PNG_SIGNATURE.len() {
+            return Err(BmpError::Truncated {
+                needed: Self::PNG_SIGNATURE.len(),
+                available: buffer.len()
+            });
+        }
+
+        if buffer[0..8] != Self::PNG_SIGNATURE {
+            return Err(BmpError::BadSignature(u16::from_le_bytes([buffer[0], buffer[1]])));
+        }
+
+        let mut header: Option<PngHeader> = None;
+        let mut palette: Vec<RGBColor> = Vec::new();
+        let mut transparency: Option<&[u8]> = None;
+        let mut compressed: Vec<u8> = Vec::new();
+
+        let mut offset = Self::PNG_SIGNATURE.len();
+        let mut has_end = false;
+
+        //
+        // Each chunk is its length, its type, its data, and the CRC of its type and data.
+        // Read chunks until IEND, or until the end of the buffer.
+        //
+        while offset < buffer.len() {
+            let chunk_header = match buffer.get(offset..(offset + 8)) {
+                Some(chunk_header) => chunk_header,
+                None if is_lenient => break,
+                None => return Err(BmpError::Truncated {
+                    needed: offset + 8,
+                    available: buffer.len()
+                })
+            };
+
+            let length = u32::from_be_bytes([chunk_header[0], chunk_header[1], chunk_header[2], chunk_header[3]]) as usize;
+            let chunk_type = &chunk_header[4..8];
+            let name = String::from_utf8_lossy(chunk_type).to_string();
+
+            let data_end = offset + 8 + length;
+
+            let (data, crc) = match (buffer.get((offset + 8)..data_end), buffer.get(data_end..(data_end + 4))) {
+                (Some(data), Some(crc)) => (data, u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]])),
+                //Use as much of a truncated image data chunk as there is
+                _ if is_lenient => {
+                    if chunk_type == b"IDAT" {
+                        compressed.extend_from_slice(&buffer[usize::min(offset + 8, buffer.len())..usize::min(data_end, buffer.len())]);
+                    }
+
+                    break;
+                },
+                _ => return Err(BmpError::Truncated {
+                    needed: data_end + 4,
+                    available: buffer.len()
+                })
+            };
+
+            if !is_lenient && Self::png_crc(&[chunk_type, data]) != crc {
+                return Err(BmpError::CorruptData(format!("The CRC of the {name} chunk does not match its contents.")));
+            }
+
+            offset = data_end + 4;
+
+            if header.is_none() && chunk_type != b"IHDR" {
+                return Err(BmpError::MalformedHeader("The first chunk must be IHDR.".to_string()));
+            }
+
+            match chunk_type {
+                b"IHDR" => header = Some(Self::read_png_header(data)?),
+                b"PLTE" => {
+                    if data.len() % 3 != 0 || data.len() > 256 * 3 {
+                        return Err(BmpError::MalformedHeader(format!("A palette of {} bytes is not a whole number of colors, up to 256.", data.len())));
+                    }
+
+                    palette = data.chunks_exact(3)
+                        .map(|rgb| RGBColor {
+                            red: rgb[0],
+                            green: rgb[1],
+                            blue: rgb[2],
+                            alpha: 0xFF
+                        })
+                        .collect();
+                },
+                b"tRNS" => transparency = Some(data),
+                b"IDAT" => {
+                    DecodeLimits::check(DecodeLimit::AllocBytes, (compressed.len() + data.len()) as u64, settings.limits.max_alloc_bytes)?;
+                    compressed.extend_from_slice(data);
+                },
+                b"IEND" => {
+                    has_end = true;
+                    break;
+                },
+                //Ancillary chunks, whose names start with a lowercase letter, can be skipped safely
+                _ if chunk_type[0].is_ascii_lowercase() || is_lenient => {},
+                _ => return Err(BmpError::MalformedHeader(format!("Critical chunk {name} is not supported.")))
+            }
+        }
+
+        //The image ends with an empty IEND chunk
+        if !has_end && !is_lenient {
+            return Err(BmpError::Truncated {
+                needed: offset + 12,
+                available: buffer.len()
+            });
+        }
+
+        let header = match header {
+            Some(header) => header,
+            None => return Err(BmpError::MalformedHeader("IHDR chunk is missing.".to_string()))
+        };
+
+        let width = header.width as usize;
+        let height = header.height as usize;
+        let pixel_count = width.checked_mul(height).ok_or(BmpError::InvalidDimensions {
+            width: header.width.min(i32::MAX as u32) as i32,
+            height: header.height.min(i32::MAX as u32) as i32
+        })?;
+
+        DecodeLimits::check(DecodeLimit::Width, header.width as u64, settings.limits.max_width as u64)?;
+        DecodeLimits::check(DecodeLimit::Height, header.height as u64, settings.limits.max_height as u64)?;
+        DecodeLimits::check(DecodeLimit::Pixels, pixel_count as u64, settings.limits.max_pixels)?;
+        DecodeLimits::check(DecodeLimit::AllocBytes, (pixel_count as u64).saturating_mul(std::mem::size_of::<RGBColor>() as u64), settings.limits.max_alloc_bytes)?;
+
+        let is_palette = header.color_type == PngColorType::Palette;
+
+        if is_palette && palette.is_empty() {
+            return Err(BmpError::MalformedHeader("Palette images must have a PLTE chunk.".to_string()));
+        }
+
+        let channels = header.color_type.channels();
+        let bit_depth = header.bit_depth as usize;
+        let passes = Self::png_passes(header.interlaced);
+
+        //The size of each row of each pass, not counting the filter type byte
+        let row_size = |pass_width: usize| (pass_width * channels * bit_depth).div_ceil(8);
+
+        let filtered_size: usize = passes.iter()
+            .map(|(x0, y0, dx, dy)| {
+                let pass_width = Self::png_pass_size(width, *x0, *dx);
+                let pass_height = Self::png_pass_size(height, *y0, *dy);
+
+                match pass_width {
+                    0 => 0,
+                    _ => pass_height.saturating_mul(1 + row_size(pass_width))
+                }
+            })
+            .fold(0_usize, usize::saturating_add);
+
+        DecodeLimits::check(DecodeLimit::AllocBytes, filtered_size as u64, settings.limits.max_alloc_bytes)?;
+
+        //
+        // Decompress as much of the image data as there is, up to the end of the last row.
+        // Any data after the last row is ignored.
+        //
+        let mut filtered: Vec<u8> = vec![0; filtered_size];
+        let mut inflater = InflateState::new_boxed(DataFormat::Zlib);
+        let result = inflate(&mut inflater, &compressed, &mut filtered, MZFlush::Finish);
+
+        match result.status {
+            Ok(_) | Err(MZError::Buf) => {},
+            Err(e) if !is_lenient => return Err(BmpError::CorruptData(format!("Image data could not be decompressed ({e:?})."))),
+            Err(_) => {}
+        }
+
+        filtered.truncate(result.bytes_written);
+
+        //Palette transparency is the alpha of each palette entry, in order
+        if is_palette {
+            if let Some(alphas) = transparency {
+                palette.iter_mut()
+                    .zip(alphas.iter())
+                    .for_each(|(color, alpha)| color.alpha = *alpha);
+            }
+        }
+
+        //Greyscale and RGB transparency is a single color, at the sample depth, which is fully transparent
+        let transparent_samples: Option<Vec<u16>> = match (header.color_type, transparency) {
+            (PngColorType::Greyscale | PngColorType::Rgb, Some(key)) => Some(key.chunks_exact(2)
+                .take(channels)
+                .map(|sample| u16::from_be_bytes([sample[0], sample[1]]))
+                .collect()),
+            _ => None
+        };
+
+        //Scale a sample from the bit depth to 8 bits. 16 bit samples are rounded.
+        let max_sample = ((1_u32 << bit_depth) - 1) as u16;
+        let scale = |sample: u16| -> u8 {
+            match bit_depth {
+                16 => ((sample as u32 + 128) / 257) as u8,
+                8 => sample as u8,
+                _ => ((sample as u32 * 255) / max_sample as u32) as u8
+            }
+        };
+
+        //Pixels of a palette image which have not been decoded have no index
+        let mut indices: Vec<Option<u8>> = vec![None; if is_palette { pixel_count } else { 0 }];
+        let mut pixels: Vec<RGBColor> = vec![settings.fill_color.clone(); if is_palette { 0 } else { pixel_count }];
+        let mut decoded_pixels = 0_usize;
+
+        //The number of bytes back the corresponding byte of the previous pixel is, for filtering
+        let filter_distance = usize::max(1, (channels * bit_depth) / 8);
+        let mut position = 0_usize;
+
+        'passes: for (x0, y0, dx, dy) in passes {
+            let pass_width = Self::png_pass_size(width, x0, dx);
+            let pass_height = Self::png_pass_size(height, y0, dy);
+
+            //Empty passes have no rows, not even filter type bytes
+            if pass_width == 0 || pass_height == 0 {
+                continue;
+            }
+
+            let size = row_size(pass_width);
+            let mut previous: Vec<u8> = vec![0; size];
+
+            for pass_y in 0..pass_height {
+                let row = match filtered.get(position..(position + 1 + size)) {
+                    Some(row) => row,
+                    None => break 'passes
+                };
+
+                position += 1 + size;
+
+                let mut current = row[1..].to_vec();
+
+                if let Err(e) = Self::png_unfilter(row[0], &mut current, &previous, filter_distance) {
+                    match is_lenient {
+                        true => break 'passes,
+                        false => return Err(e)
+                    }
+                }
+
+                //Get the sample at the given index in the row
+                let sample = |index: usize| -> u16 {
+                    match bit_depth {
+                        16 => u16::from_be_bytes([current[index * 2], current[index * 2 + 1]]),
+                        8 => current[index] as u16,
+                        _ => {
+                            let bit = index * bit_depth;
+                            ((current[bit / 8] >> (8 - bit_depth - (bit % 8))) as u16) & max_sample
+                        }
+                    }
+                };
+
+                let y = y0 + (pass_y * dy);
+
+                for pass_x in 0..pass_width {
+                    let x = x0 + (pass_x * dx);
+                    let first = pass_x * channels;
+                    let target = (y * width) + x;
+
+                    if is_palette {
+                        indices[target] = Some(sample(first) as u8);
+                    }
+                    else {
+                        let samples: Vec<u16> = (first..(first + channels)).map(sample).collect();
+
+                        let is_transparent = transparent_samples.as_ref()
+                            .is_some_and(|key| key.len() == samples.len() && key.iter().zip(samples.iter()).all(|(a, b)| a == b));
+
+                        pixels[target] = match samples[..] {
+                            [grey] => RGBColor { red: scale(grey), green: scale(grey), blue: scale(grey), alpha: 0xFF },
+                            [grey, alpha] => RGBColor { red: scale(grey), green: scale(grey), blue: scale(grey), alpha: scale(alpha) },
+                            [red, green, blue] => RGBColor { red: scale(red), green: scale(green), blue: scale(blue), alpha: 0xFF },
+                            _ => RGBColor { red: scale(samples[0]), green: scale(samples[1]), blue: scale(samples[2]), alpha: scale(samples[3]) }
+                        };
+
+                        if is_transparent {
+                            pixels[target].alpha = 0;
+                        }
+                    }
+                }
+
+                decoded_pixels += pass_width;
+                previous = current;
+            }
+        }
+
+        let missing_pixels = pixel_count - decoded_pixels;
+
+        if missing_pixels > 0 && !is_lenient {
+            return Err(BmpError::Truncated {
+                needed: filtered_size,
+                available: filtered.len()
+            });
+        }
+
+        let mut warnings: Vec<DecodeWarning> = Vec::new();
+
+        let (bits_per_pixel, color_table, pixel_data) = match is_palette {
+            true => {
+                let out_of_range_pixels = indices.iter().flatten().filter(|index| (**index as usize) >= palette.len()).count();
+
+                //A BMP has no 2 bpp, so those are widened to 4
+                let bits_per_pixel = match bit_depth {
+                    1 => 1,
+                    2 | 4 => 4,
+                    _ => 8
+                };
+
+                let color_table = BitMapColorTable {
+                    colors: palette
+                };
+
+                if out_of_range_pixels > 0 && !is_lenient {
+                    let index = *indices.iter().flatten().find(|index| (**index as usize) >= color_table.colors.len()).unwrap_or(&0) as usize;
+
+                    return Err(BmpError::PaletteIndexOutOfRange {
+                        index,
+                        palette_size: color_table.colors.len()
+                    });
+                }
+
+                //Pixels which were never decoded, or which are outside of the palette, get the fill color
+                match missing_pixels + out_of_range_pixels {
+                    0 => (bits_per_pixel, color_table, BitMapPixelData::Indexed(indices.into_iter().flatten().collect())),
+                    _ => {
+                        let colors = indices.iter()
+                            .map(|index| index.and_then(|index| color_table.colors.get(index as usize)).cloned().unwrap_or(settings.fill_color.clone()))
+                            .collect();
+
+                        if out_of_range_pixels > 0 {
+                            warnings.push(DecodeWarning::PaletteTooShort {
+                                palette_size: color_table.colors.len(),
+                                out_of_range_pixels
+                            });
+                        }
+
+                        (bits_per_pixel, color_table, BitMapPixelData::Color(colors))
+                    }
+                }
+            },
+            false => {
+                let has_alpha = matches!(header.color_type, PngColorType::GreyscaleAlpha | PngColorType::Rgba) || transparent_samples.is_some();
+
+                let bits_per_pixel = match has_alpha {
+                    true => 32,
+                    false => 24
+                };
+
+                (bits_per_pixel, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(pixels))
+            }
+        };
+
+        if missing_pixels > 0 {
+            warnings.push(DecodeWarning::TruncatedPixelData { missing_pixels });
+        }
+
+        let mut bitmap = Self::from_pixels(header.width, header.height, bits_per_pixel, color_table, pixel_data)?;
+        bitmap.warnings = warnings;
+
+        Ok(bitmap)
+    }
+
+    /// 
+    /// Read and validate the contents of an IHDR chunk
+    /// 
+    fn read_png_header(data: &[u8]) -> Result<PngHeader, BmpError> {
+        if data.len() != 13 {
+            return Err(BmpError::MalformedHeader(format!("IHDR chunk should be 13 bytes, but is {}.", data.len())));
+        }
+
+        let width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        let height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let bit_depth = data[8];
+
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return Err(BmpError::InvalidDimensions {
+                width: width.min(i32::MAX as u32) as i32,
+                height: height.min(i32::MAX as u32) as i32
+            });
+        }
+
+        let color_type = match PngColorType::from_u8(data[9]) {
+            Some(color_type) => color_type,
+            None => return Err(BmpError::MalformedHeader(format!("{} is not a valid color type.", data[9])))
+        };
+
+        if !color_type.allows_bit_depth(bit_depth) {
+            return Err(BmpError::UnsupportedBitDepth(bit_depth as u16));
+        }
+
+        //Deflate and adaptive filtering are the only methods defined
+        if data[10] != 0 {
+            return Err(BmpError::UnsupportedCompression(data[10] as u32));
+        }
+
+        if data[11] != 0 {
+            return Err(BmpError::MalformedHeader(format!("{} is not a valid filter method.", data[11])));
+        }
+
+        let interlaced = match data[12] {
+            0 => false,
+            1 => true,
+            method => return Err(BmpError::MalformedHeader(format!("{method} is not a valid interlace method.")))
+        };
+
+        Ok(PngHeader {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            interlaced
+        })
+    }
+
+    /// 
+    /// Reverse the filter of the given type on a row, in place, given the unfiltered row before it.
+    /// The distance is the number of bytes in a pixel, rounded up to 1.
+    /// 
+    fn png_unfilter(filter: u8, row: &mut [u8], previous: &[u8], distance: usize) -> Result<(), BmpError> {
+        match filter {
+            //None
+            0 => {},
+            //Sub: the byte to the left
+            1 => {
+                for ndx in distance..row.len() {
+                    row[ndx] = row[ndx].wrapping_add(row[ndx - distance]);
+                }
+            },
+            //Up: the byte above
+            2 => {
+                for ndx in 0..row.len() {
+                    row[ndx] = row[ndx].wrapping_add(previous[ndx]);
+                }
+            },
+            //Average: the mean of the bytes to the left and above
+            3 => {
+                for ndx in 0..row.len() {
+                    let left = match ndx >= distance {
+                        true => row[ndx - distance],
+                        false => 0
+                    };
+
+                    row[ndx] = row[ndx].wrapping_add(((left as u16 + previous[ndx] as u16) / 2) as u8);
+                }
+            },
+            //Paeth
+            4 => {
+                for ndx in 0..row.len() {
+                    let (left, upper_left) = match ndx >= distance {
+                        true => (row[ndx - distance], previous[ndx - distance]),
+                        false => (0, 0)
+                    };
+
+                    row[ndx] = row[ndx].wrapping_add(Self::png_paeth(left, previous[ndx], upper_left));
+                }
+            },
+            _ => return Err(BmpError::CorruptData(format!("{filter} is not a valid filter type.")))
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+use std::io::{Write, BufWriter};
+use std::fs::File;
+use super::*;
+
+/// 
+/// Settings for BitMapRaw::write_png_to
+/// 
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct PngWriteSettings {
+    /// 
+    /// The color type to write
+    /// 
+    color_type: PngColorType,
+    /// 
+    /// The number of bits in each sample. Must be allowed by the color type.
+    /// 
+    bit_depth: u8,
+    /// 
+    /// Whether to write the image with Adam7 interlacing
+    /// 
+    interlaced: bool,
+    /// 
+    /// How to choose the palette when writing a palette image with more colors than fit
+    /// 
+    quantization: BitMapQuantization,
+    /// 
+    /// The algorithm to use to find the closest palette color to each pixel
+    /// 
+    algorithm: fn(&RGBColor, &RGBColor) -> f32
+}
+
+impl PngWriteSettings {
+    /// 
+    /// Create a new instance of PngWriteSettings with the
+    /// given settings
+    /// 
+    pub fn new(color_type: PngColorType, bit_depth: u8, interlaced: bool, quantization: BitMapQuantization, algorithm: fn(&RGBColor, &RGBColor) -> f32) -> Self {
+        PngWriteSettings {
+            color_type,
+            bit_depth,
+            interlaced,
+            quantization,
+            algorithm
+        }
+    }
+
+    pub fn with_color_type(&mut self, color_type: PngColorType) -> &Self {
+        self.color_type = color_type;
+        self
+    }
+
+    pub fn with_bit_depth(&mut self, bit_depth: u8) -> &Self {
+        self.bit_depth = bit_depth;
+        self
+    }
+
+    pub fn with_interlaced(&mut self, interlaced: bool) -> &Self {
+        self.interlaced = interlaced;
+        self
+    }
+
+    pub fn with_quantization(&mut self, quantization: BitMapQuantization) -> &Self {
+        self.quantization = quantization;
+        self
+    }
+
+    pub fn with_algorithm(&mut self, algorithm: fn(&RGBColor, &RGBColor) -> f32) -> &Self {
+        self.algorithm = algorithm;
+        self
+    }
+}
+
+impl Default for PngWriteSettings {
+    /// 
+    /// 8-bit RGBA, without interlacing
+    /// 
+    fn default() -> Self {
+        Self::new(PngColorType::Rgba, 8, false, BitMapQuantization::MedianCut, RGBColor::get_euclidean_distance_rgb)
+    }
+}
+
+impl BitMapRaw {
+    /// 
+    /// The most image data to put in a single IDAT chunk
+    /// 
+    const PNG_MAX_IDAT_SIZE: usize = 0x10000;
+
+    /// 
+    /// Write the image to the file at the given path as a PNG, with the given settings
+    /// 
+    pub fn write_png_to_file(&self, path: &str, settings: &PngWriteSettings) -> Result<(), BmpError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        self.write_png_to(&mut writer, settings)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// 
+    /// Write the image to the given destination as a PNG, with the given settings.
+    /// 
+    /// Greyscale is the luminance of each pixel. Palette images use the color table of an indexed
+    /// bitmap if every index fits in the bit depth, and otherwise quantize the image; only a color
+    /// table that is kept keeps its alpha.
+    /// 
+    pub fn write_png_to<W: Write>(&self, writer: &mut W, settings: &PngWriteSettings) -> Result<(), BmpError> {
+        if !settings.color_type.allows_bit_depth(settings.bit_depth) {
+            return Err(BmpError::UnsupportedBitDepth(settings.bit_depth as u16));
+        }
+
+        let width = self.info_header.width.unsigned_abs() as usize;
+        let height = self.info_header.height.unsigned_abs() as usize;
+
+        let pixel_count = width.checked_mul(height).ok_or(BmpError::InvalidDimensions {
+            width: self.info_header.width,
+            height: self.info_header.height
+        })?;
+
+        if width == 0 || height == 0 {
+            return Err(BmpError::InvalidDimensions {
+                width: self.info_header.width,
+                height: self.info_header.height
+            });
+        }
+
+        if self.pixel_data.len() != pixel_count {
+            return Err(BmpError::PixelCountMismatch {
+                expected: pixel_count,
+                actual: self.pixel_data.len()
+            });
+        }
+
+        let bit_depth = settings.bit_depth as usize;
+        let channels = settings.color_type.channels();
+        let max_sample = ((1_u32 << bit_depth) - 1) as u16;
+
+        //The palette and the index of each pixel in the pixel data, for palette images
+        let (palette, indices) = match settings.color_type {
+            PngColorType::Palette => self.quantize(1 << bit_depth, settings.quantization, settings.algorithm),
+            _ => (Vec::new(), Vec::new())
+        };
+
+        //Scale a channel from 8 bits to the bit depth
+        let scale = |channel: u8| -> u16 {
+            ((channel as u32 * max_sample as u32 + 127) / 255) as u16
+        };
+
+        //Get the samples of the pixel at the given position
+        let samples = |x: usize, y: usize| -> Vec<u16> {
+            let index = self.get_pixel_index(x, y).unwrap_or(0);
+
+            let pixel = self.get_pixel(index).unwrap_or(RGBColor {
+                red: 0,
+                green: 0,
+                blue: 0,
+                alpha: 0
+            });
+
+            match settings.color_type {
+                PngColorType::Palette => vec![indices.get(index).copied().unwrap_or(0) as u16],
+                PngColorType::Greyscale => vec![scale(pixel.get_luminance())],
+                PngColorType::GreyscaleAlpha => vec![scale(pixel.get_luminance()), scale(pixel.alpha)],
+                PngColorType::Rgb => vec![scale(pixel.red), scale(pixel.green), scale(pixel.blue)],
+                PngColorType::Rgba => vec![scale(pixel.red), scale(pixel.green), scale(pixel.blue), scale(pixel.alpha)]
+            }
+        };
+
+        //
+        // Build the filtered image data, pass by pass. Each row is its filter type, followed by the filtered row.
+        // Palette images and images under 8 bits are not filtered, since filtering rarely makes them smaller.
+        //
+        let should_filter = settings.color_type != PngColorType::Palette && bit_depth >= 8;
+        let filter_distance = usize::max(1, (channels * bit_depth) / 8);
+
+        let mut filtered: Vec<u8> = Vec::new();
+
+        for (x0, y0, dx, dy) in Self::png_passes(settings.interlaced) {
+            let pass_width = Self::png_pass_size(width, x0, dx);
+            let pass_height = Self::png_pass_size(height, y0, dy);
+
+            if pass_width == 0 || pass_height == 0 {
+                continue;
+            }
+
+            let row_size = (pass_width * channels * bit_depth).div_ceil(8);
+            let mut previous: Vec<u8> = vec![0; row_size];
+
+            for pass_y in 0..pass_height {
+                let y = y0 + (pass_y * dy);
+                let mut row: Vec<u8> = vec![0; row_size];
+                let mut bit = 0_usize;
+
+                for pass_x in 0..pass_width {
+                    for sample in samples(x0 + (pass_x * dx), y) {
+                        match bit_depth {
+                            16 => row[(bit / 8)..(bit / 8 + 2)].copy_from_slice(&sample.to_be_bytes()),
+                            8 => row[bit / 8] = sample as u8,
+                            //Samples under 8 bits are packed from the high bit down
+                            _ => row[bit / 8] |= (sample as u8) << (8 - bit_depth - (bit % 8))
+                        }
+
+                        bit += bit_depth;
+                    }
+                }
+
+                let (filter, filtered_row) = match should_filter {
+                    true => Self::png_filter(&row, &previous, filter_distance),
+                    false => (0, row.clone())
+                };
+
+                filtered.push(filter);
+                filtered.extend_from_slice(&filtered_row);
+                previous = row;
+            }
+        }
+
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&filtered, 6);
+
+        //Write the signature, then each chunk
+        writer.write_all(&Self::PNG_SIGNATURE)?;
+
+        let mut header: Vec<u8> = Vec::with_capacity(13);
+        header.extend_from_slice(&(width as u32).to_be_bytes());
+        header.extend_from_slice(&(height as u32).to_be_bytes());
+        header.extend_from_slice(&[settings.bit_depth, settings.color_type.to_u8(), 0, 0, settings.interlaced as u8]);
+
+        Self::write_png_chunk(writer, b"IHDR", &header)?;
+
+        if settings.color_type == PngColorType::Palette {
+            let colors: Vec<u8> = palette.iter()
+                .flat_map(|color| [color.red, color.green, color.blue])
+                .collect();
+
+            Self::write_png_chunk(writer, b"PLTE", &colors)?;
+
+            //Only write the alpha of each palette entry up to the last which is not opaque
+            let alphas: Vec<u8> = palette.iter().map(|color| color.alpha).collect();
+
+            if let Some(last) = alphas.iter().rposition(|alpha| *alpha != 0xFF) {
+                Self::write_png_chunk(writer, b"tRNS", &alphas[..=last])?;
+            }
+        }
+
+        for data in compressed.chunks(Self::PNG_MAX_IDAT_SIZE) {
+            Self::write_png_chunk(writer, b"IDAT", data)?;
+        }
+
+        Self::write_png_chunk(writer, b"IEND", &[])?;
+
+        Ok(())
+    }
+
+    /// 
+    /// Write a chunk with the given type and data, along with its length and CRC
+    /// 
+    fn write_png_chunk<W: Write>(writer: &mut W, chunk_type: &[u8; 4], data: &[u8]) -> Result<(), BmpError> {
+        writer.write_all(&(data.len() as u32).to_be_bytes())?;
+        writer.write_all(chunk_type)?;
+        writer.write_all(data)?;
+        writer.write_all(&Self::png_crc(&[chunk_type, data]).to_be_bytes())?;
+
+        Ok(())
+    }
+
+    /// 
+    /// Filter a row with whichever filter type gives the smallest sum of absolute differences,
+    /// returning the filter type and the filtered row
+    /// 
+    fn png_filter(row: &[u8], previous: &[u8], distance: usize) -> (u8, Vec<u8>) {
+        (0..=4_u8)
+            .map(|filter| {
+                let filtered: Vec<u8> = (0..row.len())
+                    .map(|ndx| {
+                        let (left, upper_left) = match ndx >= distance {
+                            true => (row[ndx - distance], previous[ndx - distance]),
+                            false => (0, 0)
+                        };
+
+                        let above = previous[ndx];
+
+                        let prediction = match filter {
+                            0 => 0,
+                            1 => left,
+                            2 => above,
+                            3 => ((left as u16 + above as u16) / 2) as u8,
+                            _ => Self::png_paeth(left, above, upper_left)
+                        };
+
+                        row[ndx].wrapping_sub(prediction)
+                    })
+                    .collect();
+
+                (filter, filtered)
+            })
+            .min_by_key(|(_, filtered)| filtered.iter().map(|byte| (*byte as i8).unsigned_abs() as usize).sum::<usize>())
+            .unwrap_or((0, row.to_vec()))
+    }
+}
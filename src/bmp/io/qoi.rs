@@ -0,0 +1,397 @@
+use std::io::{Write, BufReader, BufWriter};
+use std::fs::File;
+use super::super::*;
+
+/// 
+/// The channels stored in a QOI image
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QoiChannels {
+    /// 
+    /// Red, green and blue. Every pixel is written as opaque.
+    /// 
+    Rgb,
+    /// 
+    /// Red, green, blue and alpha
+    /// 
+    #[default]
+    Rgba
+}
+
+/// 
+/// The color space recorded in a QOI image. This is only informative; it does not change how pixels are stored.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QoiColorSpace {
+    /// 
+    /// sRGB color channels, with a linear alpha channel
+    /// 
+    #[default]
+    Srgb,
+    /// 
+    /// Every channel is linear
+    /// 
+    Linear
+}
+
+/// 
+/// Settings for BitMapRaw::write_qoi_to
+/// 
+#[allow(dead_code)]
+#[derive(Clone, Default)]
+pub struct QoiWriteSettings {
+    /// 
+    /// The channels to write
+    /// 
+    channels: QoiChannels,
+    /// 
+    /// The color space to record in the header
+    /// 
+    color_space: QoiColorSpace
+}
+
+impl QoiWriteSettings {
+    /// 
+    /// Create a new instance of QoiWriteSettings with the
+    /// given settings
+    /// 
+    pub fn new(channels: QoiChannels, color_space: QoiColorSpace) -> Self {
+        QoiWriteSettings {
+            channels,
+            color_space
+        }
+    }
+
+    pub fn with_channels(&mut self, channels: QoiChannels) -> &Self {
+        self.channels = channels;
+        self
+    }
+
+    pub fn with_color_space(&mut self, color_space: QoiColorSpace) -> &Self {
+        self.color_space = color_space;
+        self
+    }
+}
+
+impl BitMapRaw {
+    /// 
+    /// The 4 bytes every QOI file begins with
+    /// 
+    pub const QOI_SIGNATURE: [u8; 4] = *b"qoif";
+
+    /// 
+    /// The size of the QOI header: the signature, width, height, channels and color space
+    /// 
+    const QOI_HEADER_SIZE: usize = 14;
+
+    /// 
+    /// The 8 bytes every QOI file ends with
+    /// 
+    const QOI_END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+    const QOI_OP_INDEX: u8 = 0x00;
+    const QOI_OP_DIFF: u8 = 0x40;
+    const QOI_OP_LUMA: u8 = 0x80;
+    const QOI_OP_RUN: u8 = 0xC0;
+    const QOI_OP_RGB: u8 = 0xFE;
+    const QOI_OP_RGBA: u8 = 0xFF;
+
+    /// 
+    /// The position of a color in the array of recently seen colors
+    /// 
+    fn qoi_hash(color: &RGBColor) -> usize {
+        ((color.red as usize * 3) + (color.green as usize * 5) + (color.blue as usize * 7) + (color.alpha as usize * 11)) % 64
+    }
+
+    /// 
+    /// Read a QOI image from the file at the given path
+    /// 
+    pub fn read_qoi_from_file(path: &str) -> Result<Self, BmpError> {
+        Self::read_qoi_from_file_with_settings(path, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read a QOI image from the file at the given path, with the given settings
+    /// 
+    pub fn read_qoi_from_file_with_settings(path: &str, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let buffer = Self::read_to_buffer(&mut BufReader::new(File::open(path)?), settings)?;
+
+        Self::from_qoi_bytes_with_settings(&buffer, settings)
+    }
+
+    /// 
+    /// Read a QOI image from the given bytes
+    /// 
+    pub fn from_qoi_bytes(buffer: &[u8]) -> Result<Self, BmpError> {
+        Self::from_qoi_bytes_with_settings(buffer, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read a QOI image from the given bytes, with the given settings.
+    /// 4-channel images are 32 bpp, and 3-channel images are 24 bpp.
+    /// 
+    pub fn from_qoi_bytes_with_settings(buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        if buffer.len() < Self::QOI_HEADER_SIZE {
+            return Err(BmpError::Truncated {
+                needed: Self::QOI_HEADER_SIZE,
+                available: buffer.len()
+            });
+        }
+
+        if buffer[0..4] != Self::QOI_SIGNATURE {
+            return Err(BmpError::BadSignature(u16::from_le_bytes([buffer[0], buffer[1]])));
+        }
+
+        let width = u32::from_be_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]);
+        let height = u32::from_be_bytes([buffer[8], buffer[9], buffer[10], buffer[11]]);
+        let channels = buffer[12];
+
+        if channels != 3 && channels != 4 {
+            return Err(BmpError::MalformedHeader(format!("{channels} is not a valid number of channels.")));
+        }
+
+        if buffer[13] > 1 {
+            return Err(BmpError::MalformedHeader(format!("{} is not a valid color space.", buffer[13])));
+        }
+
+        let pixel_count = (width as usize).checked_mul(height as usize).ok_or(BmpError::InvalidDimensions {
+            width: width.min(i32::MAX as u32) as i32,
+            height: height.min(i32::MAX as u32) as i32
+        })?;
+
+        DecodeLimits::check(DecodeLimit::Width, width as u64, settings.limits.max_width as u64)?;
+        DecodeLimits::check(DecodeLimit::Height, height as u64, settings.limits.max_height as u64)?;
+        DecodeLimits::check(DecodeLimit::Pixels, pixel_count as u64, settings.limits.max_pixels)?;
+        DecodeLimits::check(DecodeLimit::AllocBytes, (pixel_count as u64).saturating_mul(std::mem::size_of::<RGBColor>() as u64), settings.limits.max_alloc_bytes)?;
+
+        let mut pixels: Vec<RGBColor> = Vec::with_capacity(pixel_count);
+
+        //Colors seen recently, by their hash, and the previous pixel
+        let mut seen: Vec<RGBColor> = vec![RGBColor { red: 0, green: 0, blue: 0, alpha: 0 }; 64];
+        let mut previous = RGBColor { red: 0, green: 0, blue: 0, alpha: 0xFF };
+
+        //The pixel data ends at the end marker
+        let data = &buffer[Self::QOI_HEADER_SIZE..];
+        let mut offset = 0;
+
+        while pixels.len() < pixel_count {
+            let op = match data.get(offset) {
+                Some(op) => *op,
+                None => break
+            };
+
+            //The number of bytes the op takes, including itself
+            let size = match op {
+                Self::QOI_OP_RGB => 4,
+                Self::QOI_OP_RGBA => 5,
+                _ if op & 0xC0 == Self::QOI_OP_LUMA => 2,
+                _ => 1
+            };
+
+            let bytes = match data.get(offset..(offset + size)) {
+                Some(bytes) => bytes,
+                None => break
+            };
+
+            offset += size;
+
+            let mut color = previous.clone();
+            let mut run = 1;
+
+            match op {
+                Self::QOI_OP_RGB => {
+                    color.red = bytes[1];
+                    color.green = bytes[2];
+                    color.blue = bytes[3];
+                },
+                Self::QOI_OP_RGBA => {
+                    color.red = bytes[1];
+                    color.green = bytes[2];
+                    color.blue = bytes[3];
+                    color.alpha = bytes[4];
+                },
+                _ => match op & 0xC0 {
+                    Self::QOI_OP_INDEX => color = seen[(op & 0x3F) as usize].clone(),
+                    //The difference of each channel from the previous pixel, from -2 to 1
+                    Self::QOI_OP_DIFF => {
+                        color.red = color.red.wrapping_add((op >> 4) & 0x03).wrapping_sub(2);
+                        color.green = color.green.wrapping_add((op >> 2) & 0x03).wrapping_sub(2);
+                        color.blue = color.blue.wrapping_add(op & 0x03).wrapping_sub(2);
+                    },
+                    //The difference of green from -32 to 31, and of red and blue relative to green, from -8 to 7
+                    Self::QOI_OP_LUMA => {
+                        let green_difference = (op & 0x3F).wrapping_sub(32);
+
+                        color.red = color.red.wrapping_add(green_difference).wrapping_add(bytes[1] >> 4).wrapping_sub(8);
+                        color.green = color.green.wrapping_add(green_difference);
+                        color.blue = color.blue.wrapping_add(green_difference).wrapping_add(bytes[1] & 0x0F).wrapping_sub(8);
+                    },
+                    //The previous pixel repeated 1 to 62 times
+                    _ => run = ((op & 0x3F) + 1) as usize
+                }
+            }
+
+            seen[Self::qoi_hash(&color)] = color.clone();
+
+            for _ in 0..usize::min(run, pixel_count - pixels.len()) {
+                pixels.push(color.clone());
+            }
+
+            previous = color;
+        }
+
+        let missing_pixels = pixel_count - pixels.len();
+
+        if missing_pixels > 0 {
+            if settings.mode == DecodeMode::Strict {
+                return Err(BmpError::Truncated {
+                    needed: Self::QOI_HEADER_SIZE + offset + 1,
+                    available: buffer.len()
+                });
+            }
+
+            pixels.resize(pixel_count, settings.fill_color.clone());
+        }
+
+        let bits_per_pixel = match channels {
+            4 => 32,
+            _ => 24
+        };
+
+        let mut bitmap = Self::from_pixels(width, height, bits_per_pixel, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(pixels))?;
+
+        if missing_pixels > 0 {
+            bitmap.warnings.push(DecodeWarning::TruncatedPixelData { missing_pixels });
+        }
+
+        Ok(bitmap)
+    }
+
+    /// 
+    /// Write the image to the file at the given path as a QOI image, with the given settings
+    /// 
+    pub fn write_qoi_to_file(&self, path: &str, settings: &QoiWriteSettings) -> Result<(), BmpError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        self.write_qoi_to(&mut writer, settings)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// 
+    /// Write the image to the given destination as a QOI image, with the given settings
+    /// 
+    pub fn write_qoi_to<W: Write>(&self, writer: &mut W, settings: &QoiWriteSettings) -> Result<(), BmpError> {
+        let width = self.info_header.width.unsigned_abs() as usize;
+        let height = self.info_header.height.unsigned_abs() as usize;
+
+        let pixel_count = width.checked_mul(height).ok_or(BmpError::InvalidDimensions {
+            width: self.info_header.width,
+            height: self.info_header.height
+        })?;
+
+        if self.pixel_data.len() != pixel_count {
+            return Err(BmpError::PixelCountMismatch {
+                expected: pixel_count,
+                actual: self.pixel_data.len()
+            });
+        }
+
+        let mut data: Vec<u8> = Vec::with_capacity(Self::QOI_HEADER_SIZE + Self::QOI_END_MARKER.len() + pixel_count);
+
+        data.extend_from_slice(&Self::QOI_SIGNATURE);
+        data.extend_from_slice(&(width as u32).to_be_bytes());
+        data.extend_from_slice(&(height as u32).to_be_bytes());
+
+        data.push(match settings.channels {
+            QoiChannels::Rgb => 3,
+            QoiChannels::Rgba => 4
+        });
+
+        data.push(match settings.color_space {
+            QoiColorSpace::Srgb => 0,
+            QoiColorSpace::Linear => 1
+        });
+
+        let mut seen: Vec<RGBColor> = vec![RGBColor { red: 0, green: 0, blue: 0, alpha: 0 }; 64];
+        let mut previous = RGBColor { red: 0, green: 0, blue: 0, alpha: 0xFF };
+        let mut run: u8 = 0;
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut color = self.get_pixel_at(x, y).unwrap_or(RGBColor {
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                    alpha: 0
+                });
+
+                if settings.channels == QoiChannels::Rgb {
+                    color.alpha = 0xFF;
+                }
+
+                let is_repeat = color.red == previous.red && color.green == previous.green && color.blue == previous.blue && color.alpha == previous.alpha;
+
+                //Runs are at most 62 pixels, as 63 and 64 would be QOI_OP_RGB and QOI_OP_RGBA
+                if is_repeat {
+                    run += 1;
+
+                    if run == 62 {
+                        data.push(Self::QOI_OP_RUN | (run - 1));
+                        run = 0;
+                    }
+
+                    continue;
+                }
+
+                if run > 0 {
+                    data.push(Self::QOI_OP_RUN | (run - 1));
+                    run = 0;
+                }
+
+                let hash = Self::qoi_hash(&color);
+                let entry = &seen[hash];
+
+                if entry.red == color.red && entry.green == color.green && entry.blue == color.blue && entry.alpha == color.alpha {
+                    data.push(Self::QOI_OP_INDEX | hash as u8);
+                }
+                else if color.alpha == previous.alpha {
+                    let red_difference = color.red.wrapping_sub(previous.red) as i8;
+                    let green_difference = color.green.wrapping_sub(previous.green) as i8;
+                    let blue_difference = color.blue.wrapping_sub(previous.blue) as i8;
+
+                    let red_green_difference = red_difference.wrapping_sub(green_difference);
+                    let blue_green_difference = blue_difference.wrapping_sub(green_difference);
+
+                    if (-2..=1).contains(&red_difference) && (-2..=1).contains(&green_difference) && (-2..=1).contains(&blue_difference) {
+                        data.push(Self::QOI_OP_DIFF | (((red_difference + 2) as u8) << 4) | (((green_difference + 2) as u8) << 2) | ((blue_difference + 2) as u8));
+                    }
+                    else if (-32..=31).contains(&green_difference) && (-8..=7).contains(&red_green_difference) && (-8..=7).contains(&blue_green_difference) {
+                        data.push(Self::QOI_OP_LUMA | ((green_difference + 32) as u8));
+                        data.push((((red_green_difference + 8) as u8) << 4) | ((blue_green_difference + 8) as u8));
+                    }
+                    else {
+                        data.extend_from_slice(&[Self::QOI_OP_RGB, color.red, color.green, color.blue]);
+                    }
+                }
+                else {
+                    data.extend_from_slice(&[Self::QOI_OP_RGBA, color.red, color.green, color.blue, color.alpha]);
+                }
+
+                seen[hash] = color.clone();
+                previous = color;
+            }
+        }
+
+        if run > 0 {
+            data.push(Self::QOI_OP_RUN | (run - 1));
+        }
+
+        data.extend_from_slice(&Self::QOI_END_MARKER);
+
+        writer.write_all(&data)?;
+
+        Ok(())
+    }
+}
@@ -1,62 +1,509 @@
-use std::io::{self, Read, BufReader};
+use std::io::{Read, Seek, SeekFrom, BufReader};
 use std::fs::File;
+use std::mem;
 use super::super::*;
 
+mod huffman;
+mod rle;
+mod array;
+
+pub use array::*;
+
+///
+/// Limits on the size of the bitmaps that BitMapRaw will decode.
+/// Exceeding any of these fails the decode before the pixel data is allocated.
+/// 
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct DecodeLimits {
+    ///
+    /// The maximum width of the bitmap, in pixels
+    /// 
+    pub max_width: u32,
+    ///
+    /// The maximum height of the bitmap, in pixels
+    /// 
+    pub max_height: u32,
+    ///
+    /// The maximum number of pixels in the bitmap
+    /// 
+    pub max_pixels: u64,
+    ///
+    /// The maximum number of bytes to allocate while decoding,
+    /// including the source data
+    /// 
+    pub max_alloc_bytes: u64
+}
+
+impl DecodeLimits {
+    ///
+    /// Create a new instance of DecodeLimits with the given limits
+    /// 
+    pub fn new(max_width: u32, max_height: u32, max_pixels: u64, max_alloc_bytes: u64) -> Self {
+        DecodeLimits {
+            max_width,
+            max_height,
+            max_pixels,
+            max_alloc_bytes
+        }
+    }
+
+    ///
+    /// Limits which allow any bitmap that can be represented in memory
+    /// 
+    pub fn unlimited() -> Self {
+        Self::new(u32::MAX, u32::MAX, u64::MAX, u64::MAX)
+    }
+
+    ///
+    /// Make sure the given value does not exceed the given limit
+    /// 
+    pub(super) fn check(limit: DecodeLimit, value: u64, max: u64) -> Result<(), BmpError> {
+        if value > max {
+            Err(BmpError::LimitExceeded {
+                limit,
+                value,
+                max
+            })
+        }
+        else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for DecodeLimits {
+    ///
+    /// 65535x65535, up to 2^28 pixels, and up to 2GiB allocated
+    /// 
+    fn default() -> Self {
+        Self::new(0xFFFF, 0xFFFF, 1 << 28, 1 << 31)
+    }
+}
+
+///
+/// How BitMapRaw handles a bitmap that is damaged, but not beyond recovery
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecodeMode {
+    ///
+    /// Fail the decode on truncated pixel data or invalid palette indices
+    /// 
+    #[default]
+    Strict,
+    ///
+    /// Decode as much of the bitmap as possible, filling in anything missing
+    /// with the fill color and recording a DecodeWarning for each problem
+    /// 
+    Lenient
+}
+
+///
+/// Settings for BitMapRaw::from_bytes_with_settings
+/// 
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct BitMapRawReadSettings {
+    ///
+    /// Limits on the size of the bitmap
+    /// 
+    pub(super) limits: DecodeLimits,
+    ///
+    /// Whether to fail on damaged bitmaps, or recover what can be recovered
+    /// 
+    pub(super) mode: DecodeMode,
+    ///
+    /// The color of pixels that could not be recovered in lenient mode
+    /// 
+    pub(super) fill_color: RGBColor
+}
+
+impl BitMapRawReadSettings {
+    ///
+    /// Create a new instance of BitMapRawReadSettings with the
+    /// given settings
+    /// 
+    pub fn new(limits: DecodeLimits, mode: DecodeMode, fill_color: RGBColor) -> Self {
+        BitMapRawReadSettings {
+            limits,
+            mode,
+            fill_color
+        }
+    }
+
+    pub fn with_limits(&mut self, limits: DecodeLimits) -> &Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_mode(&mut self, mode: DecodeMode) -> &Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_fill_color(&mut self, fill_color: RGBColor) -> &Self {
+        self.fill_color = fill_color;
+        self
+    }
+}
+
+impl Default for BitMapRawReadSettings {
+    ///
+    /// Default limits, strict mode, and a transparent fill color
+    /// 
+    fn default() -> Self {
+        Self::new(DecodeLimits::default(), DecodeMode::Strict, RGBColor {
+            red: 0,
+            green: 0,
+            blue: 0,
+            alpha: 0
+        })
+    }
+}
+
 impl BitMapRaw {
-    pub fn read_from_file(path: &str) -> Result<Self, io::Error> {
+    ///
+    /// Read a bitmap from the file at the given path
+    /// 
+    pub fn read_from_file(path: &str) -> Result<Self, BmpError> {
+        Self::read_from_file_with_settings(path, &BitMapRawReadSettings::default())
+    }
+
+    ///
+    /// Read a bitmap from the file at the given path, with the given settings
+    /// 
+    pub fn read_from_file_with_settings(path: &str, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
         //Open the file
         let fs = File::open(path)?;
 
-        // //Get file metadata
-        // let file_metadata = File::metadata(&fs)?;
+        Self::from_reader_with_settings(&mut BufReader::new(fs), settings)
+    }
+
+    ///
+    /// Read a bitmap from the given source, starting at its current position
+    /// 
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Result<Self, BmpError> {
+        Self::from_reader_with_settings(reader, &BitMapRawReadSettings::default())
+    }
+
+    ///
+    /// Read a bitmap from the given source, starting at its current position, with the given settings
+    /// 
+    pub fn from_reader_with_settings<R: Read + Seek>(reader: &mut R, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let buffer = Self::read_to_buffer(reader, settings)?;
 
-        //Read file to buffer
-        let mut br = BufReader::new(fs);
+        Self::from_bytes_with_settings(&buffer, settings)
+    }
+
+    ///
+    /// Read the rest of the given source into a buffer, if it is within the allocation limit
+    /// 
+    pub fn read_to_buffer<R: Read + Seek>(reader: &mut R, settings: &BitMapRawReadSettings) -> Result<Vec<u8>, BmpError> {
+        //Get the number of bytes remaining in the source, and return to the current position
+        let start = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+
+        let length = end.saturating_sub(start);
+
+        DecodeLimits::check(DecodeLimit::AllocBytes, length, settings.limits.max_alloc_bytes)?;
+
+        //Read source to buffer
+        let mut buffer = Vec::with_capacity(length as usize);
+
+        reader.read_to_end(&mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    ///
+    /// Read the rest of the given stream into a buffer, for sources which cannot seek, such as stdin.
+    /// Reading stops once the stream passes the allocation limit, so it is never buffered in full.
+    /// 
+    pub fn read_stream_to_buffer<R: Read>(reader: &mut R, settings: &BitMapRawReadSettings) -> Result<Vec<u8>, BmpError> {
+        let max_bytes = settings.limits.max_alloc_bytes;
+
+        //Read at most one byte past the limit, which is enough to tell that the stream is over it
         let mut buffer = Vec::new();
+        reader.take(max_bytes.saturating_add(1)).read_to_end(&mut buffer)?;
+
+        DecodeLimits::check(DecodeLimit::AllocBytes, buffer.len() as u64, max_bytes)?;
+
+        Ok(buffer)
+    }
 
-        br.read_to_end(&mut buffer)?;
+    ///
+    /// Read a bitmap from the given bytes
+    /// 
+    pub fn from_bytes(buffer: &[u8]) -> Result<Self, BmpError> {
+        Self::from_bytes_with_settings(buffer, &BitMapRawReadSettings::default())
+    }
 
+    ///
+    /// Read a bitmap from the given bytes, with the given settings
+    /// 
+    pub fn from_bytes_with_settings(buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
         let mut offset: usize = 0x0;
-        fn get_next_n_bytes<'a, 'b>(buffer: &'a [u8], offset: &'b mut usize, n: usize) -> &'a [u8] {
-            let o_offset = *offset;
-            *offset += n;
-            &buffer[o_offset..*offset]
+
+        let header = Self::read_file_header(buffer, &mut offset)?;
+
+        //Problems which did not prevent the bitmap from being decoded
+        let mut warnings: Vec<DecodeWarning> = Vec::new();
+
+        //Some encoders leave the file size as 0
+        if header.file_size != 0 && header.file_size as usize != buffer.len() {
+            warnings.push(DecodeWarning::FileSizeMismatch {
+                declared: header.file_size,
+                actual: buffer.len()
+            });
+        }
+
+        Self::read_dib(buffer, offset, header, warnings, false, settings)
+            .map(|(bitmap, _)| bitmap)
+    }
+
+    ///
+    /// Read the file header of a bitmap from the given bytes, starting at the given offset, and move the offset past it
+    /// 
+    pub(super) fn read_file_header(buffer: &[u8], offset: &mut usize) -> Result<BitMapHeader, BmpError> {
+        let signature = Self::reduce_bit_slice_u16(Self::get_next_n_bytes(buffer, offset, 2)?);
+
+        if signature != BitMapHeader::SIGNATURE {
+            return Err(BmpError::BadSignature(signature));
+        }
+
+        Ok(BitMapHeader {
+            signature,
+            file_size: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(buffer, offset, 4)?),
+            reserved: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(buffer, offset, 4)?),
+            data_offset: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(buffer, offset, 4)?)
+        })
+    }
+
+    ///
+    /// Read the DIB of a bitmap, which is the info header, color table and pixel data, from the given bytes,
+    /// starting at the given offset. The data offset in the given file header counts from the start of the bytes.
+    /// 
+    /// The DIBs in icons and cursors have no file header. For them, the color table is complete and the pixel data
+    /// follows it, the height counts both the pixel data and the AND mask after it, and the pixel data can't be
+    /// compressed. The AND mask is left to the caller to read. Their data offset is set to where the pixel data would be
+    /// if a file header came before the DIB, counting from the start of the DIB rather than of the given bytes.
+    /// 
+    /// Returns the bitmap, and the offset of the end of its pixel data.
+    /// 
+    pub(super) fn read_dib(buffer: &[u8], offset: usize, header: BitMapHeader, warnings: Vec<DecodeWarning>, is_icon: bool, settings: &BitMapRawReadSettings) -> Result<(Self, usize), BmpError> {
+        let dib_start = offset;
+        let mut offset = offset;
+        let mut header = header;
+        let mut warnings = warnings;
+        let lenient = settings.mode == DecodeMode::Lenient;
+
+        let info_header_size = Self::reduce_bit_slice_u32(Self::get_next_n_bytes(buffer, &mut offset, 4)?);
+
+        //The smallest headers are the 12 byte OS/2 1.x header, and the truncated 16 byte OS/2 2.x header
+        if info_header_size != BitMapInfoHeader::SIZE_CORE && info_header_size < 16 {
+            return Err(BmpError::UnsupportedHeaderSize(info_header_size));
+        }
+
+        //
+        // The rest of the info header. OS/2 2.x headers may be truncated to as few as 16 bytes,
+        // in which case the missing fields are 0, so pad the header out to the largest OS/2 header size.
+        //
+        let mut info_bytes: Vec<u8> = Self::get_next_n_bytes(buffer, &mut offset, (info_header_size as usize) - 4)?.to_vec();
+        if info_bytes.len() < (BitMapInfoHeader::SIZE_OS2 as usize) - 4 {
+            info_bytes.resize((BitMapInfoHeader::SIZE_OS2 as usize) - 4, 0);
+        }
+
+        let mut info_offset: usize = 0x0;
+
+        let mut info_header = if info_header_size == BitMapInfoHeader::SIZE_CORE {
+            //BITMAPCOREHEADER: 16 bit width and height, followed by planes and bit depth
+            BitMapInfoHeader {
+                size: info_header_size,
+                width: Self::reduce_bit_slice_u16(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 2)?) as i32,
+                height: Self::reduce_bit_slice_u16(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 2)?) as i32,
+                planes: Self::reduce_bit_slice_u16(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 2)?),
+                bits_per_pixel: Self::reduce_bit_slice_u16(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 2)?),
+                compression: BitMapInfoHeader::BI_RGB,
+                image_size: 0,
+                x_pixels_per_meter: 0,
+                y_pixels_per_meter: 0,
+                colors_used: 0,
+                important_colors: 0
+            }
+        }
+        else {
+            BitMapInfoHeader {
+                size: info_header_size,
+                width: Self::reduce_bit_slice_i32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                height: Self::reduce_bit_slice_i32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                planes: Self::reduce_bit_slice_u16(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 2)?),
+                bits_per_pixel: Self::reduce_bit_slice_u16(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 2)?),
+                compression: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                image_size: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                x_pixels_per_meter: Self::reduce_bit_slice_i32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                y_pixels_per_meter: Self::reduce_bit_slice_i32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                colors_used: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                important_colors: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?)
+            }
+        };
+
+        //The height of an icon counts the AND mask as well as the image
+        if is_icon {
+            info_header.height /= 2;
+        }
+
+        //OS/2 2.x: additional fields describing units, halftoning and color encoding
+        let os2_header: Option<BitMapOs2Header> = if info_header.is_os2() && info_header.size != BitMapInfoHeader::SIZE_CORE {
+            Some(BitMapOs2Header {
+                units: Self::reduce_bit_slice_u16(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 2)?),
+                reserved: Self::reduce_bit_slice_u16(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 2)?),
+                recording: Self::reduce_bit_slice_u16(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 2)?),
+                rendering: Self::reduce_bit_slice_u16(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 2)?),
+                size1: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                size2: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                color_encoding: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                identifier: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?)
+            })
+        }
+        else {
+            None
+        };
+
+        //
+        // Channel masks. BITMAPV2INFOHEADER and later store the red, green and blue masks right after the
+        // 40 byte info header fields, and BITMAPV3INFOHEADER and later also store the alpha mask.
+        // For a BITMAPINFOHEADER with BI_BITFIELDS, the red, green and blue masks follow the header instead.
+        //
+        let header_masks: Option<BitMapChannelMasks> = if info_header.is_os2() {
+            None
+        }
+        else if [BitMapInfoHeader::SIZE_V2, BitMapInfoHeader::SIZE_V3].contains(&info_header.size)
+            || info_header.size >= BitMapInfoHeader::SIZE_V4 {
+            Some(BitMapChannelMasks {
+                red: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                green: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                blue: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                alpha: match info_header.size {
+                    x if x >= BitMapInfoHeader::SIZE_V3 => Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                    _ => 0
+                }
+            })
         }
+        else if info_header.compression == BitMapInfoHeader::BI_BITFIELDS {
+            Some(BitMapChannelMasks {
+                red: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(buffer, &mut offset, 4)?),
+                green: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(buffer, &mut offset, 4)?),
+                blue: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(buffer, &mut offset, 4)?),
+                alpha: 0
+            })
+        }
+        else {
+            None
+        };
+
+        //BITMAPV4HEADER: masks, followed by color space information
+        let v4_header: Option<BitMapV4Header> = match &header_masks {
+            Some(masks) if info_header.size >= BitMapInfoHeader::SIZE_V4 => {
+                let cs_type = Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?);
+
+                let mut read_cie_xyz = || -> Result<BitMapCieXyz, BmpError> {
+                    Ok(BitMapCieXyz {
+                        x: Self::reduce_bit_slice_i32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                        y: Self::reduce_bit_slice_i32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                        z: Self::reduce_bit_slice_i32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?)
+                    })
+                };
 
-        let header = BitMapHeader {
-            signature: Self::reduce_bit_slice_u16(get_next_n_bytes(&buffer, &mut offset, 2)),
-            file_size: Self::reduce_bit_slice_u32(get_next_n_bytes(&buffer, &mut offset, 4)),
-            reserved: Self::reduce_bit_slice_u32(get_next_n_bytes(&buffer, &mut offset, 4)),
-            data_offset: Self::reduce_bit_slice_u32(get_next_n_bytes(&buffer, &mut offset, 4))
+                let endpoints = BitMapCieXyzTriple {
+                    red: read_cie_xyz()?,
+                    green: read_cie_xyz()?,
+                    blue: read_cie_xyz()?
+                };
+
+                Some(BitMapV4Header {
+                    red_mask: masks.red,
+                    green_mask: masks.green,
+                    blue_mask: masks.blue,
+                    alpha_mask: masks.alpha,
+                    cs_type,
+                    endpoints,
+                    gamma_red: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                    gamma_green: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                    gamma_blue: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?)
+                })
+            },
+            _ => None
         };
 
-        let info_header = BitMapInfoHeader {
-            size: Self::reduce_bit_slice_u32(get_next_n_bytes(&buffer, &mut offset, 4)),
-            width: Self::reduce_bit_slice_i32(get_next_n_bytes(&buffer, &mut offset, 4)),
-            height: Self::reduce_bit_slice_i32(get_next_n_bytes(&buffer, &mut offset, 4)),
-            planes: Self::reduce_bit_slice_u16(get_next_n_bytes(&buffer, &mut offset, 2)),
-            bits_per_pixel: Self::reduce_bit_slice_u16(get_next_n_bytes(&buffer, &mut offset, 2)),
-            compression: Self::reduce_bit_slice_u32(get_next_n_bytes(&buffer, &mut offset, 4)),
-            image_size: Self::reduce_bit_slice_u32(get_next_n_bytes(&buffer, &mut offset, 4)),
-            x_pixels_per_meter: Self::reduce_bit_slice_i32(get_next_n_bytes(&buffer, &mut offset, 4)),
-            y_pixels_per_meter: Self::reduce_bit_slice_i32(get_next_n_bytes(&buffer, &mut offset, 4)),
-            colors_used: Self::reduce_bit_slice_u32(get_next_n_bytes(&buffer, &mut offset, 4)),
-            important_colors: Self::reduce_bit_slice_u32(get_next_n_bytes(&buffer, &mut offset, 4))
+        //BITMAPV5HEADER: V4 fields, followed by rendering intent and ICC profile location
+        let v5_header: Option<BitMapV5Header> = match info_header.size {
+            x if x >= BitMapInfoHeader::SIZE_V5 => Some(BitMapV5Header {
+                intent: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                profile_data: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                profile_size: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?),
+                reserved: Self::reduce_bit_slice_u32(Self::get_next_n_bytes(&info_bytes, &mut info_offset, 4)?)
+            }),
+            _ => None
+        };
+
+        //
+        // The alpha of each pixel is only its opacity if the header gives an alpha mask, or for 32 bpp icons,
+        // which always have an alpha channel. Pixels which are skipped or filled in may be transparent as well.
+        //
+        let mut has_alpha = header_masks.as_ref().is_some_and(|masks| masks.alpha != 0) || (is_icon && info_header.bits_per_pixel == 32);
+
+        //
+        // Masks are only used for BI_BITFIELDS.
+        // Otherwise, 16 bit pixels default to 5 bits per channel, and 24/32 bit pixels are read as BGR(A).
+        //
+        let channel_masks: Option<BitMapChannelMasks> = if info_header.compression == BitMapInfoHeader::BI_BITFIELDS && !info_header.is_os2() {
+            header_masks
+        }
+        else if info_header.bits_per_pixel == 16 {
+            Some(BitMapChannelMasks::RGB555)
+        }
+        else {
+            None
         };
 
         //Vector of pixels in the palette
         let mut color_table_vec: Vec<RGBColor> = Vec::new();
 
-        //Vector of pixels in the bitmap
-        let mut pixel_vec: Vec<RGBColor> = Vec::new();
+        //
+        // The color table holds colors_used entries, or if 0, the maximum number of colors for the bit depth.
+        // Bit depths above 8 don't index the color table, so it can only be present if colors_used is set.
+        //
+        let max_color_table_entries: usize = match (info_header.colors_used, info_header.bits_per_pixel) {
+            (0, bpp) if bpp <= 8 => 1 << bpp,
+            (0, _) => 0,
+            (n, _) => n as usize
+        };
+
+        //OS/2 1.x color table entries are 3 bytes, rather than 4
+        let color_table_entry_size: usize = match info_header.size {
+            BitMapInfoHeader::SIZE_CORE => 3,
+            _ => 4
+        };
+
+        let max_color_table_length = match max_color_table_entries.checked_mul(color_table_entry_size) {
+            Some(n) => n,
+            None => return Err(BmpError::MalformedHeader(String::from("The number of colors used is too large.")))
+        };
 
-        //If there is any data between the current offset and data offset, insert it into the pallette
-        let color_table_length = ((header.data_offset as i32) - (offset as i32)) as usize;
+        //
+        // If there is any data between the current offset and data offset, insert it into the pallette.
+        // Icons have no data offset, so their color table is always complete.
+        //
+        let color_table_length = match is_icon {
+            true => max_color_table_length,
+            false => usize::min((header.data_offset as usize).saturating_sub(offset), max_color_table_length)
+        };
         if color_table_length > 0 {
-            let color_table_raw = get_next_n_bytes(&buffer, &mut offset, color_table_length);
+            let color_table_raw = Self::get_next_n_bytes(buffer, &mut offset, color_table_length)?;
 
-            //Each color in the pallette is 4 bytes, the first 3 representing the Blue, Green and Red intensities respectively, with the last unused
-            color_table_raw.chunks(4)
+            //Each color in the pallette is 4 (or 3) bytes, the first 3 representing the Blue, Green and Red intensities respectively, with the last unused
+            color_table_raw.chunks_exact(color_table_entry_size)
             .map(|chunk| RGBColor {
                 blue: chunk[0],
                 green: chunk[1],
@@ -66,148 +513,358 @@ impl BitMapRaw {
             .for_each(|entry| color_table_vec.push(entry));
         }
         
-        let color_table: BitMapPixelData = BitMapPixelData {
-            pixels: color_table_vec
+        let color_table: BitMapColorTable = BitMapColorTable {
+            colors: color_table_vec
+        };
+
+        if is_icon {
+            //Where the pixel data would be, if the icon had a file header
+            header.data_offset = u32::try_from(offset - dib_start).ok()
+                .and_then(|dib_offset| dib_offset.checked_add(BitMapHeader::SIZE))
+                .ok_or(BmpError::MalformedHeader(String::from("The color table of the icon is too large.")))?;
+        }
+        else {
+            if (header.data_offset as usize) < offset {
+                warnings.push(DecodeWarning::DataOffsetOverlapsHeader {
+                    data_offset: header.data_offset,
+                    header_end: offset
+                });
+            }
+
+            //Skip any gap between the color table and the pixel data
+            offset = usize::max(offset, header.data_offset as usize);
+        }
+
+        //
+        // Make sure the compression method is one that can be decoded for the bit depth. Icons can't be compressed,
+        // as the AND mask couldn't be found after compressed pixel data.
+        //
+        let is_supported_compression = match (info_header.compression, info_header.bits_per_pixel) {
+            (BitMapInfoHeader::BI_RGB, _) => true,
+            (BitMapInfoHeader::BI_BITFIELDS, 16 | 32) => !info_header.is_os2(),
+            _ if is_icon => false,
+            (BitMapInfoHeader::BI_RLE8, 8) | (BitMapInfoHeader::BI_RLE4, 4) => true,
+            (BitMapInfoHeader::BI_HUFFMAN1D, 1) | (BitMapInfoHeader::BI_RLE24, 24) => info_header.is_os2(),
+            _ => false
         };
 
-        ///
-        /// Round the value up to the nearest multiple of 4
-        /// See: https://stackoverflow.com/a/9194117
-        /// 
-        fn round_to_next_multiple_of_4(value: i32) -> usize {
-            ((value + 4 - 1) & -4) as usize
+        if !is_supported_compression {
+            return Err(BmpError::UnsupportedCompression(info_header.compression));
         }
+
+        //Dimensions of the image. A negative width or height only affects the direction in which the rows/columns are drawn.
+        let width = info_header.width.unsigned_abs() as usize;
+        let height = info_header.height.unsigned_abs() as usize;
+
+        let pixel_count = match width.checked_mul(height) {
+            Some(n) => n,
+            None => return Err(BmpError::InvalidDimensions {
+                width: info_header.width,
+                height: info_header.height
+            })
+        };
+
+        //
+        // Make sure the bitmap is within the limits before allocating anything for it. Along with the pixels themselves,
+        // compressed bitmaps are first decoded to a grid of color table indices.
+        //
+        let intermediate_size = match info_header.compression {
+            BitMapInfoHeader::BI_RLE8 | BitMapInfoHeader::BI_RLE4 | BitMapInfoHeader::BI_RLE24 => mem::size_of::<Option<u32>>(),
+            BitMapInfoHeader::BI_HUFFMAN1D => mem::size_of::<u8>(),
+            _ => 0
+        };
+
+        let alloc_bytes = (pixel_count as u64).saturating_mul((mem::size_of::<RGBColor>() + intermediate_size) as u64);
+
+        DecodeLimits::check(DecodeLimit::Width, width as u64, settings.limits.max_width as u64)?;
+        DecodeLimits::check(DecodeLimit::Height, height as u64, settings.limits.max_height as u64)?;
+        DecodeLimits::check(DecodeLimit::Pixels, pixel_count as u64, settings.limits.max_pixels)?;
+        DecodeLimits::check(DecodeLimit::AllocBytes, alloc_bytes, settings.limits.max_alloc_bytes)?;
+
+        //
+        // Get the widths of each scanline, in bytes, both with and without the 0-padding
+        // needed to round the scanline to the nearest multiple of 4 bytes.
+        //
+        let scanline_widths = width.checked_mul(info_header.bits_per_pixel as usize)
+            .map(|bits| bits.div_ceil(8))
+            .and_then(|row_width| row_width.checked_next_multiple_of(4).map(|scanline_width| (row_width, scanline_width)));
+
+        let (row_width, scanline_width) = match scanline_widths {
+            Some(widths) => widths,
+            None => return Err(BmpError::InvalidDimensions {
+                width: info_header.width,
+                height: info_header.height
+            })
+        };
+
+        //
+        // Get the next scanline. The 0-padding at the end of the last scanline is
+        // sometimes left out, but the pixels themselves must be present, unless
+        // decoding leniently, in which case whatever is left of the scanline is used.
+        //
+        let get_next_scanline = |offset: &mut usize| -> Result<&[u8], BmpError> {
+            match Self::get_next_n_bytes(buffer, offset, scanline_width) {
+                Ok(scanline) => Ok(scanline),
+                Err(_) => match Self::get_next_n_bytes(buffer, offset, row_width) {
+                    Err(_) if lenient => {
+                        let remainder = buffer.get(*offset..).unwrap_or(&[]);
+                        *offset = usize::max(*offset, buffer.len());
+                        Ok(remainder)
+                    },
+                    result => result
+                }
+            }
+        };
+
+        //
+        // Get the color at the given index of the color table. When decoding leniently,
+        // indices past the end of the table are counted, and given the fill color.
+        //
+        let mut out_of_range_pixels: usize = 0;
+        let mut get_table_color = |index: usize| -> Result<RGBColor, BmpError> {
+            match RGBColor::from_table(&color_table, index) {
+                Err(BmpError::PaletteIndexOutOfRange { .. }) if lenient => {
+                    out_of_range_pixels += 1;
+                    Ok(settings.fill_color.clone())
+                },
+                result => result
+            }
+        };
+
+        //The pixel data, which may be compressed
+        let data = buffer.get(offset..).unwrap_or(&[]);
         
+        //
+        // bpp = 4 or 8 with BI_RLE4/BI_RLE8: pixel data is run-length encoded indices of the color table.
+        // Pixels skipped by the encoding are transparent.
+        // bpp = 24 with BI_RLE24 (OS/2 only): pixel data is run-length encoded BGR colors.
+        //
+        let mut pixel_data = if [BitMapInfoHeader::BI_RLE8, BitMapInfoHeader::BI_RLE4].contains(&info_header.compression)
+            || (info_header.compression == BitMapInfoHeader::BI_RLE24 && info_header.is_os2()) {
+            let (mut values, missing_pixels) = Self::decode_rle(data, width, height, info_header.bits_per_pixel);
+
+            //The data ended before the last pixel. Leniently, the missing pixels are filled in below.
+            if missing_pixels > 0 && !lenient {
+                return Err(BmpError::Truncated {
+                    needed: buffer.len() + 1,
+                    available: buffer.len()
+                });
+            }
+
+            //Pixels the data ended before are missing rather than skipped
+            values.truncate(pixel_count - missing_pixels);
+
+            //Skipped pixels have no index, so the indices can only be kept if no pixels were skipped
+            if info_header.bits_per_pixel != 24 && values.iter().all(Option::is_some) {
+                BitMapPixelData::Indexed(values.into_iter().flatten().map(|index| index as u8).collect())
+            }
+            else {
+                let mut pixel_vec: Vec<RGBColor> = Vec::with_capacity(pixel_count);
+
+                has_alpha |= values.iter().any(Option::is_none);
+
+                for value in values {
+                    let color = match value {
+                        Some(value) if info_header.bits_per_pixel == 24 => RGBColor::from_bitfields(value, &BitMapChannelMasks::RGB888),
+                        Some(index) => get_table_color(index as usize)?,
+                        None => RGBColor {
+                            red: 0,
+                            green: 0,
+                            blue: 0,
+                            alpha: 0
+                        }
+                    };
+
+                    pixel_vec.push(color);
+                }
+
+                BitMapPixelData::Color(pixel_vec)
+            }
+        }
+        //bpp = 1 with BI_HUFFMAN1D (OS/2 only): pixel data is modified huffman encoded runs of white (index 0) and black (index 1) pixels
+        else if info_header.compression == BitMapInfoHeader::BI_HUFFMAN1D && info_header.is_os2() {
+            let (mut indices, missing_pixels) = Self::decode_huffman_1d(data, width, height);
+
+            //The data ended before the last pixel. Leniently, the missing pixels are filled in below.
+            if missing_pixels > 0 && !lenient {
+                return Err(BmpError::Truncated {
+                    needed: buffer.len() + 1,
+                    available: buffer.len()
+                });
+            }
+
+            indices.truncate(pixel_count - missing_pixels);
+
+            BitMapPixelData::Indexed(indices)
+        }
         //bpp = 1, 4 or 8: value of each pixel has a size <= 1 byte, and is an index of the color table
-        if [1, 4, 8].contains(&info_header.bits_per_pixel) {
-            //Get the width of the scanline based on bit depth and line width
-            let pixels_per_bit = f32::ceil(8_f32 / (info_header.bits_per_pixel as f32)) as usize;
-            let scaline_width_temp = f32::ceil(f32::abs(info_header.width as f32) / (pixels_per_bit as f32)) as i32;
-            let scanline_width = round_to_next_multiple_of_4(scaline_width_temp);
+        else if [1, 4, 8].contains(&info_header.bits_per_pixel) {
+            let bits_per_pixel = info_header.bits_per_pixel as usize;
+            let pixels_per_byte = 8 / bits_per_pixel;
+            let index_mask = ((1_u16 << bits_per_pixel) - 1) as u8;
+
+            //Vector of color table indices of pixels in the bitmap
+            let mut index_vec: Vec<u8> = Vec::with_capacity(pixel_count);
 
             //Read in each scanline
-            loop {
-                let mut done: bool = false;
-    
-                let mut count = scanline_width;
-    
+            for _ in 0..height {
+                let scanline = get_next_scanline(&mut offset)?;
+
                 //
-                // I don't think this should ever happen for a properly-formatted
-                // bitmap, but if the scanline goes past the end of the file,
-                // truncate it
+                // Loop over each pixel in the scanline, ignoring 0-padding at the end of the scanline.
                 //
-                if buffer.len() < offset + scanline_width {
-                    count = ((buffer.len() as i32) - (offset as i32)) as usize;
-                    done = true;
+                for x in 0..width {
+                    //A truncated scanline can only be the last one read
+                    let byte = match scanline.get(x / pixels_per_byte) {
+                        Some(byte) => *byte,
+                        None => break
+                    };
+
+                    //Extract the pixel from the byte, with the leftmost pixel in the most significant bits
+                    let shift = 8 - (bits_per_pixel * ((x % pixels_per_byte) + 1));
+                    let index = (byte >> shift) & index_mask;
+
+                    index_vec.push(index);
                 }
+            }
+
+            BitMapPixelData::Indexed(index_vec)
+        }
+        //
+        // bpp = 16: value of each pixel is 2 bytes, with each 5 bits representing Blue, Green and Red intensities respectively, and the last bit being unused,
+        // unless different masks were given by BI_BITFIELDS.
+        // bpp = 32 with BI_BITFIELDS: value of each pixel is 4 bytes, with each channel's position given by its mask.
+        //
+        else if let (16 | 32, Some(masks)) = (info_header.bits_per_pixel, &channel_masks) {
+            let bytesperpixel = (info_header.bits_per_pixel / 8) as usize;
+
+            //Vector of pixels in the bitmap
+            let mut pixel_vec: Vec<RGBColor> = Vec::with_capacity(pixel_count);
+
+            //Read in each scanline
+            for _ in 0..height {
+                let scanline = get_next_scanline(&mut offset)?;
 
-                //Get the scanline data
-                let scanline = get_next_n_bytes(&buffer, &mut offset, count as usize);
-    
                 //
-                // Loop over each bit in the scanline, ignoring 0-padding at the end of the scanline.
+                // Loop over each pixel in the scanline, ignoring 0-padding at the end of the scanline.
                 //
-                scanline.iter()
-                    .enumerate()
-                    .for_each(|(ndx, chunk)| {
-                        if ndx < (scaline_width_temp as usize) {
-                            //For each pixel in the bit
-                            for i in 1..=pixels_per_bit {
-                                //If past the width of the line, the rest of the bits are padding
-                                if (pixels_per_bit * ndx) + i > (info_header.width as usize) {
-                                    break;
-                                }
-
-                                //Extract the (i - 1)th pixel from the byte
-                                let index = (*chunk >> (8 - ((info_header.bits_per_pixel as i32) * (i as i32)))) & ((2_u16.pow(info_header.bits_per_pixel as u32) - 1) as u8);
-                                
-                                //Extract the color from the color table and add it to the pixel data
-                                let color = RGBColor::from_table(&color_table, index as usize);
-
-                                pixel_vec.push(color);
-                            }
-                        }
+                scanline
+                    .chunks_exact(bytesperpixel)
+                    .take(width)
+                    .for_each(|chunk| {
+                        //Extract each channel from the pixel using its mask
+                        let value = Self::reduce_bit_slice_u32(chunk);
+                        pixel_vec.push(RGBColor::from_bitfields(value, masks));
                     });
-    
-                if done {
-                    break;
-                }
-            };
-        }       
-        //bpp = 16: value of each pixel is 2 bytes, with each 5 bits representing Blue, Green and Red intensities respectively, and the last bit being unused.
-        else if info_header.bits_per_pixel == 16 {
-            panic!("Not implemented for 16 bit images!");
+            }
+
+            BitMapPixelData::Color(pixel_vec)
         }
         //bpp = 24: value of each pixel is 3 bytes, representing Blue, Green and Red intensities respectively
         //bpp = 32: value of each pixel is 4 bytes, representing Alpha, Blue, Green and Red intensities respectively
         else if [24, 32].contains(&info_header.bits_per_pixel) {
-            //Get scanline width based on line width
-            let bytesperpixel = f32::ceil((info_header.bits_per_pixel as f32) / 8_f32) as usize;
-            let scaline_width_temp = i32::abs(info_header.width * (bytesperpixel as i32));
-            let scanline_width = round_to_next_multiple_of_4(scaline_width_temp);
+            let bytesperpixel = (info_header.bits_per_pixel / 8) as usize;
+
+            //Vector of pixels in the bitmap
+            let mut pixel_vec: Vec<RGBColor> = Vec::with_capacity(pixel_count);
     
             //Read in each scanline
-            loop {
-                let mut done: bool = false;
-    
-                let mut count = scanline_width;
+            for _ in 0..height {
+                let scanline = get_next_scanline(&mut offset)?;
     
                 //
-                // I don't think this should ever happen for a properly-formatted
-                // bitmap, but if the scanline goes past the end of the file,
-                // truncate it
-                //
-                if buffer.len() < offset + scanline_width {
-                    count = ((buffer.len() as i32) - (offset as i32)) as usize;
-                    done = true;
-                }
-    
-                //Get the scanline data
-                let scanline = get_next_n_bytes(&buffer, &mut offset, count as usize);
-    
-                //
-                // Loop over each chunk of 3 bytes in the scanline, ignoring 0-padding at the end of the scanline.
+                // Loop over each chunk of 3 (or 4) bytes in the scanline, ignoring 0-padding at the end of the scanline.
                 //
                 scanline
-                    .chunks(bytesperpixel)
+                    .chunks_exact(bytesperpixel)
+                    .take(width)
                     .for_each(|chunk| {
-                        //Ignore 0-padding
-                        if chunk.len() == bytesperpixel {
-                            //Extract alpha, blue, green, and red from their respective bytes
-                            let color = RGBColor {
-                                blue: chunk[0],
-                                green: chunk[1],
-                                red: chunk[2],
-                                alpha: match bytesperpixel {
-                                    4 => chunk[3],
-                                    _ => 0xFF
-                                }
-                            };
-
-                            pixel_vec.push(color);
-                        }
+                        //Extract alpha, blue, green, and red from their respective bytes
+                        let color = RGBColor {
+                            blue: chunk[0],
+                            green: chunk[1],
+                            red: chunk[2],
+                            alpha: match bytesperpixel {
+                                4 => chunk[3],
+                                _ => 0xFF
+                            }
+                        };
+
+                        pixel_vec.push(color);
                     });
-    
-                if done {
-                    break;
-                }
-            };
+            }
+
+            BitMapPixelData::Color(pixel_vec)
         }
         //bpp cannot be anything but 1, 4, 8, 16, 24, or 32
         else {
-            panic!("{} is not a valid value for 'bits_per_pixel'.", info_header.bits_per_pixel);
+            return Err(BmpError::UnsupportedBitDepth(info_header.bits_per_pixel));
+        };
+
+        //
+        // Indices can only be kept if every one of them is in the color table, and no pixels
+        // need to be filled in. Otherwise, look up the color of each pixel.
+        //
+        if let BitMapPixelData::Indexed(indices) = &pixel_data {
+            if indices.len() < pixel_count || indices.iter().any(|index| *index as usize >= color_table.colors.len()) {
+                pixel_data = BitMapPixelData::Color(indices.iter()
+                    .map(|index| get_table_color(*index as usize))
+                    .collect::<Result<Vec<RGBColor>, BmpError>>()?);
+            }
         }
 
-        let pixel_data = BitMapPixelData {
-            pixels: pixel_vec
-        };
+        if out_of_range_pixels > 0 {
+            has_alpha |= settings.fill_color.alpha != 0xFF;
 
-        Ok(Self {
+            warnings.push(DecodeWarning::PaletteTooShort {
+                palette_size: color_table.colors.len(),
+                out_of_range_pixels
+            });
+        }
+
+        //Fill in any pixels missing from the end of a truncated bitmap
+        if let BitMapPixelData::Color(pixel_vec) = &mut pixel_data {
+            if pixel_vec.len() < pixel_count {
+                has_alpha |= settings.fill_color.alpha != 0xFF;
+
+                warnings.push(DecodeWarning::TruncatedPixelData {
+                    missing_pixels: pixel_count - pixel_vec.len()
+                });
+
+                pixel_vec.resize(pixel_count, settings.fill_color.clone());
+            }
+        }
+
+        Ok((Self {
             header,
             info_header,
+            v4_header,
+            v5_header,
+            os2_header,
             color_table,
-            pixel_data
-        })
+            pixel_data,
+            has_alpha,
+            warnings
+        }, offset))
+    }
+
+    ///
+    /// Get the next n bytes of the given bytes, starting at the given offset, and move the offset past them
+    /// 
+    fn get_next_n_bytes<'a>(buffer: &'a [u8], offset: &mut usize, n: usize) -> Result<&'a [u8], BmpError> {
+        let o_offset = *offset;
+        let end = o_offset.saturating_add(n);
+
+        match buffer.get(o_offset..end) {
+            Some(bytes) => {
+                *offset = end;
+                Ok(bytes)
+            },
+            None => Err(BmpError::Truncated {
+                needed: end,
+                available: buffer.len()
+            })
+        }
     }
 
     fn reduce_bit_slice_u16(slice: &[u8]) -> u16 {
@@ -0,0 +1,190 @@
+use std::io::BufReader;
+use std::fs::File;
+use std::fmt;
+use super::super::super::*;
+
+/// 
+/// One of the bitmaps in an OS/2 bitmap array, with the size of the display it was made for
+/// 
+#[allow(dead_code)]
+pub struct BitMapArrayEntry {
+    /// 
+    /// The bitmap
+    /// 
+    pub bitmap: BitMapRaw,
+    /// 
+    /// The width of the display the bitmap was made for, in pixels, or 0 if it suits any display
+    /// 
+    pub display_width: u16,
+    /// 
+    /// The height of the display the bitmap was made for, in pixels, or 0 if it suits any display
+    /// 
+    pub display_height: u16
+}
+
+impl fmt::Display for BitMapArrayEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}, {} bpp", self.bitmap.info_header.width.unsigned_abs(), self.bitmap.info_header.height.unsigned_abs(),
+            self.bitmap.info_header.bits_per_pixel)?;
+
+        match (self.display_width, self.display_height) {
+            (0, 0) => write!(f, ", for any display"),
+            (width, height) => write!(f, ", for {width}x{height} displays")
+        }
+    }
+}
+
+/// 
+/// An OS/2 bitmap array, which holds several versions of an image, each for a different display
+/// 
+#[allow(dead_code)]
+pub struct BitMapArray {
+    /// 
+    /// The bitmaps, in the order they are chained in the file
+    /// 
+    pub entries: Vec<BitMapArrayEntry>
+}
+
+impl BitMapArray {
+    /// 
+    /// The signature of each header in the chain of a bitmap array, BA
+    /// 
+    pub const SIGNATURE: u16 = 0x4142;
+
+    /// 
+    /// The size of each header in the chain: signature, size, offset of the next header, and display width and height.
+    /// The file header of the bitmap follows.
+    /// 
+    pub const HEADER_SIZE: usize = 14;
+
+    /// 
+    /// Read a bitmap array from the file at the given path
+    /// 
+    pub fn read_from_file(path: &str) -> Result<Self, BmpError> {
+        Self::read_from_file_with_settings(path, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read a bitmap array from the file at the given path, with the given settings
+    /// 
+    pub fn read_from_file_with_settings(path: &str, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let buffer = BitMapRaw::read_to_buffer(&mut BufReader::new(File::open(path)?), settings)?;
+
+        Self::from_bytes_with_settings(&buffer, settings)
+    }
+
+    /// 
+    /// Read a bitmap array from the given bytes
+    /// 
+    pub fn from_bytes(buffer: &[u8]) -> Result<Self, BmpError> {
+        Self::from_bytes_with_settings(buffer, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read a bitmap array from the given bytes, with the given settings. Each header in the chain gives the offset
+    /// of the next header, or 0 if it is the last, and is followed by a bitmap whose data offset counts from the
+    /// start of the array.
+    /// 
+    pub fn from_bytes_with_settings(buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let mut entries: Vec<BitMapArrayEntry> = Vec::new();
+        let mut offset: usize = 0;
+
+        loop {
+            let header = match buffer.get(offset..offset + Self::HEADER_SIZE) {
+                Some(header) => header,
+                None => return Err(BmpError::Truncated {
+                    needed: offset + Self::HEADER_SIZE,
+                    available: buffer.len()
+                })
+            };
+
+            let signature = u16::from_le_bytes([header[0], header[1]]);
+
+            if signature != Self::SIGNATURE {
+                return Err(BmpError::BadSignature(signature));
+            }
+
+            let next = u32::from_le_bytes([header[6], header[7], header[8], header[9]]) as usize;
+
+            //The file header of the bitmap follows the header in the chain
+            let mut bitmap_offset = offset + Self::HEADER_SIZE;
+            let file_header = BitMapRaw::read_file_header(buffer, &mut bitmap_offset)?;
+
+            let (bitmap, _) = BitMapRaw::read_dib(buffer, bitmap_offset, file_header, Vec::new(), false, settings)?;
+
+            entries.push(BitMapArrayEntry {
+                bitmap,
+                display_width: u16::from_le_bytes([header[10], header[11]]),
+                display_height: u16::from_le_bytes([header[12], header[13]])
+            });
+
+            if next == 0 {
+                break;
+            }
+
+            //Each header must come after the one before it, so the chain can't loop
+            if next <= offset {
+                return Err(BmpError::MalformedHeader(format!("The header at {offset} points back to {next}.")));
+            }
+
+            offset = next;
+        }
+
+        Ok(BitMapArray {
+            entries
+        })
+    }
+
+    /// 
+    /// Get the index of the largest bitmap, preferring the most bits per pixel, and then the first.
+    /// None if there are no bitmaps.
+    /// 
+    pub fn largest(&self) -> Option<usize> {
+        self.entries.iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, entry)| (Self::area(entry), entry.bitmap.info_header.bits_per_pixel))
+            .map(|(index, _)| index)
+    }
+
+    /// 
+    /// Get the index of the bitmap which best fits in the given width and height, in pixels: the largest which fits,
+    /// preferring the most bits per pixel, or if none fit, the smallest. None if there are no bitmaps.
+    /// 
+    pub fn best_fit(&self, width: u32, height: u32) -> Option<usize> {
+        let fits = |entry: &BitMapArrayEntry| entry.bitmap.info_header.width.unsigned_abs() <= width
+            && entry.bitmap.info_header.height.unsigned_abs() <= height;
+
+        self.entries.iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, entry)| fits(entry))
+            .max_by_key(|(_, entry)| (Self::area(entry), entry.bitmap.info_header.bits_per_pixel))
+            .or_else(|| self.entries.iter()
+                .enumerate()
+                .min_by_key(|(_, entry)| (Self::area(entry), std::cmp::Reverse(entry.bitmap.info_header.bits_per_pixel))))
+            .map(|(index, _)| index)
+    }
+
+    /// 
+    /// Take the bitmap at the given index out of the array
+    /// 
+    pub fn into_entry(self, index: usize) -> Result<BitMapRaw, BmpError> {
+        let count = self.entries.len();
+
+        self.entries.into_iter()
+            .nth(index)
+            .map(|entry| entry.bitmap)
+            .ok_or(BmpError::EntryOutOfRange {
+                index,
+                count
+            })
+    }
+
+    /// 
+    /// The number of pixels in the bitmap of the given entry
+    /// 
+    fn area(entry: &BitMapArrayEntry) -> u64 {
+        entry.bitmap.info_header.width.unsigned_abs() as u64 * entry.bitmap.info_header.height.unsigned_abs() as u64
+    }
+}
@@ -0,0 +1,143 @@
+use super::super::super::*;
+
+impl BitMapRaw {
+    ///
+    /// Decompress BI_RLE8, BI_RLE4 or BI_RLE24 pixel data into a grid of color table indices (or
+    /// for RLE24, BGR colors), ordered the same way as uncompressed scanlines. Pixels which are never
+    /// written to, i.e. those skipped by a delta, end-of-line or end-of-bitmap escape, are None.
+    /// If the data ends before an end-of-bitmap escape or the last line, the number of pixels it
+    /// never reached is returned as well. Those pixels are missing, rather than skipped.
+    /// 
+    /// Each run begins with a pair of bytes:
+    ///     n > 0, c: Encoded mode. Repeat the index c n times. For RLE4, c holds 2 indices, which alternate.
+    ///               For RLE24, c is the first of the 3 bytes of the color.
+    ///     0, 0: End of line.
+    ///     0, 1: End of bitmap.
+    ///     0, 2: Delta. The next 2 bytes are the number of pixels to move right and down.
+    ///     0, n >= 3: Absolute mode. The next n indices are copied as-is, padded to a 2 byte boundary.
+    /// 
+    pub(super) fn decode_rle(data: &[u8], width: usize, height: usize, bits_per_pixel: u16) -> (Vec<Option<u32>>, usize) {
+        let mut indices: Vec<Option<u32>> = vec![None; width * height];
+
+        let is_rle4 = bits_per_pixel == 4;
+        let is_rle24 = bits_per_pixel == 24;
+
+        let (mut x, mut y) = (0_usize, 0_usize);
+        let mut offset: usize = 0;
+        let mut end_of_bitmap = false;
+
+        //Write an index at the current position, ignoring anything outside of the image
+        let mut put = |x: usize, y: usize, index: u32| {
+            if x < width && y < height {
+                indices[(y * width) + x] = Some(index);
+            }
+        };
+
+        //Get the ith index from the given byte; RLE4 stores 2 per byte, high nibble first
+        let index_at = |byte: u8, i: usize| -> u32 {
+            if is_rle4 {
+                match i % 2 {
+                    0 => (byte >> 4) as u32,
+                    _ => (byte & 0x0F) as u32
+                }
+            }
+            else {
+                byte as u32
+            }
+        };
+
+        while y < height {
+            let (count, value) = match (data.get(offset), data.get(offset + 1)) {
+                (Some(count), Some(value)) => (*count as usize, *value),
+                //Ran out of data before reaching the end of the bitmap
+                _ => break
+            };
+
+            offset += 2;
+
+            match (count, value) {
+                //Encoded mode, for RLE24
+                (n, _) if n > 0 && is_rle24 => {
+                    let color = match data.get((offset - 1)..(offset + 2)) {
+                        Some(bytes) => Self::reduce_bit_slice_u32(bytes),
+                        None => break
+                    };
+
+                    offset += 2;
+
+                    for _ in 0..n {
+                        put(x, y, color);
+                        x += 1;
+                    }
+                },
+                //Encoded mode
+                (n, c) if n > 0 => {
+                    for i in 0..n {
+                        put(x, y, index_at(c, i));
+                        x += 1;
+                    }
+                },
+                //End of line
+                (_, 0) => {
+                    x = 0;
+                    y += 1;
+                },
+                //End of bitmap
+                (_, 1) => {
+                    end_of_bitmap = true;
+                    break;
+                },
+                //Delta
+                (_, 2) => {
+                    match (data.get(offset), data.get(offset + 1)) {
+                        (Some(dx), Some(dy)) => {
+                            x += *dx as usize;
+                            y += *dy as usize;
+                        },
+                        _ => break
+                    };
+
+                    offset += 2;
+                },
+                //Absolute mode
+                (_, n) => {
+                    let n = n as usize;
+
+                    //Number of bytes holding the indices
+                    let byte_count = match (is_rle4, is_rle24) {
+                        (true, _) => n.div_ceil(2),
+                        (_, true) => n * 3,
+                        _ => n
+                    };
+
+                    let bytes = match data.get(offset..(offset + byte_count)) {
+                        Some(bytes) => bytes,
+                        None => break
+                    };
+
+                    for i in 0..n {
+                        let value = match (is_rle4, is_rle24) {
+                            (true, _) => index_at(bytes[i / 2], i),
+                            (_, true) => Self::reduce_bit_slice_u32(&bytes[(i * 3)..((i + 1) * 3)]),
+                            _ => index_at(bytes[i], i)
+                        };
+
+                        put(x, y, value);
+                        x += 1;
+                    }
+
+                    //Absolute runs are padded to a 2 byte boundary
+                    offset += byte_count + (byte_count % 2);
+                }
+            }
+        }
+
+        //If the data ran out, every pixel from the current one on is missing
+        let missing_pixels = match end_of_bitmap || y >= height {
+            true => 0,
+            false => (width * height) - ((y * width) + usize::min(x, width))
+        };
+
+        (indices, missing_pixels)
+    }
+}
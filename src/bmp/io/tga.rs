@@ -0,0 +1,167 @@
+mod read;
+mod write;
+
+pub use write::*;
+
+use super::super::*;
+
+/// 
+/// The kind of image stored in a TGA file. Each may be stored raw or run-length encoded.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TgaImageType {
+    /// 
+    /// Indices into a color map, at 8 or 16 bits (types 1 and 9)
+    /// 
+    ColorMapped,
+    /// 
+    /// Colors, at 15, 16, 24 or 32 bits (types 2 and 10)
+    /// 
+    TrueColor,
+    /// 
+    /// Grey, at 8 bits, or 16 bits with alpha (types 3 and 11)
+    /// 
+    Greyscale
+}
+
+impl TgaImageType {
+    /// 
+    /// Get the image type with the given value in the header, and whether it is run-length encoded
+    /// 
+    pub fn from_u8(value: u8) -> Option<(Self, bool)> {
+        match value {
+            1 => Some((Self::ColorMapped, false)),
+            2 => Some((Self::TrueColor, false)),
+            3 => Some((Self::Greyscale, false)),
+            9 => Some((Self::ColorMapped, true)),
+            10 => Some((Self::TrueColor, true)),
+            11 => Some((Self::Greyscale, true)),
+            _ => None
+        }
+    }
+
+    /// 
+    /// Get the value of the image type in the header
+    /// 
+    pub fn to_u8(&self, rle: bool) -> u8 {
+        let value = match self {
+            Self::ColorMapped => 1,
+            Self::TrueColor => 2,
+            Self::Greyscale => 3
+        };
+
+        match rle {
+            true => value + 8,
+            false => value
+        }
+    }
+
+    /// 
+    /// Whether the image type allows the given number of bits per pixel
+    /// 
+    pub fn allows_bits_per_pixel(&self, bits_per_pixel: u8) -> bool {
+        match self {
+            Self::ColorMapped => matches!(bits_per_pixel, 8 | 16),
+            Self::TrueColor => matches!(bits_per_pixel, 15 | 16 | 24 | 32),
+            Self::Greyscale => matches!(bits_per_pixel, 8 | 16)
+        }
+    }
+}
+
+/// 
+/// The corner of the image which the first pixel in a TGA file is in
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TgaOrigin {
+    #[default]
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight
+}
+
+impl TgaOrigin {
+    /// 
+    /// Bit 4 of the image descriptor is set if pixels go from right to left
+    /// 
+    const RIGHT_TO_LEFT: u8 = 0x10;
+
+    /// 
+    /// Bit 5 of the image descriptor is set if rows go from top to bottom
+    /// 
+    const TOP_TO_BOTTOM: u8 = 0x20;
+
+    /// 
+    /// Get the origin from the image descriptor
+    /// 
+    pub fn from_descriptor(descriptor: u8) -> Self {
+        match (descriptor & Self::TOP_TO_BOTTOM != 0, descriptor & Self::RIGHT_TO_LEFT != 0) {
+            (false, false) => Self::BottomLeft,
+            (false, true) => Self::BottomRight,
+            (true, false) => Self::TopLeft,
+            (true, true) => Self::TopRight
+        }
+    }
+
+    /// 
+    /// Get the bits of the image descriptor which give the origin
+    /// 
+    pub fn to_descriptor(&self) -> u8 {
+        match self {
+            Self::BottomLeft => 0,
+            Self::BottomRight => Self::RIGHT_TO_LEFT,
+            Self::TopLeft => Self::TOP_TO_BOTTOM,
+            Self::TopRight => Self::TOP_TO_BOTTOM | Self::RIGHT_TO_LEFT
+        }
+    }
+
+    /// 
+    /// Get the position, counting from the top left, of the pixel at the given
+    /// index in the file
+    /// 
+    fn position(&self, index: usize, width: usize, height: usize) -> (usize, usize) {
+        let (column, row) = (index % width, index / width);
+
+        let x = match self {
+            Self::BottomRight | Self::TopRight => width - 1 - column,
+            _ => column
+        };
+
+        let y = match self {
+            Self::BottomLeft | Self::BottomRight => height - 1 - row,
+            _ => row
+        };
+
+        (x, y)
+    }
+}
+
+impl BitMapRaw {
+    /// 
+    /// The size of the TGA header, before the image ID
+    /// 
+    const TGA_HEADER_SIZE: usize = 18;
+
+    /// 
+    /// The signature at the end of the footer of a TGA 2.0 file
+    /// 
+    const TGA_FOOTER_SIGNATURE: &'static [u8; 18] = b"TRUEVISION-XFILE.\0";
+
+    /// 
+    /// The most pixels in a single run-length encoded packet
+    /// 
+    const TGA_MAX_PACKET_SIZE: usize = 128;
+
+    /// 
+    /// Get the channel masks of a pixel or color map entry with the given
+    /// number of bits, and the given number of alpha bits
+    /// 
+    fn tga_masks(bits: u8, alpha_bits: u8) -> BitMapChannelMasks {
+        match (bits, alpha_bits) {
+            (15 | 16, 0) => BitMapChannelMasks::RGB555,
+            (15 | 16, _) => BitMapChannelMasks::ARGB1555,
+            (32, 0) | (24, _) => BitMapChannelMasks::RGB888,
+            _ => BitMapChannelMasks::ARGB8888
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use std::io::BufReader;
+use std::fs::File;
+use super::*;
+
+impl BitMapRaw {
+    /// 
+    /// Read a TGA image from the file at the given path
+    /// 
+    pub fn read_tga_from_file(path: &str) -> Result<Self, BmpError> {
+        Self::read_tga_from_file_with_settings(path, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read a TGA image from the file at the given path, with the given settings
+    /// 
+    pub fn read_tga_from_file_with_settings(path: &str, settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let buffer = Self::read_to_buffer(&mut BufReader::new(File::open(path)?), settings)?;
+
+        Self::from_tga_bytes_with_settings(&buffer, settings)
+    }
+
+    /// 
+    /// Read a TGA image from the given bytes
+    /// 
+    pub fn from_tga_bytes(buffer: &[u8]) -> Result<Self, BmpError> {
+        Self::from_tga_bytes_with_settings(buffer, &BitMapRawReadSettings::default())
+    }
+
+    /// 
+    /// Read a TGA image from the given bytes, with the given settings.
+    /// 
+    /// 8-bit color mapped images keep their color map as the color table, as do 8-bit greyscale images,
+    /// with a table of every grey, the same as an 8 bpp bitmap. True color images keep their bits per pixel,
+    /// with 15-bit images read as 16 bpp. Other images are read as 32 bpp if they have alpha, and 24 bpp otherwise.
+    /// Alpha is only read if the image descriptor gives the pixels (or color map entries) alpha bits.
+    /// 
+    /// In lenient mode, pixels missing from truncated image data, or outside of the color map, are given the fill color.
+    /// 
+    pub fn from_tga_bytes_with_settings(buffer: &[u8], settings: &BitMapRawReadSettings) -> Result<Self, BmpError> {
+        let is_lenient = settings.mode == DecodeMode::Lenient;
+
+        if buffer.len() < Self::TGA_HEADER_SIZE {
+            return Err(BmpError::Truncated {
+                needed: Self::TGA_HEADER_SIZE,
+                available: buffer.len()
+            });
+        }
+
+        let read_u16 = |offset: usize| u16::from_le_bytes([buffer[offset], buffer[offset + 1]]);
+
+        let id_length = buffer[0] as usize;
+        let color_map_type = buffer[1];
+        let color_map_first = read_u16(3) as usize;
+        let color_map_length = read_u16(5) as usize;
+        let color_map_entry_bits = buffer[7];
+        let width = read_u16(12) as u32;
+        let height = read_u16(14) as u32;
+        let bits_per_pixel = buffer[16];
+        let descriptor = buffer[17];
+
+        let alpha_bits = descriptor & 0x0F;
+        let origin = TgaOrigin::from_descriptor(descriptor);
+
+        let (image_type, rle) = TgaImageType::from_u8(buffer[2])
+            .ok_or(BmpError::MalformedHeader(format!("{} is not a supported TGA image type.", buffer[2])))?;
+
+        if color_map_type > 1 {
+            return Err(BmpError::MalformedHeader(format!("{color_map_type} is not a valid color map type.")));
+        }
+
+        if image_type == TgaImageType::ColorMapped && color_map_type != 1 {
+            return Err(BmpError::MalformedHeader(String::from("A color mapped image has no color map.")));
+        }
+
+        if !image_type.allows_bits_per_pixel(bits_per_pixel) {
+            return Err(BmpError::UnsupportedBitDepth(bits_per_pixel as u16));
+        }
+
+        if color_map_type == 1 && !matches!(color_map_entry_bits, 15 | 16 | 24 | 32) {
+            return Err(BmpError::UnsupportedBitDepth(color_map_entry_bits as u16));
+        }
+
+        if width == 0 || height == 0 {
+            return Err(BmpError::InvalidDimensions {
+                width: width as i32,
+                height: height as i32
+            });
+        }
+
+        let pixel_count = width as usize * height as usize;
+
+        DecodeLimits::check(DecodeLimit::Width, width as u64, settings.limits.max_width as u64)?;
+        DecodeLimits::check(DecodeLimit::Height, height as u64, settings.limits.max_height as u64)?;
+        DecodeLimits::check(DecodeLimit::Pixels, pixel_count as u64, settings.limits.max_pixels)?;
+        DecodeLimits::check(DecodeLimit::AllocBytes, (pixel_count as u64).saturating_mul(std::mem::size_of::<RGBColor>() as u64), settings.limits.max_alloc_bytes)?;
+
+        //The image ID and the color map come between the header and the pixel data
+        let color_map_offset = Self::TGA_HEADER_SIZE + id_length;
+        let color_map_entry_size = (color_map_entry_bits as usize).div_ceil(8);
+
+        let color_map_size = match color_map_type {
+            1 => color_map_length * color_map_entry_size,
+            _ => 0
+        };
+
+        let mut offset = color_map_offset + color_map_size;
+
+        if buffer.len() < offset {
+            return Err(BmpError::Truncated {
+                needed: offset,
+                available: buffer.len()
+            });
+        }
+
+        //Read a little endian pixel or color map entry
+        let read_value = |bytes: &[u8]| -> u32 {
+            bytes.iter().rev().fold(0, |value, byte| (value << 8) | *byte as u32)
+        };
+
+        //
+        // The color table which values of the pixel data refer to, if any. The color map starts at its
+        // first entry, so the entries before it are left black. Greyscale images refer to a table of every grey.
+        //
+        let palette: Option<Vec<RGBColor>> = match image_type {
+            TgaImageType::ColorMapped => {
+                let masks = Self::tga_masks(color_map_entry_bits, alpha_bits);
+
+                let mut palette = vec![RGBColor { red: 0, green: 0, blue: 0, alpha: 0xFF }; color_map_first];
+
+                palette.extend(buffer[color_map_offset..offset]
+                    .chunks_exact(color_map_entry_size)
+                    .map(|entry| RGBColor::from_bitfields(read_value(entry), &masks)));
+
+                //8-bit indices can only refer to the first 256 entries
+                if bits_per_pixel == 8 {
+                    palette.truncate(256);
+                }
+
+                Some(palette)
+            },
+            TgaImageType::Greyscale if bits_per_pixel == 8 => Some((0..=255_u8)
+                .map(|grey| RGBColor { red: grey, green: grey, blue: grey, alpha: 0xFF })
+                .collect()),
+            _ => None
+        };
+
+        //
+        // Read each value of the pixel data, in the order of the file. Run-length encoded data is a series
+        // of packets, each with a count from 1 to 128 in its low 7 bits. If the high bit is set, the packet
+        // is one value repeated; otherwise, it is that many values.
+        //
+        let pixel_size = (bits_per_pixel as usize).div_ceil(8);
+        let mut values: Vec<u32> = Vec::with_capacity(pixel_count);
+
+        while values.len() < pixel_count {
+            let (count, is_run) = match rle {
+                true => match buffer.get(offset) {
+                    Some(packet) => {
+                        offset += 1;
+                        (((packet & 0x7F) as usize) + 1, packet & 0x80 != 0)
+                    },
+                    None => break
+                },
+                false => (pixel_count, false)
+            };
+
+            let count = usize::min(count, pixel_count - values.len());
+
+            if is_run {
+                match buffer.get(offset..(offset + pixel_size)) {
+                    Some(bytes) => values.extend(std::iter::repeat_n(read_value(bytes), count)),
+                    None => break
+                };
+
+                offset += pixel_size;
+            }
+            else {
+                let available = usize::min(count, (buffer.len() - offset) / pixel_size);
+
+                values.extend(buffer[offset..(offset + (available * pixel_size))]
+                    .chunks_exact(pixel_size)
+                    .map(read_value));
+
+                offset += available * pixel_size;
+
+                if available < count {
+                    break;
+                }
+            }
+        }
+
+        let missing_pixels = pixel_count - values.len();
+
+        if missing_pixels > 0 && !is_lenient {
+            return Err(BmpError::Truncated {
+                needed: offset + pixel_size + 1,
+                available: buffer.len()
+            });
+        }
+
+        //Put each value at its position counting from the top left, according to the origin
+        let mut positioned: Vec<Option<u32>> = vec![None; pixel_count];
+
+        for (index, value) in values.into_iter().enumerate() {
+            let (x, y) = origin.position(index, width as usize, height as usize);
+            positioned[(y * width as usize) + x] = Some(value);
+        }
+
+        let mut warnings: Vec<DecodeWarning> = Vec::new();
+
+        let (bits_per_pixel, color_table, pixel_data) = match palette {
+            Some(palette) => {
+                let out_of_range_pixels = positioned.iter().flatten().filter(|value| (**value as usize) >= palette.len()).count();
+
+                if out_of_range_pixels > 0 && !is_lenient {
+                    let index = *positioned.iter().flatten().find(|value| (**value as usize) >= palette.len()).unwrap_or(&0) as usize;
+
+                    return Err(BmpError::PaletteIndexOutOfRange {
+                        index,
+                        palette_size: palette.len()
+                    });
+                }
+
+                if out_of_range_pixels > 0 {
+                    warnings.push(DecodeWarning::PaletteTooShort {
+                        palette_size: palette.len(),
+                        out_of_range_pixels
+                    });
+                }
+
+                //Indices can only be kept if they are 8-bit, and every pixel is in the color table
+                match (bits_per_pixel, missing_pixels + out_of_range_pixels) {
+                    (8, 0) => (8, BitMapColorTable { colors: palette }, BitMapPixelData::Indexed(positioned.into_iter().flatten().map(|value| value as u8).collect())),
+                    _ => {
+                        let colors = positioned.iter()
+                            .map(|value| value.and_then(|value| palette.get(value as usize)).cloned().unwrap_or(settings.fill_color.clone()))
+                            .collect();
+
+                        let bits_per_pixel = match bits_per_pixel {
+                            8 => 8,
+                            _ if palette.iter().any(|color| color.alpha != 0xFF) => 32,
+                            _ => 24
+                        };
+
+                        (bits_per_pixel, BitMapColorTable { colors: palette }, BitMapPixelData::Color(colors))
+                    }
+                }
+            },
+            None => {
+                let to_color = |value: u32| match image_type {
+                    //16-bit greyscale is grey in the low byte, and alpha in the high byte
+                    TgaImageType::Greyscale => {
+                        let grey = value as u8;
+
+                        RGBColor {
+                            red: grey,
+                            green: grey,
+                            blue: grey,
+                            alpha: match alpha_bits {
+                                0 => 0xFF,
+                                _ => (value >> 8) as u8
+                            }
+                        }
+                    },
+                    _ => RGBColor::from_bitfields(value, &Self::tga_masks(bits_per_pixel, alpha_bits))
+                };
+
+                let colors = positioned.into_iter()
+                    .map(|value| value.map(to_color).unwrap_or(settings.fill_color.clone()))
+                    .collect();
+
+                let bits_per_pixel = match (image_type, bits_per_pixel) {
+                    (TgaImageType::Greyscale, _) if alpha_bits > 0 => 32,
+                    (TgaImageType::Greyscale, _) => 24,
+                    (_, 15) => 16,
+                    (_, bits_per_pixel) => bits_per_pixel
+                };
+
+                (bits_per_pixel as u16, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(colors))
+            }
+        };
+
+        if missing_pixels > 0 {
+            warnings.push(DecodeWarning::TruncatedPixelData { missing_pixels });
+        }
+
+        let mut bitmap = Self::from_pixels(width, height, bits_per_pixel, color_table, pixel_data)?;
+        bitmap.warnings = warnings;
+
+        Ok(bitmap)
+    }
+}
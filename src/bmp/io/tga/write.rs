@@ -0,0 +1,253 @@
+use std::io::{Write, BufWriter};
+use std::fs::File;
+use super::*;
+
+/// 
+/// Settings for BitMapRaw::write_tga_to
+/// 
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct TgaWriteSettings {
+    /// 
+    /// The kind of image to write
+    /// 
+    image_type: TgaImageType,
+    /// 
+    /// The number of bits in each pixel. Must be allowed by the image type,
+    /// and color mapped images can only be written with 8.
+    /// 
+    bits_per_pixel: u8,
+    /// 
+    /// Whether to run-length encode the pixel data
+    /// 
+    rle: bool,
+    /// 
+    /// The corner of the image to write first
+    /// 
+    origin: TgaOrigin,
+    /// 
+    /// How to choose the color map when writing a color mapped image with more colors than fit
+    /// 
+    quantization: BitMapQuantization,
+    /// 
+    /// The algorithm to use to find the closest color map entry to each pixel
+    /// 
+    algorithm: fn(&RGBColor, &RGBColor) -> f32
+}
+
+impl TgaWriteSettings {
+    /// 
+    /// Create a new instance of TgaWriteSettings with the
+    /// given settings
+    /// 
+    pub fn new(image_type: TgaImageType, bits_per_pixel: u8, rle: bool, origin: TgaOrigin, quantization: BitMapQuantization, algorithm: fn(&RGBColor, &RGBColor) -> f32) -> Self {
+        TgaWriteSettings {
+            image_type,
+            bits_per_pixel,
+            rle,
+            origin,
+            quantization,
+            algorithm
+        }
+    }
+
+    pub fn with_image_type(&mut self, image_type: TgaImageType) -> &Self {
+        self.image_type = image_type;
+        self
+    }
+
+    pub fn with_bits_per_pixel(&mut self, bits_per_pixel: u8) -> &Self {
+        self.bits_per_pixel = bits_per_pixel;
+        self
+    }
+
+    pub fn with_rle(&mut self, rle: bool) -> &Self {
+        self.rle = rle;
+        self
+    }
+
+    pub fn with_origin(&mut self, origin: TgaOrigin) -> &Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn with_quantization(&mut self, quantization: BitMapQuantization) -> &Self {
+        self.quantization = quantization;
+        self
+    }
+
+    pub fn with_algorithm(&mut self, algorithm: fn(&RGBColor, &RGBColor) -> f32) -> &Self {
+        self.algorithm = algorithm;
+        self
+    }
+}
+
+impl Default for TgaWriteSettings {
+    /// 
+    /// Uncompressed 32-bit true color with alpha, from the bottom left
+    /// 
+    fn default() -> Self {
+        Self::new(TgaImageType::TrueColor, 32, false, TgaOrigin::BottomLeft, BitMapQuantization::MedianCut, RGBColor::get_euclidean_distance_rgb)
+    }
+}
+
+impl BitMapRaw {
+    /// 
+    /// Write the image to the file at the given path as a TGA image, with the given settings
+    /// 
+    pub fn write_tga_to_file(&self, path: &str, settings: &TgaWriteSettings) -> Result<(), BmpError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        self.write_tga_to(&mut writer, settings)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// 
+    /// Write the image to the given destination as a TGA 2.0 image, with the given settings.
+    /// 
+    /// Greyscale is the luminance of each pixel. Color mapped images use the color table of an indexed
+    /// bitmap if it fits, and otherwise quantize the image. Alpha is written by 32-bit true color,
+    /// 16-bit true color (as a single bit), 16-bit greyscale, and color maps with any transparent entry.
+    /// 
+    pub fn write_tga_to<W: Write>(&self, writer: &mut W, settings: &TgaWriteSettings) -> Result<(), BmpError> {
+        let bits_per_pixel = settings.bits_per_pixel;
+
+        if !settings.image_type.allows_bits_per_pixel(bits_per_pixel) || (settings.image_type == TgaImageType::ColorMapped && bits_per_pixel != 8) {
+            return Err(BmpError::UnsupportedBitDepth(bits_per_pixel as u16));
+        }
+
+        let width = self.info_header.width.unsigned_abs() as usize;
+        let height = self.info_header.height.unsigned_abs() as usize;
+
+        if width == 0 || height == 0 || width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(BmpError::InvalidDimensions {
+                width: self.info_header.width,
+                height: self.info_header.height
+            });
+        }
+
+        let pixel_count = width * height;
+
+        if self.pixel_data.len() != pixel_count {
+            return Err(BmpError::PixelCountMismatch {
+                expected: pixel_count,
+                actual: self.pixel_data.len()
+            });
+        }
+
+        //The color map and the index of each pixel in the pixel data, for color mapped images
+        let (palette, indices) = match settings.image_type {
+            TgaImageType::ColorMapped => self.quantize(256, settings.quantization, settings.algorithm),
+            _ => (Vec::new(), Vec::new())
+        };
+
+        //Color map entries only need alpha if one of them is transparent
+        let color_map_entry_bits: u8 = match palette.iter().any(|color| color.alpha != 0xFF) {
+            true => 32,
+            false => 24
+        };
+
+        let alpha_bits: u8 = match (settings.image_type, bits_per_pixel) {
+            (TgaImageType::ColorMapped, _) if color_map_entry_bits == 32 => 8,
+            (TgaImageType::TrueColor, 32) | (TgaImageType::Greyscale, 16) => 8,
+            (TgaImageType::TrueColor, 16) => 1,
+            _ => 0
+        };
+
+        let masks = Self::tga_masks(bits_per_pixel, alpha_bits);
+        let pixel_size = (bits_per_pixel as usize).div_ceil(8);
+
+        //Get the bytes of the pixel at the given index of the pixel data
+        let pixel_bytes = |index: usize| -> Vec<u8> {
+            let pixel = self.get_pixel(index).unwrap_or(RGBColor {
+                red: 0,
+                green: 0,
+                blue: 0,
+                alpha: 0
+            });
+
+            match settings.image_type {
+                TgaImageType::ColorMapped => vec![indices.get(index).copied().unwrap_or(0)],
+                TgaImageType::Greyscale => [pixel.get_luminance(), pixel.alpha][..pixel_size].to_vec(),
+                TgaImageType::TrueColor => pixel.to_bitfields(&masks).to_le_bytes()[..pixel_size].to_vec()
+            }
+        };
+
+        let mut data: Vec<u8> = Vec::with_capacity(Self::TGA_HEADER_SIZE + (palette.len() * 4) + (pixel_count * pixel_size));
+
+        //The header, with no image ID
+        data.extend_from_slice(&[0, (settings.image_type == TgaImageType::ColorMapped) as u8, settings.image_type.to_u8(settings.rle)]);
+        data.extend_from_slice(&0_u16.to_le_bytes());
+        data.extend_from_slice(&(palette.len() as u16).to_le_bytes());
+
+        data.push(match settings.image_type {
+            TgaImageType::ColorMapped => color_map_entry_bits,
+            _ => 0
+        });
+
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&(width as u16).to_le_bytes());
+        data.extend_from_slice(&(height as u16).to_le_bytes());
+        data.extend_from_slice(&[bits_per_pixel, alpha_bits | settings.origin.to_descriptor()]);
+
+        for color in &palette {
+            data.extend_from_slice(&[color.blue, color.green, color.red, color.alpha][..(color_map_entry_bits as usize / 8)]);
+        }
+
+        //Write each row, in the order given by the origin
+        for row in 0..height {
+            let pixels: Vec<Vec<u8>> = (0..width)
+                .map(|column| settings.origin.position((row * width) + column, width, height))
+                .map(|(x, y)| pixel_bytes(self.get_pixel_index(x, y).unwrap_or(0)))
+                .collect();
+
+            match settings.rle {
+                true => Self::encode_tga_rle(&pixels, &mut data),
+                false => pixels.iter().for_each(|pixel| data.extend_from_slice(pixel))
+            }
+        }
+
+        //The TGA 2.0 footer, without an extension area or developer directory
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(Self::TGA_FOOTER_SIGNATURE);
+
+        writer.write_all(&data)?;
+
+        Ok(())
+    }
+
+    /// 
+    /// Run-length encode a row of pixels. Repeated pixels are written as run packets,
+    /// and everything between them as raw packets.
+    /// 
+    fn encode_tga_rle(pixels: &[Vec<u8>], data: &mut Vec<u8>) {
+        let mut start = 0;
+
+        while start < pixels.len() {
+            let run = pixels[start..].iter()
+                .take(Self::TGA_MAX_PACKET_SIZE)
+                .take_while(|pixel| **pixel == pixels[start])
+                .count();
+
+            if run > 1 {
+                data.push(0x80 | (run - 1) as u8);
+                data.extend_from_slice(&pixels[start]);
+                start += run;
+                continue;
+            }
+
+            //A raw packet ends where the next run begins
+            let mut end = start + 1;
+
+            while end < pixels.len() && end - start < Self::TGA_MAX_PACKET_SIZE && (end + 1 >= pixels.len() || pixels[end] != pixels[end + 1]) {
+                end += 1;
+            }
+
+            data.push((end - start - 1) as u8);
+            pixels[start..end].iter().for_each(|pixel| data.extend_from_slice(pixel));
+            start = end;
+        }
+    }
+}
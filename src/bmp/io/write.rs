@@ -0,0 +1,503 @@
+use std::io::{Write, BufWriter};
+use std::fs::File;
+use super::super::*;
+
+mod quantize;
+mod rle;
+
+pub use quantize::*;
+
+/// 
+/// The order in which the rows of a bitmap are written
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitMapRowOrder {
+    /// 
+    /// The bottom row is written first, and the height is positive
+    /// 
+    #[default]
+    BottomUp,
+    /// 
+    /// The top row is written first, and the height is negative
+    /// 
+    TopDown
+}
+
+/// 
+/// Settings for BitMapRaw::write_to_with_settings
+/// 
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct BitMapRawWriteSettings {
+    /// 
+    /// The bit depth of the written pixel data. Must be 1, 4, 8, 16, 24 or 32.
+    /// 
+    bits_per_pixel: u16,
+    /// 
+    /// The order in which the rows are written
+    /// 
+    row_order: BitMapRowOrder,
+    /// 
+    /// The compression method. Must be BI_RGB, BI_RLE8 with 8 bpp, BI_RLE4 with 4 bpp,
+    /// or BI_BITFIELDS with 16 or 32 bpp. Compressed bitmaps must be written bottom-up.
+    /// BI_BITFIELDS bitmaps are written with a BITMAPV5HEADER.
+    /// 
+    compression: u32,
+    /// 
+    /// Where each channel is stored within a pixel, for BI_BITFIELDS. If not given,
+    /// RGB565 is used for 16 bpp, and ARGB8888 for 32 bpp.
+    /// 
+    channel_masks: Option<BitMapChannelMasks>,
+    /// 
+    /// How to choose the colors of the color table, for a bit depth of 8 or less,
+    /// when the image has too many colors to fit
+    /// 
+    quantization: BitMapQuantization,
+    /// 
+    /// The algorithm to use to find the nearest color in the color table
+    /// 
+    algorithm: fn(&RGBColor, &RGBColor) -> f32
+}
+
+impl BitMapRawWriteSettings {
+    /// 
+    /// Create a new instance of BitMapRawWriteSettings with the
+    /// given settings
+    /// 
+    pub fn new(bits_per_pixel: u16, row_order: BitMapRowOrder, compression: u32, channel_masks: Option<BitMapChannelMasks>, quantization: BitMapQuantization, algorithm: fn(&RGBColor, &RGBColor) -> f32) -> Self {
+        BitMapRawWriteSettings {
+            bits_per_pixel,
+            row_order,
+            compression,
+            channel_masks,
+            quantization,
+            algorithm
+        }
+    }
+
+    pub fn with_bits_per_pixel(&mut self, bits_per_pixel: u16) -> &Self {
+        self.bits_per_pixel = bits_per_pixel;
+        self
+    }
+
+    pub fn with_row_order(&mut self, row_order: BitMapRowOrder) -> &Self {
+        self.row_order = row_order;
+        self
+    }
+
+    pub fn with_compression(&mut self, compression: u32) -> &Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_channel_masks(&mut self, channel_masks: Option<BitMapChannelMasks>) -> &Self {
+        self.channel_masks = channel_masks;
+        self
+    }
+
+    pub fn with_quantization(&mut self, quantization: BitMapQuantization) -> &Self {
+        self.quantization = quantization;
+        self
+    }
+
+    pub fn with_algorithm(&mut self, algorithm: fn(&RGBColor, &RGBColor) -> f32) -> &Self {
+        self.algorithm = algorithm;
+        self
+    }
+}
+
+impl Default for BitMapRawWriteSettings {
+    /// 
+    /// 24 bpp, bottom-up and uncompressed, with median cut quantization by euclidean distance
+    /// 
+    fn default() -> Self {
+        Self::new(24, BitMapRowOrder::BottomUp, BitMapInfoHeader::BI_RGB, None, BitMapQuantization::MedianCut, RGBColor::get_euclidean_distance_rgb)
+    }
+}
+
+/// 
+/// The sizes of a written bitmap
+/// 
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct BitMapWriteReport {
+    /// 
+    /// The size of the file, including the headers and color table
+    /// 
+    pub file_size: u32,
+    /// 
+    /// The size of the pixel data, as written
+    /// 
+    pub image_size: u32,
+    /// 
+    /// The size the pixel data would have been without compression
+    /// 
+    pub uncompressed_image_size: usize
+}
+
+impl BitMapWriteReport {
+    /// 
+    /// The size of the uncompressed pixel data relative to the size of
+    /// the written pixel data, e.g. 4 if the written pixel data is a quarter the size
+    /// 
+    pub fn compression_ratio(&self) -> f32 {
+        match self.image_size {
+            0 => 1_f32,
+            image_size => self.uncompressed_image_size as f32 / image_size as f32
+        }
+    }
+}
+
+impl BitMapRaw {
+    /// 
+    /// Write the bitmap to the file at the given path
+    /// 
+    pub fn write_to_file(&self, path: &str) -> Result<BitMapWriteReport, BmpError> {
+        self.write_to_file_with_settings(path, &BitMapRawWriteSettings::default())
+    }
+
+    /// 
+    /// Write the bitmap to the file at the given path, with the given settings
+    /// 
+    pub fn write_to_file_with_settings(&self, path: &str, settings: &BitMapRawWriteSettings) -> Result<BitMapWriteReport, BmpError> {
+        //Create the file
+        let fs = File::create(path)?;
+        let mut writer = BufWriter::new(fs);
+
+        let report = self.write_to_with_settings(&mut writer, settings)?;
+        writer.flush()?;
+
+        Ok(report)
+    }
+
+    /// 
+    /// Write the bitmap to the given destination
+    /// 
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<BitMapWriteReport, BmpError> {
+        self.write_to_with_settings(writer, &BitMapRawWriteSettings::default())
+    }
+
+    /// 
+    /// Write the bitmap to the given destination, with the given settings
+    /// 
+    pub fn write_to_with_settings<W: Write>(&self, writer: &mut W, settings: &BitMapRawWriteSettings) -> Result<BitMapWriteReport, BmpError> {
+        if ![1, 4, 8, 16, 24, 32].contains(&settings.bits_per_pixel) {
+            return Err(BmpError::UnsupportedBitDepth(settings.bits_per_pixel));
+        }
+
+        //Make sure the compression method is one that can be encoded for the bit depth
+        let is_supported_compression = match (settings.compression, settings.bits_per_pixel) {
+            (BitMapInfoHeader::BI_RGB, _) => true,
+            (BitMapInfoHeader::BI_RLE8, 8) | (BitMapInfoHeader::BI_RLE4, 4) => settings.row_order == BitMapRowOrder::BottomUp,
+            (BitMapInfoHeader::BI_BITFIELDS, 16 | 32) => true,
+            _ => false
+        };
+
+        if !is_supported_compression {
+            return Err(BmpError::UnsupportedCompression(settings.compression));
+        }
+
+        //
+        // bpp = 16 or 32 with BI_BITFIELDS: each channel is placed using the given masks.
+        // bpp = 16 without BI_BITFIELDS: each channel is 5 bits, with the last bit unused.
+        //
+        let channel_masks = match (settings.compression, settings.bits_per_pixel) {
+            (BitMapInfoHeader::BI_BITFIELDS, bpp) => Some(match &settings.channel_masks {
+                Some(masks) => masks.clone(),
+                None if bpp == 16 => BitMapChannelMasks::RGB565,
+                None => BitMapChannelMasks::ARGB8888
+            }),
+            (_, 16) => Some(BitMapChannelMasks::RGB555),
+            _ => None
+        };
+
+        if let Some(masks) = &channel_masks {
+            Self::validate_channel_masks(masks, settings.bits_per_pixel)?;
+        }
+
+        //BI_BITFIELDS bitmaps are written with a BITMAPV5HEADER, so that the alpha mask and color space are kept
+        let info_header_size = match settings.compression {
+            BitMapInfoHeader::BI_BITFIELDS => BitMapInfoHeader::SIZE_V5,
+            _ => BitMapInfoHeader::SIZE_INFO
+        };
+
+        //Dimensions of the image
+        let width = self.info_header.width.unsigned_abs() as usize;
+        let height = self.info_header.height.unsigned_abs() as usize;
+
+        let invalid_dimensions = || BmpError::InvalidDimensions {
+            width: self.info_header.width,
+            height: self.info_header.height
+        };
+
+        let pixel_count = width.checked_mul(height).ok_or_else(invalid_dimensions)?;
+
+        if self.pixel_data.len() != pixel_count {
+            return Err(BmpError::PixelCountMismatch {
+                expected: pixel_count,
+                actual: self.pixel_data.len()
+            });
+        }
+
+        //bpp = 1, 4 or 8: build a color table to index
+        let (color_table, indices) = match settings.bits_per_pixel {
+            bpp if bpp <= 8 => self.quantize(1 << bpp, settings.quantization, settings.algorithm),
+            _ => (Vec::new(), Vec::new())
+        };
+
+        //Each scanline is padded to the nearest multiple of 4 bytes
+        let bits_per_pixel = settings.bits_per_pixel as usize;
+        let row_width = width.checked_mul(bits_per_pixel).map(|bits| bits.div_ceil(8)).ok_or_else(invalid_dimensions)?;
+        let scanline_width = row_width.checked_next_multiple_of(4).ok_or_else(invalid_dimensions)?;
+
+        //Index of the pixel in the pixel data at the given row and column, counting rows in the order they are written
+        let source_index = |row: usize, column: usize| -> usize {
+            //Row of the image, counting from the top
+            let image_row = match settings.row_order {
+                BitMapRowOrder::BottomUp => height - row - 1,
+                BitMapRowOrder::TopDown => row
+            };
+
+            //Row of the pixel data, which is in the same order as the rows of the bitmap it was read from
+            let source_row = match self.info_header.height {
+                x if x < 0 => image_row,
+                _ => height - image_row - 1
+            };
+
+            //If width is negative, the columns of the pixel data are in the opposite direction
+            let source_column = match self.info_header.width {
+                x if x < 0 => width - column - 1,
+                _ => column
+            };
+
+            (source_row * width) + source_column
+        };
+
+        //BI_RLE8/BI_RLE4: compress the indices, in the order the rows are written
+        let compressed: Option<Vec<u8>> = match settings.compression {
+            BitMapInfoHeader::BI_RLE8 | BitMapInfoHeader::BI_RLE4 => {
+                let ordered_indices: Vec<u8> = (0..pixel_count)
+                    .map(|n| indices[source_index(n / width, n % width)])
+                    .collect();
+
+                Some(Self::encode_rle(&ordered_indices, width, settings.bits_per_pixel))
+            },
+            _ => None
+        };
+
+        //All of the sizes and offsets in the headers are 32 bits. Each color table entry is 4 bytes.
+        let data_offset = BitMapHeader::SIZE + info_header_size + (color_table.len() as u32 * 4);
+        let uncompressed_image_size = scanline_width.checked_mul(height).ok_or_else(invalid_dimensions)?;
+        let image_size = match &compressed {
+            Some(data) => data.len(),
+            None => uncompressed_image_size
+        };
+        let image_size = u32::try_from(image_size).map_err(|_| invalid_dimensions())?;
+        let file_size = image_size.checked_add(data_offset).ok_or_else(invalid_dimensions)?;
+
+        let signed_width = i32::try_from(width).map_err(|_| invalid_dimensions())?;
+        let signed_height = i32::try_from(height).map_err(|_| invalid_dimensions())?;
+
+        let header = BitMapHeader {
+            signature: BitMapHeader::SIGNATURE,
+            file_size,
+            reserved: 0,
+            data_offset
+        };
+
+        let info_header = BitMapInfoHeader {
+            size: info_header_size,
+            width: signed_width,
+            height: match settings.row_order {
+                BitMapRowOrder::BottomUp => signed_height,
+                BitMapRowOrder::TopDown => -signed_height
+            },
+            planes: 1,
+            bits_per_pixel: settings.bits_per_pixel,
+            compression: settings.compression,
+            image_size,
+            x_pixels_per_meter: self.info_header.x_pixels_per_meter,
+            y_pixels_per_meter: self.info_header.y_pixels_per_meter,
+            colors_used: color_table.len() as u32,
+            important_colors: 0
+        };
+
+        Self::write_header(writer, &header)?;
+        Self::write_info_header(writer, &info_header)?;
+
+        if let (BitMapInfoHeader::SIZE_V5, Some(masks)) = (info_header_size, &channel_masks) {
+            //No endpoints or gamma are needed for sRGB
+            let v4_header = BitMapV4Header {
+                red_mask: masks.red,
+                green_mask: masks.green,
+                blue_mask: masks.blue,
+                alpha_mask: masks.alpha,
+                cs_type: BitMapV4Header::LCS_SRGB,
+                endpoints: BitMapCieXyzTriple {
+                    red: BitMapCieXyz { x: 0, y: 0, z: 0 },
+                    green: BitMapCieXyz { x: 0, y: 0, z: 0 },
+                    blue: BitMapCieXyz { x: 0, y: 0, z: 0 }
+                },
+                gamma_red: 0,
+                gamma_green: 0,
+                gamma_blue: 0
+            };
+
+            let v5_header = BitMapV5Header {
+                intent: BitMapV5Header::LCS_GM_IMAGES,
+                profile_data: 0,
+                profile_size: 0,
+                reserved: 0
+            };
+
+            Self::write_v4_header(writer, &v4_header)?;
+            Self::write_v5_header(writer, &v5_header)?;
+        }
+
+        //Each color in the color table is written as blue, green, red, then an unused byte
+        for color in color_table.iter() {
+            writer.write_all(&[color.blue, color.green, color.red, 0])?;
+        }
+
+        let report = BitMapWriteReport {
+            file_size,
+            image_size,
+            uncompressed_image_size
+        };
+
+        if let Some(data) = compressed {
+            writer.write_all(&data)?;
+            return Ok(report);
+        }
+
+        let mut scanline: Vec<u8> = Vec::with_capacity(scanline_width);
+
+        for row in 0..height {
+            scanline.clear();
+
+            for column in 0..width {
+                let index = source_index(row, column);
+
+                //bpp = 1, 4 or 8: pack the index into the byte, with the leftmost pixel in the most significant bits
+                if bits_per_pixel <= 8 {
+                    let pixels_per_byte = 8 / bits_per_pixel;
+                    let shift = 8 - (bits_per_pixel * ((column % pixels_per_byte) + 1));
+
+                    if column % pixels_per_byte == 0 {
+                        scanline.push(0);
+                    }
+
+                    if let Some(byte) = scanline.last_mut() {
+                        *byte |= indices[index] << shift;
+                    }
+
+                    continue;
+                }
+
+                let pixel = self.get_pixel(index).unwrap_or(RGBColor {
+                    red: 0,
+                    green: 0,
+                    blue: 0,
+                    alpha: 0
+                });
+
+                //bpp = 16, or 32 with BI_BITFIELDS: place each channel using its mask
+                if let Some(masks) = &channel_masks {
+                    let value = pixel.to_bitfields(masks).to_le_bytes();
+                    scanline.extend_from_slice(&value[..(bits_per_pixel / 8)]);
+
+                    continue;
+                }
+
+                //Each pixel is written as blue, green, red, then alpha for 32 bpp
+                scanline.extend_from_slice(&[pixel.blue, pixel.green, pixel.red]);
+
+                if bits_per_pixel == 32 {
+                    scanline.push(pixel.alpha);
+                }
+            }
+
+            //0-pad the scanline to the nearest multiple of 4 bytes
+            scanline.resize(scanline_width, 0);
+
+            writer.write_all(&scanline)?;
+        }
+
+        Ok(report)
+    }
+
+    /// 
+    /// Make sure each mask is a contiguous run of bits which fits in the bit depth,
+    /// and does not overlap any other mask
+    /// 
+    fn validate_channel_masks(masks: &BitMapChannelMasks, bits_per_pixel: u16) -> Result<(), BmpError> {
+        let mut combined: u32 = 0;
+
+        for mask in [masks.red, masks.green, masks.blue, masks.alpha] {
+            let fits = bits_per_pixel == 32 || mask >> bits_per_pixel == 0;
+            let is_contiguous = mask == 0 || (mask >> mask.trailing_zeros()).trailing_ones() == mask.count_ones();
+
+            if !fits || !is_contiguous || combined & mask != 0 {
+                return Err(BmpError::InvalidChannelMask(mask));
+            }
+
+            combined |= mask;
+        }
+
+        Ok(())
+    }
+
+    fn write_header<W: Write>(writer: &mut W, header: &BitMapHeader) -> Result<(), BmpError> {
+        writer.write_all(&header.signature.to_le_bytes())?;
+        writer.write_all(&header.file_size.to_le_bytes())?;
+        writer.write_all(&header.reserved.to_le_bytes())?;
+        writer.write_all(&header.data_offset.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    fn write_info_header<W: Write>(writer: &mut W, info_header: &BitMapInfoHeader) -> Result<(), BmpError> {
+        writer.write_all(&info_header.size.to_le_bytes())?;
+        writer.write_all(&info_header.width.to_le_bytes())?;
+        writer.write_all(&info_header.height.to_le_bytes())?;
+        writer.write_all(&info_header.planes.to_le_bytes())?;
+        writer.write_all(&info_header.bits_per_pixel.to_le_bytes())?;
+        writer.write_all(&info_header.compression.to_le_bytes())?;
+        writer.write_all(&info_header.image_size.to_le_bytes())?;
+        writer.write_all(&info_header.x_pixels_per_meter.to_le_bytes())?;
+        writer.write_all(&info_header.y_pixels_per_meter.to_le_bytes())?;
+        writer.write_all(&info_header.colors_used.to_le_bytes())?;
+        writer.write_all(&info_header.important_colors.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    fn write_v4_header<W: Write>(writer: &mut W, v4_header: &BitMapV4Header) -> Result<(), BmpError> {
+        writer.write_all(&v4_header.red_mask.to_le_bytes())?;
+        writer.write_all(&v4_header.green_mask.to_le_bytes())?;
+        writer.write_all(&v4_header.blue_mask.to_le_bytes())?;
+        writer.write_all(&v4_header.alpha_mask.to_le_bytes())?;
+        writer.write_all(&v4_header.cs_type.to_le_bytes())?;
+
+        for endpoint in [&v4_header.endpoints.red, &v4_header.endpoints.green, &v4_header.endpoints.blue] {
+            writer.write_all(&endpoint.x.to_le_bytes())?;
+            writer.write_all(&endpoint.y.to_le_bytes())?;
+            writer.write_all(&endpoint.z.to_le_bytes())?;
+        }
+
+        writer.write_all(&v4_header.gamma_red.to_le_bytes())?;
+        writer.write_all(&v4_header.gamma_green.to_le_bytes())?;
+        writer.write_all(&v4_header.gamma_blue.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    fn write_v5_header<W: Write>(writer: &mut W, v5_header: &BitMapV5Header) -> Result<(), BmpError> {
+        writer.write_all(&v5_header.intent.to_le_bytes())?;
+        writer.write_all(&v5_header.profile_data.to_le_bytes())?;
+        writer.write_all(&v5_header.profile_size.to_le_bytes())?;
+        writer.write_all(&v5_header.reserved.to_le_bytes())?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use super::super::super::*;
+
+/// 
+/// How to choose the colors of the color table when an image has more
+/// colors than the color table can hold
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitMapQuantization {
+    /// 
+    /// Repeatedly split the colors in two along the channel with the widest range, at the
+    /// median, and use the average color of each group.
+    /// 
+    #[default]
+    MedianCut,
+    /// 
+    /// Use the most common colors in the image.
+    /// 
+    Popularity
+}
+
+/// 
+/// An RGB color, and the number of pixels with that color
+/// 
+type ColorCount = ([u8; 3], usize);
+
+impl BitMapRaw {
+    /// 
+    /// Build a color table of at most max_colors colors, and get the index of each pixel in it.
+    /// 
+    /// If the bitmap is already indexed, and every index fits, its color table and indices are kept as-is.
+    /// Otherwise, if the bitmap has few enough colors, they are used exactly. Failing that, the colors are
+    /// reduced using the given method, and each pixel is given the closest color by the given algorithm.
+    /// 
+    pub(crate) fn quantize(&self, max_colors: usize, method: BitMapQuantization, algorithm: fn(&RGBColor, &RGBColor) -> f32) -> (Vec<RGBColor>, Vec<u8>) {
+        if let BitMapPixelData::Indexed(indices) = &self.pixel_data {
+            let palette_size = usize::min(max_colors, self.color_table.colors.len());
+
+            if indices.iter().all(|index| (*index as usize) < palette_size) {
+                return (self.color_table.colors[..palette_size].to_vec(), indices.clone());
+            }
+        }
+
+        let pixels: Vec<[u8; 3]> = (0..self.pixel_data.len())
+            .map(|index| match self.get_pixel(index) {
+                Some(color) => [color.red, color.green, color.blue],
+                None => [0, 0, 0]
+            })
+            .collect();
+
+        //Count the pixels of each color, in the order each color first appears
+        let mut counts: Vec<ColorCount> = Vec::new();
+        let mut positions: HashMap<[u8; 3], usize> = HashMap::new();
+
+        for pixel in pixels.iter() {
+            match positions.get(pixel) {
+                Some(position) => counts[*position].1 += 1,
+                None => {
+                    positions.insert(*pixel, counts.len());
+                    counts.push((*pixel, 1));
+                }
+            }
+        }
+
+        let to_color = |rgb: &[u8; 3]| RGBColor {
+            red: rgb[0],
+            green: rgb[1],
+            blue: rgb[2],
+            alpha: 0xFF
+        };
+
+        //Exact colors fit, so each color's index is its position
+        if counts.len() <= max_colors {
+            let palette = counts.iter().map(|(rgb, _)| to_color(rgb)).collect();
+            let indices = pixels.iter().map(|pixel| positions[pixel] as u8).collect();
+
+            return (palette, indices);
+        }
+
+        let palette: Vec<RGBColor> = match method {
+            BitMapQuantization::MedianCut => Self::median_cut(counts.clone(), max_colors),
+            BitMapQuantization::Popularity => {
+                let mut by_popularity = counts.clone();
+
+                //Stable, so ties go to the color which appears first
+                by_popularity.sort_by(|(_, a), (_, b)| b.cmp(a));
+                by_popularity.truncate(max_colors);
+
+                by_popularity.iter().map(|(rgb, _)| *rgb).collect()
+            }
+        }
+        .iter()
+        .map(to_color)
+        .collect();
+
+        //Find the closest palette color to each distinct color
+        let closest: Vec<u8> = counts.iter()
+            .map(|(rgb, _)| {
+                let color = to_color(rgb);
+
+                color.get_closest_in_set(&palette[..], algorithm)
+                    .and_then(|nearest| palette.iter().position(|entry| std::ptr::eq(entry, nearest)))
+                    .unwrap_or(0) as u8
+            })
+            .collect();
+
+        let indices = pixels.iter().map(|pixel| closest[positions[pixel]]).collect();
+
+        (palette, indices)
+    }
+
+    /// 
+    /// Reduce the given colors to at most max_colors using the median cut algorithm
+    /// 
+    fn median_cut(colors: Vec<ColorCount>, max_colors: usize) -> Vec<[u8; 3]> {
+        //The range of each channel in the group
+        fn ranges(group: &[ColorCount]) -> [u8; 3] {
+            let mut ranges = [0_u8; 3];
+
+            for (channel, range) in ranges.iter_mut().enumerate() {
+                let min = group.iter().map(|(rgb, _)| rgb[channel]).min().unwrap_or(0);
+                let max = group.iter().map(|(rgb, _)| rgb[channel]).max().unwrap_or(0);
+                *range = max - min;
+            }
+
+            ranges
+        }
+
+        //Each group of colors, and the range of each of its channels
+        let initial_ranges = ranges(&colors);
+        let mut groups: Vec<(Vec<ColorCount>, [u8; 3])> = vec![(colors, initial_ranges)];
+
+        while groups.len() < max_colors {
+            //Split the group with the widest range in any channel, if any can be split
+            let widest = groups.iter()
+                .enumerate()
+                .filter(|(_, (group, _))| group.len() > 1)
+                .max_by_key(|(_, (_, ranges))| ranges.iter().copied().max().unwrap_or(0))
+                .map(|(ndx, (_, ranges))| (ndx, *ranges));
+
+            let (ndx, group_ranges) = match widest {
+                Some(widest) => widest,
+                None => break
+            };
+
+            let channel = (0..3).max_by_key(|channel| group_ranges[*channel]).unwrap_or(0);
+
+            let (mut group, _) = groups.swap_remove(ndx);
+            group.sort_by_key(|(rgb, _)| rgb[channel]);
+
+            //Split at the median pixel, keeping at least 1 color on each side
+            let total: usize = group.iter().map(|(_, count)| count).sum();
+            let mut seen = 0;
+            let mut split = group.len() - 1;
+
+            for (position, (_, count)) in group.iter().enumerate() {
+                seen += count;
+
+                if seen * 2 >= total {
+                    split = position + 1;
+                    break;
+                }
+            }
+
+            let split = split.clamp(1, group.len() - 1);
+            let upper = group.split_off(split);
+
+            let (lower_ranges, upper_ranges) = (ranges(&group), ranges(&upper));
+            groups.push((group, lower_ranges));
+            groups.push((upper, upper_ranges));
+        }
+
+        //The average color of each group, weighted by the number of pixels of each color
+        groups.iter()
+            .map(|(group, _)| {
+                let total: usize = group.iter().map(|(_, count)| count).sum();
+                let mut average = [0_u8; 3];
+
+                for (channel, value) in average.iter_mut().enumerate() {
+                    let sum: usize = group.iter().map(|(rgb, count)| rgb[channel] as usize * count).sum();
+                    *value = ((sum + (total / 2)) / usize::max(total, 1)) as u8;
+                }
+
+                average
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,152 @@
+use super::super::super::*;
+
+/// 
+/// A run of pixels in RLE pixel data, and the number of pixels in it
+/// 
+#[derive(Clone, Copy)]
+enum RleRun {
+    Encoded(usize),
+    Absolute(usize)
+}
+
+impl BitMapRaw {
+    /// 
+    /// Compress a grid of color table indices, ordered the same way as uncompressed scanlines,
+    /// into BI_RLE8 or BI_RLE4 pixel data. See decode_rle for the format.
+    /// 
+    /// Each row is split into runs of repeated indices (for RLE4, a repeated pair of indices),
+    /// which are written in encoded mode, and runs of at least 3 indices, which are written as-is
+    /// in absolute mode. The split is chosen to make each row as small as possible.
+    /// 
+    pub(super) fn encode_rle(indices: &[u8], width: usize, bits_per_pixel: u16) -> Vec<u8> {
+        const MAX_RUN: usize = 0xFF;
+
+        let is_rle4 = bits_per_pixel == 4;
+
+        //An empty bitmap is only the end of bitmap
+        if width == 0 || indices.is_empty() {
+            return vec![0, 1];
+        }
+
+        //The number of bytes an absolute run of the given length takes, including the escape and padding
+        let absolute_size = |length: usize| -> usize {
+            let byte_count = match is_rle4 {
+                true => length.div_ceil(2),
+                false => length
+            };
+
+            2 + byte_count + (byte_count % 2)
+        };
+
+        let mut data: Vec<u8> = Vec::new();
+
+        let rows: Vec<&[u8]> = indices.chunks(width).collect();
+
+        //The smallest size of the rest of the row from each position, and the run to write at that position
+        let mut sizes: Vec<usize> = vec![0; width + 1];
+        let mut runs: Vec<RleRun> = vec![RleRun::Encoded(1); width];
+
+        //
+        // The number of pixels from each position which repeat the pixel before them in the run. An RLE8 run
+        // repeats a single index, and an RLE4 run repeats a pair of indices, so each pixel in the run must match
+        // the pixel 1 (or 2) before it.
+        //
+        let period = match is_rle4 {
+            true => 2,
+            false => 1
+        };
+        let mut repeats: Vec<usize> = vec![0; width + period];
+
+        for (y, row) in rows.iter().enumerate() {
+            //Find the smallest way to write each position to the end of the row, from the end of the row backwards
+            sizes[row.len()] = 0;
+            repeats[row.len()..].fill(0);
+
+            for x in (0..row.len()).rev() {
+                repeats[x] = match x >= period && row[x] == row[x - period] {
+                    true => repeats[x + 1] + 1,
+                    false => 0
+                };
+
+                //The number of pixels at this position which can be written as a single encoded run
+                let run_length = usize::min(MAX_RUN, usize::min(period, row.len() - x) + repeats[x + period]);
+
+                //
+                // An encoded run is 2 bytes no matter its length, and the rest of a row can only get smaller the
+                // less of it there is, so the longest encoded run is always the best one.
+                //
+                let mut best = (2 + sizes[x + run_length], RleRun::Encoded(run_length));
+
+                //Absolute mode must have at least 3 pixels
+                let max_absolute_length = usize::min(MAX_RUN, row.len() - x);
+
+                for length in 3..=max_absolute_length {
+                    //No longer absolute run can be smaller, as the rest of the row is at least as large as its end
+                    if absolute_size(length) + sizes[x + max_absolute_length] >= best.0 {
+                        break;
+                    }
+
+                    let size = absolute_size(length) + sizes[x + length];
+
+                    if size < best.0 {
+                        best = (size, RleRun::Absolute(length));
+                    }
+                }
+
+                sizes[x] = best.0;
+                runs[x] = best.1;
+            }
+
+            let mut x = 0;
+
+            while x < row.len() {
+                match runs[x] {
+                    RleRun::Encoded(length) => {
+                        let value = match is_rle4 {
+                            true => (row[x] << 4) | (row.get(x + 1).copied().unwrap_or(0) & 0x0F),
+                            false => row[x]
+                        };
+
+                        data.extend_from_slice(&[length as u8, value]);
+                        x += length;
+                    },
+                    RleRun::Absolute(length) => {
+                        let literal = &row[x..(x + length)];
+
+                        data.extend_from_slice(&[0, length as u8]);
+
+                        let byte_count = match is_rle4 {
+                            true => {
+                                //2 indices per byte, high nibble first
+                                for pair in literal.chunks(2) {
+                                    data.push((pair[0] << 4) | (pair.get(1).copied().unwrap_or(0) & 0x0F));
+                                }
+
+                                length.div_ceil(2)
+                            },
+                            false => {
+                                data.extend_from_slice(literal);
+                                length
+                            }
+                        };
+
+                        //Absolute runs are padded to a 2 byte boundary
+                        if byte_count % 2 == 1 {
+                            data.push(0);
+                        }
+
+                        x += length;
+                    }
+                }
+            }
+
+            //End of line, or end of bitmap after the last line
+            match y + 1 == rows.len() {
+                true => data.extend_from_slice(&[0, 1]),
+                false => data.extend_from_slice(&[0, 0])
+            }
+        }
+
+        data
+    }
+}
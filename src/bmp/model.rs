@@ -1,5 +1,7 @@
 mod color;
 mod bitmap;
+mod animation;
 
 pub use color::*;
-pub use bitmap::*;
\ No newline at end of file
+pub use bitmap::*;
+pub use animation::*;
\ No newline at end of file
@@ -0,0 +1,71 @@
+use std::time::Duration;
+use super::*;
+use super::super::DecodeWarning;
+
+/// 
+/// A single frame of an animation: the whole image as it should be
+/// shown, and how long to show it for.
+/// 
+#[allow(dead_code)]
+pub struct BitMapFrame {
+    /// 
+    /// The image shown by the frame
+    /// 
+    pub bitmap: BitMapRaw,
+    /// 
+    /// How long to show the frame before moving to the next one
+    /// 
+    pub delay: Duration
+}
+
+/// 
+/// An animated image, as a sequence of frames.
+/// 
+#[allow(dead_code)]
+pub struct BitMapAnimation {
+    /// 
+    /// The frames, in the order they are shown
+    /// 
+    pub frames: Vec<BitMapFrame>,
+    /// 
+    /// The number of times to repeat the animation after it is first shown. 0 repeats
+    /// it forever, and None shows it once.
+    /// 
+    pub loop_count: Option<u16>,
+    /// 
+    /// Problems found while decoding the animation
+    /// 
+    pub warnings: Vec<DecodeWarning>
+}
+
+impl BitMapAnimation {
+    /// 
+    /// Get the first frame of the animation as an image, keeping the warnings
+    /// of the animation. Returns None if there are no frames.
+    /// 
+    pub fn into_first_frame(self) -> Option<BitMapRaw> {
+        let mut bitmap = self.frames.into_iter().next()?.bitmap;
+        bitmap.warnings = self.warnings;
+
+        Some(bitmap)
+    }
+}
+
+impl From<BitMapRaw> for BitMapAnimation {
+    ///
+    /// An animation of the image as a single frame, which is shown once
+    /// 
+    fn from(bitmap: BitMapRaw) -> Self {
+        let mut bitmap = bitmap;
+        let warnings = std::mem::take(&mut bitmap.warnings);
+
+        BitMapAnimation {
+            frames: vec![BitMapFrame {
+                bitmap,
+                delay: Duration::ZERO
+            }],
+            loop_count: None,
+            warnings
+        }
+    }
+}
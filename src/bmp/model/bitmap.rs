@@ -1,4 +1,5 @@
 use super::*;
+use super::super::{BmpError, DecodeWarning};
 
 ///
 /// A bitmap.
@@ -9,8 +10,20 @@ use super::*;
 pub struct BitMapRaw {
     pub header: BitMapHeader,
     pub info_header: BitMapInfoHeader,
-    pub color_table: BitMapPixelData,
-    pub pixel_data: BitMapPixelData
+    pub v4_header: Option<BitMapV4Header>,
+    pub v5_header: Option<BitMapV5Header>,
+    pub os2_header: Option<BitMapOs2Header>,
+    pub color_table: BitMapColorTable,
+    pub pixel_data: BitMapPixelData,
+    ///
+    /// Whether the alpha of each pixel is its opacity. Bitmaps without an alpha mask
+    /// leave the fourth byte of 32 bpp pixels unused, and it is usually 0.
+    /// 
+    pub has_alpha: bool,
+    ///
+    /// Problems found while decoding the bitmap
+    /// 
+    pub warnings: Vec<DecodeWarning>
 }
 
 ///
@@ -40,6 +53,18 @@ pub struct BitMapHeader {
     pub data_offset: u32
 }
 
+impl BitMapHeader {
+    ///
+    /// The signature of a bitmap, BM
+    /// 
+    pub const SIGNATURE: u16 = 0x4D42;
+
+    ///
+    /// The size of the header, in bytes
+    /// 
+    pub const SIZE: u32 = 14;
+}
+
 ///
 /// Bitmap info header data, regarding
 /// layout/contents of the bitmap.
@@ -79,6 +104,11 @@ pub struct BitMapInfoHeader {
     ///     0 = BI_RGB   no compression
     ///     1 = BI_RLE8 8bit RLE encoding
     ///     2 = BI_RLE4 4bit RLE encoding
+    ///     3 = BI_BITFIELDS pixels are 16/32 bits, with channel masks
+    /// 
+    /// For OS/2 2.x bitmaps, 3 and 4 instead mean:
+    ///     3 = BI_HUFFMAN1D 1bit modified huffman encoding
+    ///     4 = BI_RLE24 24bit RLE encoding
     /// 
     pub compression: u32,
     ///
@@ -109,6 +139,339 @@ pub struct BitMapInfoHeader {
     pub important_colors: u32
 }
 
+impl BitMapInfoHeader {
+    ///
+    /// No compression
+    /// 
+    pub const BI_RGB: u32 = 0;
+    ///
+    /// 8bit RLE encoding
+    /// 
+    pub const BI_RLE8: u32 = 1;
+    ///
+    /// 4bit RLE encoding
+    /// 
+    pub const BI_RLE4: u32 = 2;
+    ///
+    /// Uncompressed, with the color of each pixel described by
+    /// channel masks
+    /// 
+    pub const BI_BITFIELDS: u32 = 3;
+    ///
+    /// OS/2 2.x only: 1bit modified huffman (CCITT group 3 1D) encoding
+    /// 
+    pub const BI_HUFFMAN1D: u32 = 3;
+    ///
+    /// OS/2 2.x only: 24bit RLE encoding
+    /// 
+    pub const BI_RLE24: u32 = 4;
+
+    ///
+    /// Size of an OS/2 1.x BITMAPCOREHEADER, which has 16 bit
+    /// width and height, and 3 byte color table entries
+    /// 
+    pub const SIZE_CORE: u32 = 12;
+    ///
+    /// Size of a BITMAPINFOHEADER
+    /// 
+    pub const SIZE_INFO: u32 = 40;
+    ///
+    /// Size of a BITMAPV2INFOHEADER, which adds red, green
+    /// and blue channel masks
+    /// 
+    pub const SIZE_V2: u32 = 52;
+    ///
+    /// Size of a BITMAPV3INFOHEADER, which adds an alpha
+    /// channel mask
+    /// 
+    pub const SIZE_V3: u32 = 56;
+    ///
+    /// Size of a BITMAPV4HEADER
+    /// 
+    pub const SIZE_V4: u32 = 108;
+    ///
+    /// Size of a BITMAPV5HEADER
+    /// 
+    pub const SIZE_V5: u32 = 124;
+    ///
+    /// Size of a full OS/2 2.x BITMAPINFOHEADER2. These can be
+    /// truncated to as few as 16 bytes.
+    /// 
+    pub const SIZE_OS2: u32 = 64;
+
+    ///
+    /// Whether this is the header of an OS/2 bitmap, rather
+    /// than a windows bitmap
+    /// 
+    pub fn is_os2(&self) -> bool {
+        match self.size {
+            Self::SIZE_CORE => true,
+            Self::SIZE_INFO | Self::SIZE_V2 | Self::SIZE_V3 => false,
+            x => (16..=Self::SIZE_OS2).contains(&x)
+        }
+    }
+}
+
+///
+/// Additional fields present in an OS/2 2.x BITMAPINFOHEADER2, following
+/// the fields of the BitMapInfoHeader.
+/// 
+#[allow(dead_code)]
+pub struct BitMapOs2Header {
+    ///
+    /// Units of x_pixels_per_meter and y_pixels_per_meter.
+    /// Should always be 0, for pixels per meter.
+    /// 
+    pub units: u16,
+    ///
+    /// Should be 0.
+    /// 
+    pub reserved: u16,
+    ///
+    /// Direction in which the pixel data is stored.
+    /// Should always be 0, for bottom to top.
+    /// 
+    pub recording: u16,
+    ///
+    /// Halftoning algorithm used.
+    ///     0 = none
+    ///     1 = error diffusion
+    ///     2 = PANDA
+    ///     3 = super-circle
+    /// 
+    pub rendering: u16,
+    ///
+    /// First halftoning parameter.
+    /// 
+    pub size1: u32,
+    ///
+    /// Second halftoning parameter.
+    /// 
+    pub size2: u32,
+    ///
+    /// Color model of the color table.
+    /// Should always be 0, for RGB.
+    /// 
+    pub color_encoding: u32,
+    ///
+    /// Reserved for application use.
+    /// 
+    pub identifier: u32
+}
+
+///
+/// Additional fields present in a BITMAPV4HEADER, following the
+/// fields of the BitMapInfoHeader.
+/// 
+#[allow(dead_code)]
+pub struct BitMapV4Header {
+    ///
+    /// Mask of the red channel, if compression is BI_BITFIELDS.
+    /// 
+    pub red_mask: u32,
+    ///
+    /// Mask of the green channel, if compression is BI_BITFIELDS.
+    /// 
+    pub green_mask: u32,
+    ///
+    /// Mask of the blue channel, if compression is BI_BITFIELDS.
+    /// 
+    pub blue_mask: u32,
+    ///
+    /// Mask of the alpha channel.
+    /// 
+    pub alpha_mask: u32,
+    ///
+    /// The color space of the bitmap.
+    ///     0 = LCS_CALIBRATED_RGB  use endpoints and gamma
+    ///     'sRGB' = LCS_sRGB
+    ///     'Win ' = LCS_WINDOWS_COLOR_SPACE
+    ///     'LINK' = PROFILE_LINKED  (V5 only)
+    ///     'MBED' = PROFILE_EMBEDDED  (V5 only)
+    /// 
+    pub cs_type: u32,
+    ///
+    /// The CIE XYZ coordinates of the red, green and blue endpoints,
+    /// if cs_type is LCS_CALIBRATED_RGB.
+    /// 
+    pub endpoints: BitMapCieXyzTriple,
+    ///
+    /// Tone response curve for red, in unsigned 16.16 fixed point.
+    /// 
+    pub gamma_red: u32,
+    ///
+    /// Tone response curve for green, in unsigned 16.16 fixed point.
+    /// 
+    pub gamma_green: u32,
+    ///
+    /// Tone response curve for blue, in unsigned 16.16 fixed point.
+    /// 
+    pub gamma_blue: u32
+}
+
+impl BitMapV4Header {
+    ///
+    /// Color space given by endpoints and gamma
+    /// 
+    pub const LCS_CALIBRATED_RGB: u32 = 0;
+    ///
+    /// sRGB color space
+    /// 
+    pub const LCS_SRGB: u32 = 0x73524742;
+    ///
+    /// The system default color space
+    /// 
+    pub const LCS_WINDOWS_COLOR_SPACE: u32 = 0x57696E20;
+    ///
+    /// profile_data is the offset of a path to an ICC profile
+    /// 
+    pub const PROFILE_LINKED: u32 = 0x4C494E4B;
+    ///
+    /// profile_data is the offset of an ICC profile
+    /// 
+    pub const PROFILE_EMBEDDED: u32 = 0x4D424544;
+}
+
+///
+/// Additional fields present in a BITMAPV5HEADER, following the
+/// fields of the BitMapV4Header.
+/// 
+#[allow(dead_code)]
+pub struct BitMapV5Header {
+    ///
+    /// Rendering intent.
+    ///     1 = LCS_GM_BUSINESS  saturation
+    ///     2 = LCS_GM_GRAPHICS  relative colorimetric
+    ///     4 = LCS_GM_IMAGES  perceptual
+    ///     8 = LCS_GM_ABS_COLORIMETRIC  absolute colorimetric
+    /// 
+    pub intent: u32,
+    ///
+    /// Offset of the profile data from the start of the info header.
+    /// 
+    pub profile_data: u32,
+    ///
+    /// Size of the profile data.
+    /// 
+    pub profile_size: u32,
+    ///
+    /// Should be 0.
+    /// 
+    pub reserved: u32
+}
+
+impl BitMapV5Header {
+    ///
+    /// Saturation rendering intent
+    /// 
+    pub const LCS_GM_BUSINESS: u32 = 1;
+
+    ///
+    /// Relative colorimetric rendering intent
+    /// 
+    pub const LCS_GM_GRAPHICS: u32 = 2;
+
+    ///
+    /// Perceptual rendering intent
+    /// 
+    pub const LCS_GM_IMAGES: u32 = 4;
+
+    ///
+    /// Absolute colorimetric rendering intent
+    /// 
+    pub const LCS_GM_ABS_COLORIMETRIC: u32 = 8;
+}
+
+///
+/// A CIE XYZ coordinate, with each component in
+/// signed 2.30 fixed point.
+/// 
+#[allow(dead_code)]
+pub struct BitMapCieXyz {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32
+}
+
+///
+/// CIE XYZ coordinates of the red, green and blue
+/// endpoints of a color space.
+/// 
+#[allow(dead_code)]
+pub struct BitMapCieXyzTriple {
+    pub red: BitMapCieXyz,
+    pub green: BitMapCieXyz,
+    pub blue: BitMapCieXyz
+}
+
+///
+/// Masks describing where each channel is stored
+/// within a 16 or 32 bit pixel.
+/// A mask of 0 indicates the channel is not present.
+/// 
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct BitMapChannelMasks {
+    pub red: u32,
+    pub green: u32,
+    pub blue: u32,
+    pub alpha: u32
+}
+
+impl BitMapChannelMasks {
+    ///
+    /// Default masks for a 16 bit bitmap without BI_BITFIELDS,
+    /// with 5 bits each for red, green and blue (X1R5G5B5)
+    /// 
+    pub const RGB555: BitMapChannelMasks = BitMapChannelMasks {
+        red: 0x7C00,
+        green: 0x03E0,
+        blue: 0x001F,
+        alpha: 0
+    };
+
+    ///
+    /// 16 bit masks with 5 bits for red and blue, and 6 for green
+    /// 
+    pub const RGB565: BitMapChannelMasks = BitMapChannelMasks {
+        red: 0xF800,
+        green: 0x07E0,
+        blue: 0x001F,
+        alpha: 0
+    };
+
+    ///
+    /// 16 bit masks with 5 bits each for red, green and blue,
+    /// and 1 bit for alpha (A1R5G5B5)
+    /// 
+    pub const ARGB1555: BitMapChannelMasks = BitMapChannelMasks {
+        red: 0x7C00,
+        green: 0x03E0,
+        blue: 0x001F,
+        alpha: 0x8000
+    };
+
+    ///
+    /// 24 bit masks, with 8 bits each for red, green and blue
+    /// 
+    pub const RGB888: BitMapChannelMasks = BitMapChannelMasks {
+        red: 0xFF0000,
+        green: 0x00FF00,
+        blue: 0x0000FF,
+        alpha: 0
+    };
+
+    ///
+    /// 32 bit masks, with 8 bits each for alpha, red, green and blue (A8R8G8B8)
+    /// 
+    pub const ARGB8888: BitMapChannelMasks = BitMapChannelMasks {
+        red: 0x00FF0000,
+        green: 0x0000FF00,
+        blue: 0x000000FF,
+        alpha: 0xFF000000
+    };
+}
+
 ///
 /// Bitmap color definitions.
 /// Ordered Red-Green-Blue-Reserved,
@@ -125,6 +488,174 @@ pub struct BitMapColorTable {
 /// The actual image data in the bitmap.
 /// 
 #[allow(dead_code)]
-pub struct BitMapPixelData {
-    pub pixels: Vec<RGBColor>
+pub enum BitMapPixelData {
+    ///
+    /// The color of each pixel
+    /// 
+    Color(Vec<RGBColor>),
+    ///
+    /// The index of each pixel's color in the color table.
+    /// Only bitmaps with a bit depth of 8 or less are indexed.
+    /// 
+    Indexed(Vec<u8>)
+}
+
+impl BitMapPixelData {
+    ///
+    /// The number of pixels
+    /// 
+    pub fn len(&self) -> usize {
+        match self {
+            BitMapPixelData::Color(pixels) => pixels.len(),
+            BitMapPixelData::Indexed(indices) => indices.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        matches!(self, BitMapPixelData::Indexed(_))
+    }
+
+    ///
+    /// Get the color of the pixel at the given index, looking it up in
+    /// the given color table if the pixel data is indexed.
+    /// None if there is no such pixel, or it is not in the color table.
+    /// 
+    pub fn get(&self, index: usize, color_table: &BitMapColorTable) -> Option<RGBColor> {
+        match self {
+            BitMapPixelData::Color(pixels) => pixels.get(index).cloned(),
+            BitMapPixelData::Indexed(indices) => indices.get(index)
+                .and_then(|color_index| color_table.colors.get(*color_index as usize))
+                .cloned()
+        }
+    }
+
+    ///
+    /// Get the color of every pixel, looking each up in the given color
+    /// table if the pixel data is indexed.
+    /// 
+    pub fn to_rgba(&self, color_table: &BitMapColorTable) -> Result<Vec<RGBColor>, BmpError> {
+        match self {
+            BitMapPixelData::Color(pixels) => Ok(pixels.clone()),
+            BitMapPixelData::Indexed(indices) => indices.iter()
+                .map(|color_index| RGBColor::from_table(color_table, *color_index as usize))
+                .collect()
+        }
+    }
+}
+
+impl BitMapRaw {
+    ///
+    /// Create a top-down bitmap from the given pixel data, ordered from the top row to the bottom row.
+    /// Indexed pixel data refers to the given color table.
+    /// 
+    pub fn from_pixels(width: u32, height: u32, bits_per_pixel: u16, color_table: BitMapColorTable, pixel_data: BitMapPixelData) -> Result<Self, BmpError> {
+        let invalid_dimensions = || BmpError::InvalidDimensions {
+            width: width.min(i32::MAX as u32) as i32,
+            height: height.min(i32::MAX as u32) as i32
+        };
+
+        let signed_width = i32::try_from(width).map_err(|_| invalid_dimensions())?;
+        let signed_height = i32::try_from(height).map_err(|_| invalid_dimensions())?;
+
+        let pixel_count = (width as usize).checked_mul(height as usize).ok_or_else(invalid_dimensions)?;
+
+        if pixel_data.len() != pixel_count {
+            return Err(BmpError::PixelCountMismatch {
+                expected: pixel_count,
+                actual: pixel_data.len()
+            });
+        }
+
+        //The pixels keep whatever alpha their source gave them
+        let has_alpha = match &pixel_data {
+            BitMapPixelData::Color(colors) => colors.iter().any(|color| color.alpha != 0xFF),
+            BitMapPixelData::Indexed(_) => color_table.colors.iter().any(|color| color.alpha != 0xFF)
+        };
+
+        Ok(Self {
+            header: BitMapHeader {
+                signature: BitMapHeader::SIGNATURE,
+                file_size: 0,
+                reserved: 0,
+                data_offset: 0
+            },
+            info_header: BitMapInfoHeader {
+                size: BitMapInfoHeader::SIZE_INFO,
+                width: signed_width,
+                height: -signed_height,
+                planes: 1,
+                bits_per_pixel,
+                compression: BitMapInfoHeader::BI_RGB,
+                image_size: 0,
+                x_pixels_per_meter: 0,
+                y_pixels_per_meter: 0,
+                colors_used: color_table.colors.len() as u32,
+                important_colors: 0
+            },
+            v4_header: None,
+            v5_header: None,
+            os2_header: None,
+            color_table,
+            pixel_data,
+            has_alpha,
+            warnings: Vec::new()
+        })
+    }
+
+    ///
+    /// Get the color of the pixel at the given column and row, counting from the top left of the image
+    /// 
+    pub fn get_pixel_at(&self, x: usize, y: usize) -> Option<RGBColor> {
+        self.get_pixel_index(x, y).and_then(|index| self.get_pixel(index))
+    }
+
+    ///
+    /// Get the index in the pixel data of the pixel at the given column and row,
+    /// counting from the top left of the image
+    /// 
+    pub fn get_pixel_index(&self, x: usize, y: usize) -> Option<usize> {
+        let width = self.info_header.width.unsigned_abs() as usize;
+        let height = self.info_header.height.unsigned_abs() as usize;
+
+        if x >= width || y >= height {
+            return None;
+        }
+
+        //The rows of the pixel data are bottom-up, unless height is negative
+        let row = match self.info_header.height {
+            h if h < 0 => y,
+            _ => height - y - 1
+        };
+
+        //The columns of the pixel data are right-to-left if width is negative
+        let column = match self.info_header.width {
+            w if w < 0 => width - x - 1,
+            _ => x
+        };
+
+        Some((row * width) + column)
+    }
+
+    ///
+    /// Get the color of the pixel at the given index of the pixel data
+    /// 
+    pub fn get_pixel(&self, index: usize) -> Option<RGBColor> {
+        self.pixel_data.get(index, &self.color_table)
+    }
+
+    ///
+    /// Replace indexed pixel data with the color of each pixel.
+    /// Does nothing if the pixel data is not indexed.
+    /// 
+    pub fn expand_palette(&mut self) -> Result<(), BmpError> {
+        if self.pixel_data.is_indexed() {
+            self.pixel_data = BitMapPixelData::Color(self.pixel_data.to_rgba(&self.color_table)?);
+        }
+
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
 use super::super::*;
+use super::super::super::BmpError;
 
 impl Clone for RGBColor {
     fn clone(&self) -> Self {
@@ -109,11 +110,73 @@ impl RGBColor {
     /// Create a RGBColor from the palette color at the given index
     /// of the palette table
     /// 
-    pub fn from_table(palette: &BitMapPixelData, index: usize) -> Self {
-        if palette.pixels.len() <= index {
-            panic!("Tried to access index {} of palette, which only has {} entries!", index, palette.pixels.len());
+    pub fn from_table(palette: &BitMapColorTable, index: usize) -> Result<Self, BmpError> {
+        match palette.colors.get(index) {
+            Some(color) => Ok(color.clone()),
+            None => Err(BmpError::PaletteIndexOutOfRange {
+                index,
+                palette_size: palette.colors.len()
+            })
         }
+    }
+
+    ///
+    /// Create a RGBColor from a 16 or 32 bit pixel value, using the given
+    /// masks to extract each channel, and scaling each channel to 8 bits.
+    /// If there is no alpha mask, the color will be fully opaque.
+    /// 
+    pub fn from_bitfields(value: u32, masks: &BitMapChannelMasks) -> Self {
+        fn extract_channel(value: u32, mask: u32) -> Option<u8> {
+            if mask == 0 {
+                return None;
+            }
+
+            //Shift the channel down to the lowest bits, and scale it from its own bit depth to 8 bits
+            let shift = mask.trailing_zeros();
+            let max = (mask >> shift) as u64;
+            let channel = ((value & mask) >> shift) as u64;
+
+            Some(((channel * 255 + max / 2) / max) as u8)
+        }
+
+        RGBColor {
+            red: extract_channel(value, masks.red).unwrap_or(0),
+            green: extract_channel(value, masks.green).unwrap_or(0),
+            blue: extract_channel(value, masks.blue).unwrap_or(0),
+            alpha: extract_channel(value, masks.alpha).unwrap_or(0xFF)
+        }
+    }
 
-        palette.pixels[index].clone()
+    ///
+    /// Convert the RGBColor to a 16 or 32 bit pixel value, using the given
+    /// masks to place each channel, and scaling each channel from 8 bits to
+    /// the size of its mask. Channels without a mask are left out.
+    /// 
+    pub fn to_bitfields(&self, masks: &BitMapChannelMasks) -> u32 {
+        fn insert_channel(channel: u8, mask: u32) -> u32 {
+            if mask == 0 {
+                return 0;
+            }
+
+            //Scale the channel from 8 bits to the bit depth of the mask, and shift it up into the mask
+            let shift = mask.trailing_zeros();
+            let max = (mask >> shift) as u64;
+            let value = ((channel as u64 * max) + 127) / 255;
+
+            ((value as u32) << shift) & mask
+        }
+
+        insert_channel(self.red, masks.red)
+            | insert_channel(self.green, masks.green)
+            | insert_channel(self.blue, masks.blue)
+            | insert_channel(self.alpha, masks.alpha)
+    }
+
+    ///
+    /// Get the luminance of the color, from 0 to 255, weighting each
+    /// channel by how bright it appears (ITU-R BT.601). Alpha is ignored.
+    /// 
+    pub fn get_luminance(&self) -> u8 {
+        ((self.red as u32 * 299 + self.green as u32 * 587 + self.blue as u32 * 114 + 500) / 1000) as u8
     }
-}
\ No newline at end of file
+}
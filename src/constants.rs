@@ -3,6 +3,30 @@
 /// 
 pub const FILE_PATH_KEY: &str = "path";
 
+///
+/// Value for the file path argument indicating that
+/// the bitmap should be read from stdin.
+/// 
+pub const FILE_PATH_STDIN: &str = "-";
+
+///
+/// Command line argument key for the path to convert the
+/// image to, rather than drawing it
+/// 
+pub const OUTPUT_PATH_KEY: &str = "output";
+
+///
+/// Command line argument key for the index of the image to read
+/// from a file holding several, like an icon or bitmap array
+/// 
+pub const ENTRY_KEY: &str = "entry";
+
+///
+/// Command line argument key for whether to recover what
+/// can be recovered from a damaged bitmap, rather than failing
+/// 
+pub const LENIENT_KEY: &str = "lenient";
+
 ///
 /// Command line argument key for the color representing
 /// transparency.
@@ -41,6 +65,99 @@ pub const PIXEL_STRING_WIDTH_KEY: &str = "pixel_width";
 /// 
 pub const PIXEL_STRING_WIDTH_DEFAULT: u32 = 1;
 
+///
+/// Command line argument key for how pixels are drawn in
+/// the cells of the console
+/// 
+pub const RENDER_MODE_KEY: &str = "render_mode";
+
+///
+/// Draw each pixel with the pixel strings
+/// 
+pub const RENDER_MODE_GLYPH: &str = "glyph";
+
+///
+/// Draw two pixels, one above the other, in each cell with half blocks
+/// 
+pub const RENDER_MODE_HALF_BLOCK: &str = "half_block";
+
+///
+/// Draw 2x2 pixels in each cell with quadrant blocks
+/// 
+pub const RENDER_MODE_QUADRANT: &str = "quadrant";
+
+///
+/// Draw 2x3 pixels in each cell with sextant blocks
+/// 
+pub const RENDER_MODE_SEXTANT: &str = "sextant";
+
+///
+/// Draw 2x4 pixels in each cell with Braille dots
+/// 
+pub const RENDER_MODE_BRAILLE: &str = "braille";
+
+///
+/// Draw 2x4 pixels in each cell with the closest of the symbols
+/// 
+pub const RENDER_MODE_SYMBOLS: &str = "symbols";
+
+///
+/// The default way to draw pixels in the console
+/// 
+pub const RENDER_MODE_DEFAULT: &str = RENDER_MODE_GLYPH;
+
+///
+/// Command line argument key for how the pixels in each cell are
+/// split between two colors, in the quadrant, sextant and braille modes
+/// 
+pub const CELL_SPLIT_KEY: &str = "cell_split";
+
+///
+/// Split the pixels in each cell by whether they are brighter than its average
+/// 
+pub const CELL_SPLIT_THRESHOLD: &str = "threshold";
+
+///
+/// Split the pixels in each cell in the way closest to their colors
+/// 
+pub const CELL_SPLIT_BEST_FIT: &str = "best_fit";
+
+///
+/// The default way to split the pixels in each cell
+/// 
+pub const CELL_SPLIT_DEFAULT: &str = CELL_SPLIT_BEST_FIT;
+
+///
+/// Command line argument key for the sets of symbols to
+/// choose from in the symbols mode
+/// 
+pub const SYMBOLS_KEY: &str = "symbols";
+
+///
+/// The full, half, eighth and quadrant blocks
+/// 
+pub const SYMBOLS_BLOCKS: &str = "blocks";
+
+///
+/// Triangles filling half of a cell
+/// 
+pub const SYMBOLS_WEDGES: &str = "wedges";
+
+///
+/// Lines, corners, tees, crosses and diagonals
+/// 
+pub const SYMBOLS_BOX_DRAWING: &str = "box_drawing";
+
+///
+/// Default sets of symbols to choose from
+/// 
+pub const SYMBOLS_DEFAULT: &str = "blocks,wedges,box_drawing";
+
+///
+/// Delimiter to split the sets of symbols
+/// 
+pub const SYMBOLS_DELIMITER: &str = ",";
+
 ///
 /// Command line argument key for the algorithm with which to
 /// calculate the nearest console color when truecolor is disabled
@@ -104,6 +221,21 @@ pub const TRUECOLOR_ENABLED_VALUE_TRUECOLOR: &str = "truecolor";
 /// 
 pub const TRUECOLOR_ENABLED_VALUE_24BIT: &str = "24bit";
 
+///
+/// Environment variable for the width of the terminal, in characters
+/// 
+pub const TERMINAL_WIDTH_ENV_KEY: &str = "COLUMNS";
+
+///
+/// Environment variable for the height of the terminal, in characters
+/// 
+pub const TERMINAL_HEIGHT_ENV_KEY: &str = "LINES";
+
+///
+/// Width and height of the terminal, in characters, if it can't be found
+/// 
+pub const TERMINAL_SIZE_DEFAULT: (u32, u32) = (80, 24);
+
 ///
 /// Prefix for command line arguments.
 /// 
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::io::BufReader;
 use regex::Regex;
 
 pub mod bmp;
@@ -129,6 +130,47 @@ fn main() {
         }
     };
 
+    let render_mode_name = match args.get(RENDER_MODE_KEY) {
+        None => RENDER_MODE_DEFAULT,
+        Some(value) => value.as_str()
+    };
+
+    let render_mode = match render_mode_name.to_lowercase().as_str() {
+        RENDER_MODE_GLYPH => ConsoleRenderMode::Glyph,
+        RENDER_MODE_HALF_BLOCK => ConsoleRenderMode::HalfBlock,
+        RENDER_MODE_QUADRANT => ConsoleRenderMode::Quadrant,
+        RENDER_MODE_SEXTANT => ConsoleRenderMode::Sextant,
+        RENDER_MODE_BRAILLE => ConsoleRenderMode::Braille,
+        RENDER_MODE_SYMBOLS => ConsoleRenderMode::Symbols,
+        _ => panic!("{render_mode_name} is not a valid render mode.")
+    };
+
+    let cell_split_name = match args.get(CELL_SPLIT_KEY) {
+        None => CELL_SPLIT_DEFAULT,
+        Some(value) => value.as_str()
+    };
+
+    let cell_split = match cell_split_name.to_lowercase().as_str() {
+        CELL_SPLIT_THRESHOLD => ConsoleCellSplit::Threshold,
+        CELL_SPLIT_BEST_FIT => ConsoleCellSplit::BestFit,
+        _ => panic!("{cell_split_name} is not a valid way to split cells.")
+    };
+
+    let symbol_sets: &str = match args.get(SYMBOLS_KEY) {
+        None => SYMBOLS_DEFAULT,
+        Some(value) => value.as_str()
+    };
+
+    let symbols = symbol_sets.split(SYMBOLS_DELIMITER)
+        .flat_map(|set| match set.trim().to_lowercase().as_str() {
+            SYMBOLS_BLOCKS => ConsoleSymbol::blocks(),
+            SYMBOLS_WEDGES => ConsoleSymbol::wedges(),
+            SYMBOLS_BOX_DRAWING => ConsoleSymbol::box_drawing(),
+            _ => panic!("{set} is not a valid set of symbols.")
+        })
+        .collect::<Vec<ConsoleSymbol>>();
+
+    println!("Render mode: {render_mode_name}.");
     println!("Pixel opacities representation: {pixel_strings}.");
     println!("Characters per pixel: {pixel_width}.");
 
@@ -176,20 +218,185 @@ fn main() {
         println!("No background color given.");
     }
 
+    let lenient: bool = args.contains_key(LENIENT_KEY) && String::from(args.get(LENIENT_KEY).unwrap()).eq(true.to_string().as_str());
+
+    let mut read_settings = BitMapRawReadSettings::default();
+
+    if lenient {
+        read_settings.with_mode(DecodeMode::Lenient);
+    }
+
+    let entry_index: Option<u32> = match args.get(ENTRY_KEY) {
+        None => None,
+        Some(value) => {
+            match string_to_u32(value, 10, &u32_regex) {
+                None => panic!("{value} is not a valid value for '{ENTRY_KEY}'."),
+                Some(x) => Some(x)
+            }
+        }
+    };
+
+    let mut draw_settings = BitMapRawDrawToConsoleSettings::new(transparent_color, use_truecolor, pixel_width, opacity_levels, background_color, algorithm);
+    draw_settings.with_render_mode(render_mode);
+    draw_settings.with_cell_split(cell_split);
+    draw_settings.with_symbols(symbols);
+
     println!("Reading file:");
 
-    let bitmap = match BitMapRaw::read_from_file(&path) {
+    //The format of the image is recognized from the bytes it begins with
+    let codecs = CodecRegistry::default();
+
+    //The image is only read up to the allocation limit, so a huge file or pipe is never buffered in full
+    let buffer_result = if path == FILE_PATH_STDIN {
+        //Read the image from stdin, which cannot seek to find its length
+        BitMapRaw::read_stream_to_buffer(&mut std::io::stdin().lock(), &read_settings)
+    }
+    else {
+        std::fs::File::open(&path)
+            .map_err(BmpError::from)
+            .and_then(|file| BitMapRaw::read_to_buffer(&mut BufReader::new(file), &read_settings))
+    };
+
+    //TGA images have no signature, so they are recognized by their extension
+    let extension = std::path::Path::new(&path).extension()
+        .and_then(|extension| extension.to_str());
+
+    let entry_index = entry_index.map(|entry| entry as usize);
+
+    //Icons, cursors and bitmap arrays hold several images, which are listed so that one can be chosen
+    let read_result = buffer_result.and_then(|buffer| match ImageFormat::from_magic(&buffer) {
+        Some(ImageFormat::Ico) => read_ico_entry(&buffer, entry_index, &read_settings),
+        Some(ImageFormat::BitmapArray) => read_bitmap_array_entry(&buffer, entry_index, &draw_settings, &read_settings),
+        _ => codecs.decode_frames(&buffer, extension, &read_settings)
+    });
+
+    //Still images are read as an animation of a single frame
+    let animation = match read_result {
         Err(msg) => {
             panic!("{msg}");
         },
-        Ok(bmp) => bmp
+        Ok(animation) => animation
     };
     
     println!("Successfully read file.");
 
-    println!("Drawing to console:");
+    for warning in &animation.warnings {
+        println!("Warning: {warning}");
+    }
+
+    let bitmap = &animation.frames[0].bitmap;
+
+    //If an output path is given, convert the image to the format of its extension instead of drawing it
+    if let Some(output_path) = args.get(OUTPUT_PATH_KEY) {
+        match codecs.write_to_file(bitmap, output_path) {
+            Err(BmpError::UnsupportedFormat(_)) => eprintln!("Cannot write '{output_path}': the extension must be one of {}.", codecs.encoder_extensions().join(", ")),
+            Err(msg) => panic!("{msg}"),
+            Ok(_) => println!("Successfully wrote {output_path}.")
+        }
+
+        return;
+    }
+
+    if animation.frames.len() > 1 {
+        println!("Playing {} frames in console:", animation.frames.len());
+        animation.draw_to_console(&draw_settings);
+    }
+    else {
+        println!("Drawing to console:");
+        bitmap.draw_to_console(&draw_settings);
+    }
+}
+
+///
+/// List the images of an icon or cursor, and read the one at the given index, or else the largest
+/// 
+fn read_ico_entry(buffer: &[u8], entry_index: Option<usize>, settings: &BitMapRawReadSettings) -> Result<BitMapAnimation, BmpError> {
+    let entries = BitMapRaw::ico_entries_from_bytes(buffer)?;
+
+    println!("Images in the icon:");
 
-    bitmap.draw_to_console(&BitMapRawDrawToConsoleSettings::new(transparent_color, use_truecolor, pixel_width, opacity_levels, background_color, algorithm));
+    for (index, entry) in entries.iter().enumerate() {
+        println!(" - {index}: {entry}");
+    }
+
+    let index = match entry_index.or_else(|| BitMapRaw::best_ico_entry(&entries)) {
+        Some(index) => index,
+        None => return Err(BmpError::MalformedHeader(String::from("The icon has no images.")))
+    };
+
+    match entries.get(index) {
+        Some(entry) => {
+            println!("Reading image {index}.");
+            BitMapRaw::decode_ico_entry(buffer, entry, settings).map(BitMapAnimation::from)
+        },
+        None => Err(BmpError::EntryOutOfRange {
+            index,
+            count: entries.len()
+        })
+    }
+}
+
+///
+/// List the bitmaps of a bitmap array, and read the one at the given index, or else the one which best fits in the terminal
+/// 
+fn read_bitmap_array_entry(buffer: &[u8], entry_index: Option<usize>, draw_settings: &BitMapRawDrawToConsoleSettings, settings: &BitMapRawReadSettings) -> Result<BitMapAnimation, BmpError> {
+    let array = BitMapArray::from_bytes_with_settings(buffer, settings)?;
+
+    println!("Bitmaps in the array:");
+
+    for (index, entry) in array.entries.iter().enumerate() {
+        println!(" - {index}: {entry}");
+    }
+
+    //The size of the terminal is only needed to choose a bitmap
+    let index = entry_index.or_else(|| {
+        let (terminal_width, terminal_height) = get_terminal_size();
+        let (width, height) = draw_settings.pixels_fitting(terminal_width, terminal_height);
+
+        array.best_fit(width, height)
+    });
+
+    match index {
+        Some(index) => {
+            if index < array.entries.len() {
+                println!("Reading bitmap {index}.");
+            }
+
+            array.into_entry(index).map(BitMapAnimation::from)
+        },
+        None => Err(BmpError::MalformedHeader(String::from("The bitmap array has no bitmaps.")))
+    }
+}
+
+///
+/// Get the width and height of the terminal, in characters, from the environment,
+/// or else from stty, or else the default size.
+/// 
+fn get_terminal_size() -> (u32, u32) {
+    let from_env = |key: &str| std::env::var(key).ok().and_then(|value| value.trim().parse::<u32>().ok());
+
+    if let (Some(width), Some(height)) = (from_env(TERMINAL_WIDTH_ENV_KEY), from_env(TERMINAL_HEIGHT_ENV_KEY)) {
+        return (width, height);
+    }
+
+    //stty gives the number of rows, then the number of columns
+    let stty = std::fs::File::open("/dev/tty")
+        .and_then(|tty| std::process::Command::new("stty").arg("size").stdin(tty).output());
+
+    if let Ok(output) = stty {
+        let size: Vec<u32> = String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .filter_map(|value| value.parse::<u32>().ok())
+            .collect();
+
+        if let [height, width] = size[..] {
+            if width > 0 && height > 0 {
+                return (width, height);
+            }
+        }
+    }
+
+    TERMINAL_SIZE_DEFAULT
 }
 
 ///
@@ -254,12 +461,36 @@ fn print_help() {
         ),
         (
             FILE_PATH_KEY,
-            "The path to the bitmap.".to_string(),
+            "The path to the image. Its format is recognized from the bytes it begins with, whatever its extension.".to_string(),
             pair_example(FILE_PATH_KEY),
             "".to_string(),
-            "Must be a valid filepath (either relative or absolute) to a bitmap.".to_string(),
+            format!("Must be a valid filepath (either relative or absolute) to a bitmap, OS/2 bitmap array, PNG, QOI, TGA, GIF, Netpbm (PBM/PGM/PPM/PAM) or ICO/CUR image, or {FILE_PATH_STDIN} to read the image from stdin. TGA images are recognized by a .tga extension, so cannot be read from stdin. Animated GIFs are played in the console."),
+            None
+        ),
+        (
+            OUTPUT_PATH_KEY,
+            "When set, the image is converted and written to this path instead of being drawn. The format is chosen by the extension of the path. Only the first frame of an animated GIF is written.".to_string(),
+            pair_example(OUTPUT_PATH_KEY),
+            format!("Must end in one of {}.", CodecRegistry::default().encoder_extensions().join(", ")),
+            format!("{ARGUMENT_PREFIX}{OUTPUT_PATH_KEY}{ARGUMENT_DELIMITER}image.qoi writes the image as QOI."),
             None
         ),
+        (
+            ENTRY_KEY,
+            "The index of the image to read from an icon, cursor or OS/2 bitmap array, which hold several images. The images are listed when the file is read.".to_string(),
+            pair_example(ENTRY_KEY),
+            "Must be a non-negative integer less than the number of images in the file.".to_string(),
+            format!("{ARGUMENT_PREFIX}{ENTRY_KEY}{ARGUMENT_DELIMITER}0 reads the first image."),
+            Some("For icons and cursors, the largest image, with the most bits per pixel. For bitmap arrays, the largest bitmap which fits in the terminal.")
+        ),
+        (
+            LENIENT_KEY,
+            "When set, will draw as much of a damaged bitmap as can be recovered, rather than failing. Missing pixels are drawn as transparent.".to_string(),
+            flag_example(LENIENT_KEY),
+            flag_key_restriction.to_string(),
+            "".to_string(),
+            Some(false_string)
+        ),
         (
             TRANSPARENCY_COLOR_KEY,
             "A 32-bit, RGBA color representing transparency. Can be in decimal, binary (prefixed with 0b), or hex (prefixed with 0x).".to_string(),
@@ -284,6 +515,30 @@ fn print_help() {
             "".to_string(),
             Some(false_string)
         ),
+        (
+            RENDER_MODE_KEY,
+            format!("How pixels are drawn in the console. {RENDER_MODE_GLYPH} draws each pixel with {{{ARGUMENT_PREFIX}{PIXEL_STRINGS_KEY}}}. {RENDER_MODE_HALF_BLOCK} draws two pixels, one above\r\n      the other, in each character with ▀ and ▄, so images are half as tall. {RENDER_MODE_QUADRANT}, {RENDER_MODE_SEXTANT} and {RENDER_MODE_BRAILLE} draw 2x2, 2x3 and 2x4 pixels\r\n      in each character, split between two colors by {{{ARGUMENT_PREFIX}{CELL_SPLIT_KEY}}}. Sextants need a font with Unicode 13 symbols. {RENDER_MODE_SYMBOLS} draws 2x4 pixels\r\n      in each character as whichever of {{{ARGUMENT_PREFIX}{SYMBOLS_KEY}}}, and pair of colors, is closest to them by {{{ARGUMENT_PREFIX}{CONSOLE_COLOR_ALGORITHM_KEY}}}.\r\n      Transparent pixels show {{{ARGUMENT_PREFIX}{BACKGROUND_COLOR_KEY}}}, if given."),
+            pair_example(RENDER_MODE_KEY),
+            format!("[{RENDER_MODE_GLYPH}, {RENDER_MODE_HALF_BLOCK}, {RENDER_MODE_QUADRANT}, {RENDER_MODE_SEXTANT}, {RENDER_MODE_BRAILLE}, {RENDER_MODE_SYMBOLS}]"),
+            "".to_string(),
+            Some(RENDER_MODE_DEFAULT)
+        ),
+        (
+            CELL_SPLIT_KEY,
+            format!("How the pixels in each character are split between a foreground and background color, when {{{ARGUMENT_PREFIX}{RENDER_MODE_KEY}}} is {RENDER_MODE_QUADRANT}, {RENDER_MODE_SEXTANT}\r\n      or {RENDER_MODE_BRAILLE}. {CELL_SPLIT_THRESHOLD} puts pixels at least as bright as the average in the foreground. {CELL_SPLIT_BEST_FIT} tries every split, and keeps the one\r\n      whose colors are closest to the pixels by {{{ARGUMENT_PREFIX}{CONSOLE_COLOR_ALGORITHM_KEY}}}."),
+            pair_example(CELL_SPLIT_KEY),
+            format!("[{CELL_SPLIT_THRESHOLD}, {CELL_SPLIT_BEST_FIT}]"),
+            "".to_string(),
+            Some(CELL_SPLIT_DEFAULT)
+        ),
+        (
+            SYMBOLS_KEY,
+            format!("The comma-delimited sets of symbols to choose from for each character, when {{{ARGUMENT_PREFIX}{RENDER_MODE_KEY}}} is {RENDER_MODE_SYMBOLS}. {SYMBOLS_BLOCKS} are the full,\r\n      half, eighth and quadrant blocks, {SYMBOLS_WEDGES} are triangles filling half of a character, and {SYMBOLS_BOX_DRAWING} are lines, corners, tees, crosses and diagonals."),
+            pair_example(SYMBOLS_KEY),
+            format!("[{SYMBOLS_BLOCKS}, {SYMBOLS_WEDGES}, {SYMBOLS_BOX_DRAWING}]"),
+            format!("{ARGUMENT_PREFIX}{SYMBOLS_KEY}{ARGUMENT_DELIMITER}{SYMBOLS_BLOCKS},{SYMBOLS_WEDGES} leaves out box drawing characters."),
+            Some(SYMBOLS_DEFAULT)
+        ),
         (
             PIXEL_STRINGS_KEY,
             "The comma-delimited strings to use to represent pixels of different opacities when displaying the bitmap in the terminal, from most to least opaque.\r\n      If only one string is present, opacity will be ignored. For a bitmap with a depth < 32, only the first string will be considered.".to_string(),
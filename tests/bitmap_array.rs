@@ -0,0 +1,129 @@
+use bitmap_handler::bmp::*;
+
+mod common;
+use common::*;
+
+/// 
+/// A bitmap of the given size, filled with the given color
+/// 
+fn bitmap(width: u32, height: u32, color: (u8, u8, u8, u8)) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    image(width, &vec![color; (width * height) as usize]).write_to(&mut bytes).unwrap();
+    bytes
+}
+
+/// 
+/// Chain the given bitmaps into a bitmap array, each with the given display width and height
+/// 
+fn array(entries: &[(u16, u16, &[u8])]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+
+    for (index, (display_width, display_height, bitmap)) in entries.iter().enumerate() {
+        let start = bytes.len();
+        let next = match index + 1 < entries.len() {
+            true => (start + 14 + bitmap.len()) as u32,
+            false => 0
+        };
+
+        bytes.extend(b"BA");
+        bytes.extend(40_u32.to_le_bytes());
+        bytes.extend(next.to_le_bytes());
+        bytes.extend(display_width.to_le_bytes());
+        bytes.extend(display_height.to_le_bytes());
+
+        //The data offset of each bitmap counts from the start of the array
+        let mut bitmap = bitmap.to_vec();
+        let data_offset = u32::from_le_bytes(bitmap[10..14].try_into().unwrap()) + (start + 14) as u32;
+        bitmap[10..14].copy_from_slice(&data_offset.to_le_bytes());
+
+        bytes.extend(bitmap);
+    }
+
+    bytes
+}
+
+fn sample() -> Vec<u8> {
+    array(&[(640, 480, &bitmap(4, 2, RED)), (0, 0, &bitmap(2, 1, GREEN)), (1024, 768, &bitmap(8, 4, BLUE))])
+}
+
+fn array_of_one() -> Vec<u8> {
+    array(&[(0, 0, &bitmap(1, 1, RED))])
+}
+
+#[test]
+fn read_every_bitmap() {
+    let array = BitMapArray::from_bytes(&sample()).unwrap();
+
+    let sizes: Vec<(u32, u32, u16, u16)> = array.entries.iter()
+        .map(|entry| (entry.bitmap.info_header.width.unsigned_abs(), entry.bitmap.info_header.height.unsigned_abs(), entry.display_width, entry.display_height))
+        .collect();
+
+    assert_eq!(sizes, vec![(4, 2, 640, 480), (2, 1, 0, 0), (8, 4, 1024, 768)]);
+    assert_eq!(pixels(&array.entries[0].bitmap), vec![RED; 8]);
+    assert_eq!(pixels(&array.entries[1].bitmap), vec![GREEN; 2]);
+    assert_eq!(pixels(&array.entries[2].bitmap), vec![BLUE; 32]);
+
+    assert_eq!(array.entries[0].to_string(), "4x2, 24 bpp, for 640x480 displays");
+    assert_eq!(array.entries[1].to_string(), "2x1, 24 bpp, for any display");
+
+    //A single bitmap is an array of one
+    let single = BitMapArray::from_bytes(&array_of_one()).unwrap();
+    assert_eq!(single.entries.len(), 1);
+}
+
+#[test]
+fn choose_bitmap() {
+    let array = BitMapArray::from_bytes(&sample()).unwrap();
+
+    //The largest bitmap which fits, or else the smallest
+    assert_eq!(array.largest(), Some(2));
+    assert_eq!(array.best_fit(100, 100), Some(2));
+    assert_eq!(array.best_fit(7, 4), Some(0));
+    assert_eq!(array.best_fit(3, 3), Some(1));
+    assert_eq!(array.best_fit(1, 1), Some(1));
+
+    //The registry reads the largest bitmap, unless a codec choosing another is registered
+    let settings = BitMapRawReadSettings::default();
+    let mut codecs = CodecRegistry::default();
+    assert_eq!(pixels(&codecs.decode(&sample(), None, &settings).unwrap()).len(), 32);
+
+    codecs.register_decoder(Box::new(BitmapArrayCodec::new(None, Some((4, 2)))));
+    assert_eq!(pixels(&codecs.decode(&sample(), None, &settings).unwrap()), vec![RED; 8]);
+
+    codecs.register_decoder(Box::new(BitmapArrayCodec::new(Some(1), Some((4, 2)))));
+    assert_eq!(pixels(&codecs.decode(&sample(), None, &settings).unwrap()), vec![GREEN; 2]);
+
+    codecs.register_decoder(Box::new(BitmapArrayCodec::new(Some(3), None)));
+    assert!(matches!(codecs.decode(&sample(), None, &settings), Err(BmpError::EntryOutOfRange { index: 3, count: 3 })));
+}
+
+#[test]
+fn read_errors() {
+    let sample = sample();
+
+    assert!(matches!(BitMapArray::from_bytes(&sample[..10]), Err(BmpError::Truncated { needed: 14, available: 10 })));
+    assert!(matches!(BitMapArray::from_bytes(&bitmap(1, 1, RED)), Err(BmpError::BadSignature(0x4D42))));
+
+    //The chain must lead to another header
+    let mut broken = sample.clone();
+    broken[6..10].copy_from_slice(&16_u32.to_le_bytes());
+    assert!(matches!(BitMapArray::from_bytes(&broken), Err(BmpError::BadSignature(_))));
+
+    let mut truncated = sample.clone();
+    truncated[6..10].copy_from_slice(&(sample.len() as u32).to_le_bytes());
+    assert!(matches!(BitMapArray::from_bytes(&truncated), Err(BmpError::Truncated { .. })));
+
+    //The chain can't go back on itself
+    let mut looped = array(&[(0, 0, &bitmap(1, 1, RED)), (0, 0, &bitmap(1, 1, GREEN))]);
+    let second = u32::from_le_bytes(looped[6..10].try_into().unwrap()) as usize;
+    looped[second + 6..second + 10].copy_from_slice(&0_u32.to_le_bytes());
+    assert_eq!(BitMapArray::from_bytes(&looped).unwrap().entries.len(), 2);
+
+    looped[second + 6..second + 10].copy_from_slice(&(second as u32).to_le_bytes());
+    assert!(matches!(BitMapArray::from_bytes(&looped), Err(BmpError::MalformedHeader(_))));
+
+    //Each header must be followed by a bitmap
+    let mut not_bitmap = array_of_one();
+    not_bitmap[14..16].copy_from_slice(b"CI");
+    assert!(matches!(BitMapArray::from_bytes(&not_bitmap), Err(BmpError::BadSignature(0x4943))));
+}
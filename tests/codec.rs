@@ -0,0 +1,202 @@
+use std::io::Write;
+use bitmap_handler::bmp::*;
+
+mod common;
+use common::*;
+
+/// 
+/// A 3x2 image with an opaque pixel of each color
+/// 
+fn sample() -> BitMapRaw {
+    let colors = [(0xFF, 0, 0), (0, 0xFF, 0), (0, 0, 0xFF), (0xFF, 0xFF, 0), (0, 0xFF, 0xFF), (0xFF, 0xFF, 0xFF)]
+        .into_iter()
+        .map(|(red, green, blue)| RGBColor { red, green, blue, alpha: 0xFF })
+        .collect();
+
+    BitMapRaw::from_pixels(3, 2, 24, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(colors)).unwrap()
+}
+
+fn encode(codecs: &CodecRegistry, bitmap: &BitMapRaw, extension: &str) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    codecs.encode(bitmap, extension, &mut bytes).unwrap();
+    bytes
+}
+
+/// 
+/// A format which is a single byte, #, followed by the gray value of a 1x1 image
+/// 
+struct GrayCodec;
+
+impl Decoder for GrayCodec {
+    fn name(&self) -> &str {
+        "gray"
+    }
+
+    fn can_decode(&self, buffer: &[u8]) -> bool {
+        buffer.first() == Some(&b'#')
+    }
+
+    fn decode(&self, buffer: &[u8], _settings: &BitMapRawReadSettings) -> Result<BitMapRaw, BmpError> {
+        let value = *buffer.get(1).ok_or(BmpError::Truncated { needed: 2, available: buffer.len() })?;
+        let color = RGBColor { red: value, green: value, blue: value, alpha: 0xFF };
+
+        BitMapRaw::from_pixels(1, 1, 24, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(vec![color]))
+    }
+}
+
+impl Encoder for GrayCodec {
+    fn name(&self) -> &str {
+        "gray"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gray", "bmp"]
+    }
+
+    fn encode(&self, bitmap: &BitMapRaw, writer: &mut dyn Write) -> Result<(), BmpError> {
+        let color = bitmap.get_pixel_at(0, 0).unwrap();
+        writer.write_all(&[b'#', color.get_luminance()])?;
+        Ok(())
+    }
+}
+
+#[test]
+fn format_from_magic() {
+    assert_eq!(ImageFormat::from_magic(b"BM\x00\x00"), Some(ImageFormat::Bmp));
+    assert_eq!(ImageFormat::from_magic(b"BA\x00\x00"), Some(ImageFormat::BitmapArray));
+    assert_eq!(ImageFormat::from_magic(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]), Some(ImageFormat::Png));
+    assert_eq!(ImageFormat::from_magic(b"GIF89a"), Some(ImageFormat::Gif));
+    assert_eq!(ImageFormat::from_magic(b"qoif"), Some(ImageFormat::Qoi));
+    assert_eq!(ImageFormat::from_magic(&[0, 0, 1, 0, 1, 0]), Some(ImageFormat::Ico));
+    assert_eq!(ImageFormat::from_magic(&[0, 0, 2, 0, 3, 0]), Some(ImageFormat::Ico));
+
+    for magic in b'1'..=b'7' {
+        assert_eq!(ImageFormat::from_magic(&[b'P', magic, b'\n']), Some(ImageFormat::Netpbm));
+    }
+
+    //Too short, an unknown signature, a P without a number, and an icon without images
+    assert_eq!(ImageFormat::from_magic(b""), None);
+    assert_eq!(ImageFormat::from_magic(b"B"), None);
+    assert_eq!(ImageFormat::from_magic(b"qoi"), None);
+    assert_eq!(ImageFormat::from_magic(b"JFIF"), None);
+    assert_eq!(ImageFormat::from_magic(b"P8"), None);
+    assert_eq!(ImageFormat::from_magic(&[0, 0, 1, 0, 0, 0]), None);
+    assert_eq!(ImageFormat::from_magic(&[0x89, b'P', b'N', b'G']), None);
+}
+
+/// 
+/// Every built in format should read back what was written, whatever format it is
+/// 
+#[test]
+fn round_trip_each_format() {
+    let codecs = CodecRegistry::default();
+    let sample = sample();
+
+    for extension in ["bmp", "png", "qoi", "ppm", "pam", "tga"] {
+        let bytes = encode(&codecs, &sample, extension);
+
+        //TGA has no signature, so is only recognized by its extension
+        let hint = (extension == "tga").then_some(extension);
+
+        let bitmap = codecs.decode(&bytes, hint, &BitMapRawReadSettings::default()).unwrap();
+        assert_eq!(pixels(&bitmap), pixels(&sample), "{extension}");
+
+        let animation = codecs.decode_frames(&bytes, hint, &BitMapRawReadSettings::default()).unwrap();
+        assert_eq!(animation.frames.len(), 1, "{extension}");
+        assert_eq!(animation.loop_count, None, "{extension}");
+    }
+
+    //Extensions are not case sensitive
+    assert_eq!(encode(&codecs, &sample, "PNG"), encode(&codecs, &sample, "png"));
+
+    //The bytes take priority over the extension
+    let png = encode(&codecs, &sample, "png");
+    assert_eq!(pixels(&codecs.decode(&png, Some("tga"), &BitMapRawReadSettings::default()).unwrap()), pixels(&sample));
+}
+
+/// 
+/// Bitmaps should be written with an alpha mask if, and only if, the image has an alpha channel
+/// 
+#[test]
+fn write_bitmap_alpha() {
+    let codecs = CodecRegistry::default();
+
+    let colors = vec![RGBColor { red: 0xFF, green: 0, blue: 0, alpha: 0x80 }, RGBColor { red: 0, green: 0xFF, blue: 0, alpha: 0 }];
+    let translucent = BitMapRaw::from_pixels(2, 1, 32, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(colors)).unwrap();
+    assert!(translucent.has_alpha);
+
+    let written = BitMapRaw::from_bytes(&encode(&codecs, &translucent, "bmp")).unwrap();
+    assert_eq!(written.info_header.compression, BitMapInfoHeader::BI_BITFIELDS);
+    assert_eq!(written.v4_header.as_ref().unwrap().alpha_mask, BitMapChannelMasks::ARGB8888.alpha);
+    assert!(written.has_alpha);
+    assert_eq!(pixels(&written), pixels(&translucent));
+
+    //Opaque images have no alpha channel to keep
+    let opaque = sample();
+    assert!(!opaque.has_alpha);
+
+    let written = BitMapRaw::from_bytes(&encode(&codecs, &opaque, "bmp")).unwrap();
+    assert_eq!(written.info_header.compression, BitMapInfoHeader::BI_RGB);
+    assert!(written.v4_header.is_none());
+    assert!(!written.has_alpha);
+}
+
+#[test]
+fn read_every_sample_bitmap() {
+    let codecs = CodecRegistry::default();
+
+    for entry in std::fs::read_dir("input").unwrap() {
+        let path = entry.unwrap().path();
+        let path = path.to_str().unwrap();
+
+        let bitmap = codecs.read_from_file(path, &BitMapRawReadSettings::default()).unwrap();
+        let expected = BitMapRaw::read_from_file(path).unwrap();
+        assert_eq!(pixels(&bitmap), pixels(&expected), "{path}");
+    }
+}
+
+#[test]
+fn unsupported_formats() {
+    let codecs = CodecRegistry::default();
+    let settings = BitMapRawReadSettings::default();
+
+    let unsupported = |result: Result<BitMapRaw, BmpError>| matches!(result, Err(BmpError::UnsupportedFormat(_)));
+
+    assert!(unsupported(codecs.decode(b"JFIF", None, &settings)));
+    assert!(unsupported(codecs.decode(b"", Some("jpg"), &settings)));
+
+    //Without any codecs, nothing can be read or written
+    let empty = CodecRegistry::new();
+    assert!(unsupported(empty.decode(&encode(&codecs, &sample(), "bmp"), None, &settings)));
+    assert!(matches!(empty.encode(&sample(), "bmp", &mut Vec::new()), Err(BmpError::UnsupportedFormat(_))));
+
+    assert!(matches!(codecs.encode(&sample(), "jpg", &mut Vec::new()), Err(BmpError::UnsupportedFormat(_))));
+    assert!(codecs.find_encoder("").is_none());
+    assert!(codecs.find_decoder(b"\x00", None).is_none());
+}
+
+/// 
+/// Codecs registered later should be preferred, and add to the formats which can be read and written
+/// 
+#[test]
+fn register_codecs() {
+    let mut codecs = CodecRegistry::default();
+    assert!(!codecs.encoder_extensions().contains(&"gray"));
+
+    codecs.register_decoder(Box::new(GrayCodec));
+    codecs.register_encoder(Box::new(GrayCodec));
+
+    let gray = codecs.decode(b"#\x80", None, &BitMapRawReadSettings::default()).unwrap();
+    assert_eq!(pixels(&gray), vec![(0x80, 0x80, 0x80, 0xFF)]);
+    assert_eq!(codecs.find_decoder(b"#", None).unwrap().name(), "gray");
+
+    //The built in bitmap encoder is replaced, but the built in decoder is still used for bitmaps
+    assert_eq!(encode(&codecs, &gray, "gray"), b"#\x80");
+    assert_eq!(encode(&codecs, &gray, "bmp"), b"#\x80");
+    assert_eq!(codecs.find_encoder("bmp").unwrap().name(), "gray");
+    assert_eq!(codecs.find_decoder(b"BM", None).unwrap().name(), "bitmap");
+
+    let extensions = codecs.encoder_extensions();
+    assert!(extensions.contains(&"gray"));
+    assert_eq!(extensions.iter().filter(|extension| **extension == "bmp").count(), 1);
+}
@@ -0,0 +1,36 @@
+//Each test only uses some of the helpers
+#![allow(dead_code)]
+
+use bitmap_handler::bmp::*;
+
+pub const RED: (u8, u8, u8, u8) = (0xFF, 0, 0, 0xFF);
+pub const GREEN: (u8, u8, u8, u8) = (0, 0xFF, 0, 0xFF);
+pub const BLUE: (u8, u8, u8, u8) = (0, 0, 0xFF, 0xFF);
+pub const WHITE: (u8, u8, u8, u8) = (0xFF, 0xFF, 0xFF, 0xFF);
+pub const BLACK: (u8, u8, u8, u8) = (0, 0, 0, 0xFF);
+pub const CLEAR: (u8, u8, u8, u8) = (0, 0, 0, 0);
+
+/// 
+/// Get the colors of the image, from the top left, a row at a time
+/// 
+pub fn pixels(bitmap: &BitMapRaw) -> Vec<(u8, u8, u8, u8)> {
+    let width = bitmap.info_header.width.unsigned_abs() as usize;
+    let height = bitmap.info_header.height.unsigned_abs() as usize;
+
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| bitmap.get_pixel_at(x, y).unwrap())
+        .map(|p| (p.red, p.green, p.blue, p.alpha))
+        .collect()
+}
+
+/// 
+/// Build a 32 bpp image of the given width from the given colors, from the top left, a row at a time
+/// 
+pub fn image(width: u32, colors: &[(u8, u8, u8, u8)]) -> BitMapRaw {
+    let colors: Vec<RGBColor> = colors.iter()
+        .map(|(red, green, blue, alpha)| RGBColor { red: *red, green: *green, blue: *blue, alpha: *alpha })
+        .collect();
+
+    BitMapRaw::from_pixels(width, colors.len() as u32 / width, 32, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(colors)).unwrap()
+}
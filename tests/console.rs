@@ -0,0 +1,214 @@
+use bitmap_handler::bmp::*;
+
+mod common;
+use common::*;
+
+/// 
+/// Settings drawing in truecolor or the console's colors, with the given render mode and background
+/// 
+fn settings(use_truecolor: bool, render_mode: ConsoleRenderMode, background: Option<u32>) -> BitMapRawDrawToConsoleSettings {
+    let opacity_levels = vec![String::from("██"), String::from("▓▓")];
+    let mut settings = BitMapRawDrawToConsoleSettings::new(None, use_truecolor, 1, opacity_levels, background, RGBColor::get_euclidean_distance_rgb);
+    settings.with_render_mode(render_mode);
+    settings
+}
+
+/// 
+/// Split each row into its cells, each with its text and the ANSI codes it is drawn with
+/// 
+fn cells(rows: &[String]) -> Vec<Vec<(String, String)>> {
+    rows.iter()
+        .map(|row| row.split("\x1b[0m")
+            .filter(|cell| !cell.is_empty())
+            .map(|cell| match cell.strip_prefix("\x1b[") {
+                Some(colored) => {
+                    let (codes, text) = colored.split_once('m').unwrap();
+                    (text.to_string(), codes.to_string())
+                },
+                None => (cell.to_string(), String::new())
+            })
+            .collect())
+        .collect()
+}
+
+#[test]
+fn draw_half_blocks() {
+    colored::control::set_override(true);
+
+    //Three rows of pixels fit in two rows of cells, the last of which has no lower half
+    let bitmap = image(2, &[RED, GREEN, BLUE, CLEAR, GREEN, CLEAR]);
+    let rows = bitmap.to_console_rows(&settings(true, ConsoleRenderMode::HalfBlock, None));
+
+    assert_eq!(cells(&rows), vec![
+        vec![(String::from("▀"), String::from("48;2;0;0;255;38;2;255;0;0")), (String::from("▀"), String::from("38;2;0;255;0"))],
+        vec![(String::from("▀"), String::from("38;2;0;255;0")), (String::from(" "), String::new())]
+    ]);
+
+    //Glyphs draw a row of cells for each row of pixels
+    assert_eq!(bitmap.to_console_rows(&settings(true, ConsoleRenderMode::Glyph, None)).len(), 3);
+}
+
+#[test]
+fn draw_transparent_halves() {
+    colored::control::set_override(true);
+
+    //Transparent halves show the background, and partly transparent pixels are blended over it
+    let bitmap = image(3, &[CLEAR, RED, (0xFF, 0, 0, 0x80), BLUE, CLEAR, CLEAR]);
+    let rows = bitmap.to_console_rows(&settings(true, ConsoleRenderMode::HalfBlock, Some(0xFFFFFFFF)));
+
+    assert_eq!(cells(&rows), vec![vec![
+        (String::from("▄"), String::from("48;2;255;255;255;38;2;0;0;255")),
+        (String::from("▀"), String::from("48;2;255;255;255;38;2;255;0;0")),
+        (String::from("▀"), String::from("48;2;255;255;255;38;2;255;127;127"))
+    ]]);
+
+    //Without a background, partly transparent pixels are drawn as they are
+    let rows = bitmap.to_console_rows(&settings(true, ConsoleRenderMode::HalfBlock, None));
+    assert_eq!(cells(&rows)[0][2], (String::from("▀"), String::from("38;2;255;0;0")));
+}
+
+#[test]
+fn draw_without_alpha() {
+    colored::control::set_override(true);
+
+    //Without an alpha channel, the fourth byte of 32 bpp pixels is unused, and the pixels are drawn opaque
+    let mut bitmap = image(1, &[(0xFF, 0, 0, 0), (0, 0, 0xFF, 0)]);
+    bitmap.has_alpha = false;
+
+    let rows = bitmap.to_console_rows(&settings(true, ConsoleRenderMode::HalfBlock, None));
+    assert_eq!(cells(&rows), vec![vec![(String::from("▀"), String::from("48;2;0;0;255;38;2;255;0;0"))]]);
+
+    let rows = bitmap.to_console_rows(&settings(true, ConsoleRenderMode::Glyph, None));
+    assert_eq!(cells(&rows)[0], vec![(String::from("██"), String::from("38;2;255;0;0"))]);
+
+    //Both pixels fill the left half of the block, in their average color
+    let rows = bitmap.to_console_rows(&settings(true, ConsoleRenderMode::Quadrant, None));
+    assert_eq!(cells(&rows), vec![vec![(String::from("▌"), String::from("38;2;127;0;127"))]]);
+}
+
+#[test]
+fn draw_half_blocks_in_console_colors() {
+    colored::control::set_override(true);
+
+    //Each half is drawn in the closest of the console's colors
+    let bitmap = image(2, &[(0xF0, 0x10, 0x10, 0xFF), CLEAR, (0x10, 0x10, 0x90, 0xFF), (0x00, 0xF0, 0xF0, 0xFF)]);
+    let rows = bitmap.to_console_rows(&settings(false, ConsoleRenderMode::HalfBlock, None));
+
+    assert_eq!(cells(&rows), vec![vec![(String::from("▀"), String::from("44;91")), (String::from("▄"), String::from("96"))]]);
+}
+
+#[test]
+fn draw_blocks() {
+    colored::control::set_override(true);
+
+    let quadrant = settings(true, ConsoleRenderMode::Quadrant, None);
+    let sextant = settings(true, ConsoleRenderMode::Sextant, None);
+
+    //The best fit splits the pixels by color, with the lower right pixel in the foreground
+    let rows = image(2, &[RED, BLUE, BLUE, RED]).to_console_rows(&quadrant);
+    assert_eq!(cells(&rows), vec![vec![(String::from("▚"), String::from("48;2;0;0;255;38;2;255;0;0"))]]);
+
+    let rows = image(2, &[RED, BLUE, RED, BLUE, RED, BLUE]).to_console_rows(&sextant);
+    assert_eq!(cells(&rows), vec![vec![(String::from("▐"), String::from("48;2;255;0;0;38;2;0;0;255"))]]);
+
+    let rows = image(2, &[RED, BLUE, BLUE, BLUE, BLUE, BLUE]).to_console_rows(&sextant);
+    assert_eq!(cells(&rows), vec![vec![(String::from("\u{1FB3B}"), String::from("48;2;255;0;0;38;2;0;0;255"))]]);
+
+    //Cells of one color are drawn whole
+    let rows = image(2, &[GREEN; 4]).to_console_rows(&quadrant);
+    assert_eq!(cells(&rows), vec![vec![(String::from("█"), String::from("48;2;0;255;0;38;2;0;255;0"))]]);
+
+    //Blocks at the edge of the image are only partly filled, and transparent pixels show the background
+    let rows = image(3, &[RED, GREEN, CLEAR, BLUE, RED, GREEN]).to_console_rows(&quadrant.clone_with_background(Some(0xFFFFFFFF)));
+    assert_eq!(cells(&rows)[0][1], (String::from("▖"), String::from("48;2;255;255;255;38;2;0;255;0")));
+}
+
+#[test]
+fn draw_braille() {
+    colored::control::set_override(true);
+
+    //Pixels at least as bright as the average are dots in the foreground
+    let braille = settings(true, ConsoleRenderMode::Braille, None).clone_with_cell_split(ConsoleCellSplit::Threshold);
+    let rows = image(2, &[WHITE, BLACK, WHITE, BLACK, WHITE, BLACK, WHITE, WHITE]).to_console_rows(&braille);
+    assert_eq!(cells(&rows), vec![vec![(String::from("⣇"), String::from("48;2;0;0;0;38;2;255;255;255"))]]);
+
+    //The best fit is the same split, with the lower right pixel in the foreground
+    let rows = image(2, &[WHITE, BLACK, WHITE, BLACK, WHITE, BLACK, WHITE, WHITE]).to_console_rows(&braille.clone_with_cell_split(ConsoleCellSplit::BestFit));
+    assert_eq!(cells(&rows), vec![vec![(String::from("⣇"), String::from("48;2;0;0;0;38;2;255;255;255"))]]);
+
+    //In the console's colors, each side of the split is drawn in the closest color to its average. Red is brighter than blue.
+    let rows = image(2, &[RED, BLUE, RED, BLUE, RED, BLUE, RED, BLUE]).to_console_rows(&braille.clone_with_use_truecolor(false));
+    assert_eq!(cells(&rows), vec![vec![(String::from("⡇"), String::from("104;91"))]]);
+}
+
+#[test]
+fn build_symbols() {
+    //Masks are 8x16, from the top left, a row at a time
+    assert_eq!(ConsoleSymbol::new("▀", |_, y| y < 0.5).mask, u64::MAX as u128);
+    assert_eq!(ConsoleSymbol::new("▐", |x, _| x >= 0.5).mask, (0..16).fold(0, |mask, row| mask | (0xF0 << (row * 8))));
+
+    //Light lines are two parts of the grid thick, and heavy lines four
+    let box_drawing = ConsoleSymbol::box_drawing();
+    assert_eq!(box_drawing[0].glyph, "─");
+    assert_eq!(box_drawing[0].mask, 0xFFFF << 56);
+    assert_eq!(box_drawing[1].mask, (0..16).fold(0, |mask, row| mask | (0x18 << (row * 8))));
+    assert_eq!(box_drawing[3].mask, (0..16).fold(0, |mask, row| mask | (0x3C << (row * 8))));
+
+    //Each wedge covers half of the cell
+    assert!(ConsoleSymbol::wedges().iter().all(|wedge| wedge.mask.count_ones() == 64));
+}
+
+#[test]
+fn draw_symbols() {
+    colored::control::set_override(true);
+
+    let symbols = settings(true, ConsoleRenderMode::Symbols, None);
+
+    //The first of the closest symbols is drawn, with the colors it covers and leaves uncovered
+    let rows = image(2, &[RED, BLUE, RED, BLUE, RED, BLUE, RED, BLUE]).to_console_rows(&symbols);
+    assert_eq!(cells(&rows), vec![vec![(String::from("▌"), String::from("48;2;0;0;255;38;2;255;0;0"))]]);
+
+    let rows = image(2, &[WHITE, WHITE, WHITE, WHITE, WHITE, BLACK, WHITE, BLACK]).to_console_rows(&symbols);
+    assert_eq!(cells(&rows), vec![vec![(String::from("▗"), String::from("48;2;255;255;255;38;2;0;0;0"))]]);
+
+    //Cells of one color are spaces over that color
+    let rows = image(2, &[GREEN; 8]).to_console_rows(&symbols);
+    assert_eq!(cells(&rows), vec![vec![(String::from(" "), String::from("48;2;0;255;0"))]]);
+
+    //The symbols to choose from can be set
+    let rows = image(2, &[RED, BLUE, RED, BLUE, RED, BLUE, RED, BLUE]).to_console_rows(&symbols.clone_with_symbols(vec![ConsoleSymbol::new("X", |x, _| x >= 0.5)]));
+    assert_eq!(cells(&rows), vec![vec![(String::from("X"), String::from("48;2;255;0;0;38;2;0;0;255"))]]);
+
+    //Transparent pixels show the background, under the symbol which covers the rest
+    let rows = image(2, &[CLEAR, RED, CLEAR, RED, CLEAR, RED, CLEAR, RED]).to_console_rows(&symbols.clone_with_background(Some(0xFFFFFFFF)));
+    assert_eq!(cells(&rows), vec![vec![(String::from("▐"), String::from("48;2;255;255;255;38;2;255;0;0"))]]);
+}
+
+#[test]
+fn draw_symbols_in_console_colors() {
+    colored::control::set_override(true);
+
+    //The colors of each symbol are the closest console colors, and its error is measured with them
+    let dark_red = (0x90, 0x10, 0x10, 0xFF);
+    let rows = image(2, &[dark_red, dark_red, dark_red, dark_red, BLUE, BLUE, BLUE, BLUE])
+        .to_console_rows(&settings(false, ConsoleRenderMode::Symbols, None));
+
+    assert_eq!(cells(&rows), vec![vec![(String::from("▀"), String::from("104;31"))]]);
+}
+
+#[test]
+fn fit_in_console() {
+    let glyph = settings(true, ConsoleRenderMode::Glyph, None);
+    let half_block = settings(true, ConsoleRenderMode::HalfBlock, None);
+
+    //Glyphs are two characters wide, and half blocks hold two pixels in each character
+    assert_eq!(glyph.pixels_fitting(80, 24), (40, 24));
+    assert_eq!(half_block.pixels_fitting(80, 24), (80, 48));
+    assert_eq!(half_block.clone_with_pixel_width(2).pixels_fitting(80, 24), (40, 48));
+
+    //Blocks hold two columns of pixels in each character, and two, three or four rows
+    assert_eq!(half_block.clone_with_render_mode(ConsoleRenderMode::Quadrant).pixels_fitting(80, 24), (160, 48));
+    assert_eq!(half_block.clone_with_render_mode(ConsoleRenderMode::Sextant).pixels_fitting(80, 24), (160, 72));
+    assert_eq!(half_block.clone_with_render_mode(ConsoleRenderMode::Braille).pixels_fitting(80, 24), (160, 96));
+    assert_eq!(half_block.clone_with_render_mode(ConsoleRenderMode::Symbols).pixels_fitting(80, 24), (160, 96));
+}
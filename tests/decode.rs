@@ -0,0 +1,406 @@
+use std::fs;
+use std::io::Cursor;
+use bitmap_handler::bmp::*;
+
+mod common;
+use common::*;
+
+/// 
+/// The first 40 bytes of an info header of the given size, with no color table entries
+/// 
+fn info_header(size: u32, width: i32, height: i32, bits_per_pixel: u16, compression: u32) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+
+    bytes.extend(size.to_le_bytes());
+    bytes.extend(width.to_le_bytes());
+    bytes.extend(height.to_le_bytes());
+    bytes.extend(1_u16.to_le_bytes());
+    bytes.extend(bits_per_pixel.to_le_bytes());
+    bytes.extend(compression.to_le_bytes());
+    bytes.extend([0; 20]);
+
+    bytes
+}
+
+/// 
+/// A bitmap file with the given info header, and anything following it, before the given pixel data
+/// 
+fn bitmap_file(dib: &[u8], data: &[u8]) -> Vec<u8> {
+    let data_offset = 14 + dib.len() as u32;
+
+    let mut bytes: Vec<u8> = Vec::new();
+    bytes.extend(b"BM");
+    bytes.extend((data_offset + data.len() as u32).to_le_bytes());
+    bytes.extend(0_u32.to_le_bytes());
+    bytes.extend(data_offset.to_le_bytes());
+    bytes.extend(dib);
+    bytes.extend(data);
+
+    bytes
+}
+
+///
+/// Every file in the hostile corpus should decode to either
+/// a bitmap or an error, without panicking
+/// 
+#[test]
+fn hostile_corpus_does_not_panic() {
+    let mut count = 0;
+
+    for entry in fs::read_dir("input_hostile").unwrap() {
+        let path = entry.unwrap().path();
+        let bytes = fs::read(&path).unwrap();
+
+        if let Ok(bitmap) = BitMapRaw::from_bytes(&bytes) {
+            let width = bitmap.info_header.width.unsigned_abs() as usize;
+            let height = bitmap.info_header.height.unsigned_abs() as usize;
+            assert_eq!(bitmap.pixel_data.len(), width * height, "{}", path.display());
+        }
+
+        count += 1;
+    }
+
+    assert!(count > 0);
+}
+
+#[test]
+fn hostile_corpus_errors() {
+    fn decode(name: &str) -> Result<BitMapRaw, BmpError> {
+        BitMapRaw::from_bytes(&fs::read(format!("input_hostile/{name}")).unwrap())
+    }
+
+    assert!(matches!(decode("empty.bmp"), Err(BmpError::Truncated { needed: 2, available: 0 })));
+    assert!(matches!(decode("bad_signature.bmp"), Err(BmpError::BadSignature(_))));
+    assert!(matches!(decode("info_header_size_zero.bmp"), Err(BmpError::UnsupportedHeaderSize(0))));
+    assert!(matches!(decode("bpp_seven.bmp"), Err(BmpError::UnsupportedBitDepth(7))));
+    assert!(matches!(decode("compression_jpeg.bmp"), Err(BmpError::UnsupportedCompression(4))));
+    assert!(matches!(decode("pixels_truncated.bmp"), Err(BmpError::Truncated { .. })));
+    assert!(matches!(decode("palette_index_out_of_range.bmp"), Err(BmpError::PaletteIndexOutOfRange { index: 200, palette_size: 2 })));
+    assert!(matches!(decode("rle8_huge_dimensions.bmp"), Err(BmpError::LimitExceeded { limit: DecodeLimit::Width, .. })));
+    assert!(matches!(decode("rle4_huge_pixel_count.bmp"), Err(BmpError::LimitExceeded { limit: DecodeLimit::Pixels, .. })));
+}
+
+#[test]
+fn decode_limits() {
+    let bytes = fs::read("input/img10.bmp").unwrap();
+
+    let decode = |limits: DecodeLimits| {
+        let mut settings = BitMapRawReadSettings::default();
+        settings.with_limits(limits);
+        BitMapRaw::from_bytes_with_settings(&bytes, &settings)
+    };
+
+    assert!(decode(DecodeLimits::new(400, 400, 160_000, 1 << 20)).is_ok());
+    assert!(matches!(decode(DecodeLimits::new(399, 400, 160_000, 1 << 20)), Err(BmpError::LimitExceeded { limit: DecodeLimit::Width, value: 400, max: 399 })));
+    assert!(matches!(decode(DecodeLimits::new(400, 399, 160_000, 1 << 20)), Err(BmpError::LimitExceeded { limit: DecodeLimit::Height, .. })));
+    assert!(matches!(decode(DecodeLimits::new(400, 400, 159_999, 1 << 20)), Err(BmpError::LimitExceeded { limit: DecodeLimit::Pixels, .. })));
+    assert!(matches!(decode(DecodeLimits::new(400, 400, 160_000, 1 << 16)), Err(BmpError::LimitExceeded { limit: DecodeLimit::AllocBytes, .. })));
+}
+
+///
+/// Every sample bitmap should decode, with one pixel
+/// for each position in the image
+/// 
+#[test]
+fn sample_bitmaps_decode() {
+    for entry in fs::read_dir("input").unwrap() {
+        let path = entry.unwrap().path();
+        let bitmap = BitMapRaw::read_from_file(path.to_str().unwrap()).unwrap();
+
+        let width = bitmap.info_header.width.unsigned_abs() as usize;
+        let height = bitmap.info_header.height.unsigned_abs() as usize;
+        assert_eq!(bitmap.pixel_data.len(), width * height, "{}", path.display());
+    }
+}
+
+///
+/// Bitmaps should be read from any Read + Seek source, starting at its current position, and
+/// only the rest of the source should count towards the allocation limit
+/// 
+#[test]
+fn read_from_reader() {
+    let bytes = fs::read("input/img26.bmp").unwrap();
+    let expected = pixels(&BitMapRaw::from_bytes(&bytes).unwrap());
+
+    //The bitmap follows 5 bytes of something else
+    let mut cursor = Cursor::new([vec![0xAA; 5], bytes.clone()].concat());
+    cursor.set_position(5);
+    assert_eq!(pixels(&BitMapRaw::from_reader(&mut cursor).unwrap()), expected);
+
+    let mut settings = BitMapRawReadSettings::default();
+    settings.with_limits(DecodeLimits::new(100, 100, 100, bytes.len() as u64));
+
+    cursor.set_position(5);
+    assert_eq!(pixels(&BitMapRaw::from_reader_with_settings(&mut cursor, &settings).unwrap()), expected);
+
+    cursor.set_position(4);
+    assert!(matches!(BitMapRaw::from_reader_with_settings(&mut cursor, &settings), Err(BmpError::LimitExceeded { limit: DecodeLimit::AllocBytes, .. })));
+
+    //Streams which cannot seek are read until they pass the limit
+    assert_eq!(BitMapRaw::read_stream_to_buffer(&mut &bytes[..], &settings).unwrap(), bytes);
+
+    let longer = [bytes.clone(), vec![0]].concat();
+    assert!(matches!(BitMapRaw::read_stream_to_buffer(&mut &longer[..], &settings), Err(BmpError::LimitExceeded { limit: DecodeLimit::AllocBytes, .. })));
+}
+
+///
+/// Damaged bitmaps which fail in strict mode should decode
+/// in lenient mode, with the damage filled in and reported
+/// 
+#[test]
+fn lenient_decode() {
+    let fill_color = RGBColor {
+        red: 0xFF,
+        green: 0,
+        blue: 0xFF,
+        alpha: 0xFF
+    };
+
+    let mut settings = BitMapRawReadSettings::default();
+    settings.with_mode(DecodeMode::Lenient);
+    settings.with_fill_color(fill_color);
+
+    let is_fill = |p: &RGBColor| (p.red, p.green, p.blue, p.alpha) == (0xFF, 0, 0xFF, 0xFF);
+
+    let decode = |name: &str| BitMapRaw::from_bytes_with_settings(&fs::read(format!("input_hostile/{name}")).unwrap(), &settings).unwrap();
+
+    let truncated = decode("pixels_truncated.bmp");
+    let width = truncated.info_header.width.unsigned_abs() as usize;
+    let height = truncated.info_header.height.unsigned_abs() as usize;
+    assert_eq!(truncated.pixel_data.len(), width * height);
+    assert!(truncated.warnings.iter().any(|w| matches!(w, DecodeWarning::TruncatedPixelData { .. })));
+    assert!(is_fill(&truncated.get_pixel(width * height - 1).unwrap()));
+
+    let out_of_range = decode("palette_index_out_of_range.bmp");
+    assert!(out_of_range.warnings.iter().any(|w| matches!(w, DecodeWarning::PaletteTooShort { palette_size: 2, .. })));
+    assert!(out_of_range.pixel_data.to_rgba(&out_of_range.color_table).unwrap().iter().any(is_fill));
+
+    //Every file in the hostile corpus that decodes strictly should decode the same way leniently
+    for entry in fs::read_dir("input_hostile").unwrap() {
+        let bytes = fs::read(entry.unwrap().path()).unwrap();
+
+        if let Ok(strict) = BitMapRaw::from_bytes(&bytes) {
+            let lenient = BitMapRaw::from_bytes_with_settings(&bytes, &settings).unwrap();
+            assert_eq!(strict.pixel_data.len(), lenient.pixel_data.len());
+        }
+    }
+}
+
+///
+/// Compressed bitmaps whose data ends before the last pixel should fail in strict mode,
+/// and in lenient mode have the missing pixels filled in and reported
+/// 
+#[test]
+fn truncated_compressed_data() {
+    let mut settings = BitMapRawReadSettings::default();
+    settings.with_mode(DecodeMode::Lenient);
+    settings.with_fill_color(RGBColor { red: 0xFF, green: 0, blue: 0xFF, alpha: 0xFF });
+
+    let fill = (0xFF, 0, 0xFF, 0xFF);
+
+    let missing_pixels = |bitmap: &BitMapRaw| bitmap.warnings.iter().find_map(|warning| match warning {
+        DecodeWarning::TruncatedPixelData { missing_pixels } => Some(*missing_pixels),
+        _ => None
+    });
+
+    //An RLE8 run of 2 reds ending the bottom line, and a run of 1 green, after which the data ends
+    let color_table: Vec<u8> = vec![0, 0, 0xFF, 0, 0, 0xFF, 0, 0];
+    let rle8 = bitmap_file(&[info_header(40, 3, 2, 8, BitMapInfoHeader::BI_RLE8), color_table].concat(), &[2, 0, 0, 0, 1, 1]);
+
+    assert!(matches!(BitMapRaw::from_bytes(&rle8), Err(BmpError::Truncated { .. })));
+
+    let bitmap = BitMapRaw::from_bytes_with_settings(&rle8, &settings).unwrap();
+    assert_eq!(missing_pixels(&bitmap), Some(2));
+    assert_eq!(pixels(&bitmap), vec![GREEN, fill, fill, RED, RED, CLEAR]);
+
+    //Huffman 1D, ending before the second line
+    let bytes = fs::read("input/img28.bmp").unwrap();
+    let huffman = &bytes[..bytes.len() - 3];
+
+    assert!(matches!(BitMapRaw::from_bytes(huffman), Err(BmpError::Truncated { .. })));
+
+    let bitmap = BitMapRaw::from_bytes_with_settings(huffman, &settings).unwrap();
+    assert_eq!(missing_pixels(&bitmap), Some(8));
+    assert_eq!(pixels(&bitmap), [vec![fill; 8], vec![WHITE, WHITE, WHITE, BLACK, BLACK, WHITE, WHITE, WHITE]].concat());
+}
+
+///
+/// Bitmaps with a bit depth of 8 or less should keep their color table indices,
+/// and expand to the same colors they would have been decoded to
+/// 
+#[test]
+fn indexed_bitmaps_stay_indexed() {
+    for name in ["img14.bmp", "img15.bmp", "img18.bmp", "img20.bmp"] {
+        let mut bitmap = BitMapRaw::read_from_file(&format!("input/{name}")).unwrap();
+        assert!(bitmap.pixel_data.is_indexed(), "{name}");
+
+        let colors: Vec<(u8, u8, u8, u8)> = (0..bitmap.pixel_data.len())
+            .map(|index| bitmap.get_pixel(index).unwrap())
+            .map(|p| (p.red, p.green, p.blue, p.alpha))
+            .collect();
+
+        bitmap.expand_palette().unwrap();
+        assert!(!bitmap.pixel_data.is_indexed(), "{name}");
+
+        let expanded: Vec<(u8, u8, u8, u8)> = bitmap.pixel_data.to_rgba(&bitmap.color_table).unwrap()
+            .iter()
+            .map(|p| (p.red, p.green, p.blue, p.alpha))
+            .collect();
+
+        assert_eq!(colors, expanded, "{name}");
+    }
+
+    let bitmap = BitMapRaw::read_from_file("input/img0.bmp").unwrap();
+    assert!(!bitmap.pixel_data.is_indexed());
+}
+
+///
+/// 16 bpp bitmaps should default to 5 bits per channel, and BI_BITFIELDS bitmaps should
+/// take each channel from its mask, scaled to 8 bits
+/// 
+#[test]
+fn decode_bitfields() {
+    let rgb555: Vec<u8> = [0x001F_u16, 0x4210, 0x7C00, 0x03E0].iter().flat_map(|value| value.to_le_bytes()).collect();
+    let bitmap = BitMapRaw::from_bytes(&bitmap_file(&info_header(40, 2, 2, 16, BitMapInfoHeader::BI_RGB), &rgb555)).unwrap();
+    assert_eq!(pixels(&bitmap), vec![(0xFF, 0, 0, 0xFF), (0, 0xFF, 0, 0xFF), (0, 0, 0xFF, 0xFF), (132, 132, 132, 0xFF)]);
+
+    //The masks follow a BITMAPINFOHEADER
+    let masks = |red: u32, green: u32, blue: u32| [red, green, blue].iter().flat_map(|mask| mask.to_le_bytes()).collect::<Vec<u8>>();
+
+    let rgb565: Vec<u8> = [0x001F_u16, 0x8410, 0xF800, 0x07E0].iter().flat_map(|value| value.to_le_bytes()).collect();
+    let dib = [info_header(40, 2, 2, 16, BitMapInfoHeader::BI_BITFIELDS), masks(0xF800, 0x07E0, 0x001F)].concat();
+    let bitmap = BitMapRaw::from_bytes(&bitmap_file(&dib, &rgb565)).unwrap();
+    assert_eq!(pixels(&bitmap), vec![(0xFF, 0, 0, 0xFF), (0, 0xFF, 0, 0xFF), (0, 0, 0xFF, 0xFF), (132, 130, 132, 0xFF)]);
+
+    //Red in the lowest byte, and the highest byte unused, so every pixel is opaque
+    let xbgr: Vec<u8> = [0x00FF0000_u32, 0x11336699, 0x000000FF, 0xFF00FF00].iter().flat_map(|value| value.to_le_bytes()).collect();
+    let dib = [info_header(40, 2, -2, 32, BitMapInfoHeader::BI_BITFIELDS), masks(0x000000FF, 0x0000FF00, 0x00FF0000)].concat();
+    let bitmap = BitMapRaw::from_bytes(&bitmap_file(&dib, &xbgr)).unwrap();
+    assert_eq!(pixels(&bitmap), vec![(0, 0, 0xFF, 0xFF), (0x99, 0x66, 0x33, 0xFF), (0xFF, 0, 0, 0xFF), (0, 0xFF, 0, 0xFF)]);
+}
+
+///
+/// RLE8 and RLE4 bitmaps should decode each run, with the pixels skipped by a delta,
+/// or left after the end of the bitmap, transparent. Pixels the data ends before are missing instead.
+/// 
+#[test]
+fn decode_rle() {
+    //Red, green, blue and white
+    let color_table: Vec<u8> = vec![0, 0, 0xFF, 0, 0, 0xFF, 0, 0, 0xFF, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0];
+
+    //
+    // A run of 2 reds, then 3 absolute pixels, ending the bottom line. A delta moves 1 right and 1 up,
+    // past the middle line, to a run of 1 white, and the bitmap ends before the rest of the top line.
+    //
+    let rle8: Vec<u8> = vec![2, 0, 0, 3, 1, 2, 3, 0, 0, 0, 0, 2, 1, 1, 1, 3, 0, 1];
+    let dib = [info_header(40, 5, 3, 8, BitMapInfoHeader::BI_RLE8), color_table.clone()].concat();
+    let bitmap = BitMapRaw::from_bytes(&bitmap_file(&dib, &rle8)).unwrap();
+
+    assert!(!bitmap.pixel_data.is_indexed());
+    assert_eq!(pixels(&bitmap), vec![
+        CLEAR, WHITE, CLEAR, CLEAR, CLEAR,
+        CLEAR, CLEAR, CLEAR, CLEAR, CLEAR,
+        RED, RED, GREEN, BLUE, WHITE
+    ]);
+
+    //The same, with 2 indices to a byte, alternating in runs and padded to 2 bytes in absolute mode
+    let rle4: Vec<u8> = vec![2, 0x00, 0, 3, 0x12, 0x30, 0, 0, 0, 2, 1, 1, 1, 0x33, 0, 1];
+    let dib = [info_header(40, 5, 3, 4, BitMapInfoHeader::BI_RLE4), color_table.clone()].concat();
+    let bitmap = BitMapRaw::from_bytes(&bitmap_file(&dib, &rle4)).unwrap();
+
+    assert_eq!(pixels(&bitmap), vec![
+        CLEAR, WHITE, CLEAR, CLEAR, CLEAR,
+        CLEAR, CLEAR, CLEAR, CLEAR, CLEAR,
+        RED, RED, GREEN, BLUE, WHITE
+    ]);
+
+    //A run of 2 alternating indices, and a bitmap with no pixels skipped keeps its indices
+    let rle4: Vec<u8> = vec![4, 0x12, 0, 0, 0, 1];
+    let dib = [info_header(40, 4, 1, 4, BitMapInfoHeader::BI_RLE4), color_table.clone()].concat();
+    let bitmap = BitMapRaw::from_bytes(&bitmap_file(&dib, &rle4)).unwrap();
+
+    assert!(bitmap.pixel_data.is_indexed());
+    assert_eq!(pixels(&bitmap), vec![GREEN, BLUE, GREEN, BLUE]);
+
+    //Pixels after the data ends without an end of bitmap are missing, rather than skipped, and are given the fill color
+    let mut settings = BitMapRawReadSettings::default();
+    settings.with_mode(DecodeMode::Lenient);
+    settings.with_fill_color(RGBColor { red: 0xFF, green: 0xFF, blue: 0xFF, alpha: 0xFF });
+
+    let rle8: Vec<u8> = vec![2, 0, 0, 0, 0, 2, 1, 0, 1, 1];
+    let dib = [info_header(40, 3, 2, 8, BitMapInfoHeader::BI_RLE8), color_table].concat();
+    let bitmap = BitMapRaw::from_bytes_with_settings(&bitmap_file(&dib, &rle8), &settings).unwrap();
+
+    assert!(matches!(bitmap.warnings[..], [DecodeWarning::TruncatedPixelData { missing_pixels: 1 }]));
+    assert_eq!(pixels(&bitmap), vec![CLEAR, GREEN, WHITE, RED, RED, CLEAR]);
+}
+
+///
+/// A BITMAPV5HEADER should be read field by field, and its alpha mask applied to the pixels
+/// 
+#[test]
+fn decode_v5_header() {
+    let to_bytes = |values: &[u32]| values.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>();
+
+    let dib = [
+        info_header(BitMapInfoHeader::SIZE_V5, 2, -1, 32, BitMapInfoHeader::BI_BITFIELDS),
+        //Channel masks
+        to_bytes(&[0x00FF0000, 0x0000FF00, 0x000000FF, 0xFF000000]),
+        //Color space type, endpoints and gamma
+        to_bytes(&[BitMapV4Header::PROFILE_EMBEDDED, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0x10000, 0x20000, 0x30000]),
+        //Intent, profile offset and size, and reserved
+        to_bytes(&[BitMapV5Header::LCS_GM_IMAGES, BitMapInfoHeader::SIZE_V5, 4, 0]),
+        //The profile, between the header and the pixel data
+        b"ICC!".to_vec()
+    ].concat();
+
+    let bitmap = BitMapRaw::from_bytes(&bitmap_file(&dib, &to_bytes(&[0x80FF0000, 0x0000FF00]))).unwrap();
+    assert_eq!(bitmap.info_header.size, BitMapInfoHeader::SIZE_V5);
+
+    let v4_header = bitmap.v4_header.as_ref().unwrap();
+    assert_eq!((v4_header.red_mask, v4_header.green_mask, v4_header.blue_mask, v4_header.alpha_mask), (0x00FF0000, 0x0000FF00, 0x000000FF, 0xFF000000));
+    assert_eq!(v4_header.cs_type, BitMapV4Header::PROFILE_EMBEDDED);
+
+    let endpoints = &v4_header.endpoints;
+    assert_eq!([endpoints.red.x, endpoints.red.y, endpoints.red.z], [1, 2, 3]);
+    assert_eq!([endpoints.green.x, endpoints.green.y, endpoints.green.z], [4, 5, 6]);
+    assert_eq!([endpoints.blue.x, endpoints.blue.y, endpoints.blue.z], [7, 8, 9]);
+    assert_eq!((v4_header.gamma_red, v4_header.gamma_green, v4_header.gamma_blue), (0x10000, 0x20000, 0x30000));
+
+    let v5_header = bitmap.v5_header.as_ref().unwrap();
+    assert_eq!(v5_header.intent, BitMapV5Header::LCS_GM_IMAGES);
+    assert_eq!((v5_header.profile_data, v5_header.profile_size), (BitMapInfoHeader::SIZE_V5, 4));
+
+    //The pixel data begins after the profile
+    assert_eq!(pixels(&bitmap), vec![(0xFF, 0, 0, 0x80), (0, 0xFF, 0, 0)]);
+}
+
+///
+/// OS/2 bitmaps should decode with 3 byte color table entries in the 1.x header,
+/// and with the 2.x header, uncompressed or with Huffman 1D or RLE24 compression
+/// 
+#[test]
+fn decode_os2_bitmaps() {
+    //OS/2 1.x, with a color table of 3 entries
+    let core = BitMapRaw::read_from_file("input/img26.bmp").unwrap();
+    assert_eq!(core.info_header.size, BitMapInfoHeader::SIZE_CORE);
+    assert_eq!(core.color_table.colors.len(), 3);
+    assert_eq!(pixels(&core), vec![RED, RED, GREEN, GREEN, BLUE, GREEN, RED, BLUE]);
+
+    //OS/2 2.x, uncompressed
+    let os2 = BitMapRaw::read_from_file("input/img27.bmp").unwrap();
+    assert_eq!(os2.info_header.size, BitMapInfoHeader::SIZE_OS2);
+    assert!(os2.os2_header.is_some());
+    assert_eq!(pixels(&os2), vec![RED, GREEN, BLUE, WHITE, BLUE, GREEN]);
+
+    //Huffman 1D, with runs of white and black, and an end-of-line code before each line
+    let huffman = BitMapRaw::read_from_file("input/img28.bmp").unwrap();
+    assert_eq!(huffman.info_header.compression, BitMapInfoHeader::BI_HUFFMAN1D);
+    assert!(huffman.pixel_data.is_indexed());
+    assert_eq!(pixels(&huffman), [vec![BLACK; 8], vec![WHITE, WHITE, WHITE, BLACK, BLACK, WHITE, WHITE, WHITE]].concat());
+
+    //RLE24, with encoded and absolute runs
+    let rle24 = BitMapRaw::read_from_file("input/img29.bmp").unwrap();
+    assert_eq!(rle24.info_header.compression, BitMapInfoHeader::BI_RLE24);
+    assert_eq!(pixels(&rle24), vec![BLUE, WHITE, RED, BLUE, RED, RED, GREEN, GREEN]);
+}
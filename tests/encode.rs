@@ -0,0 +1,276 @@
+use std::fs;
+use bitmap_handler::bmp::*;
+
+fn colors(bitmap: &BitMapRaw) -> Vec<(u8, u8, u8, u8)> {
+    bitmap.pixel_data.to_rgba(&bitmap.color_table).unwrap()
+        .iter()
+        .map(|p| (p.red, p.green, p.blue, p.alpha))
+        .collect()
+}
+
+/// 
+/// Get the colors of the bitmap, from the top row to the bottom row
+/// 
+fn colors_top_down(bitmap: &BitMapRaw) -> Vec<(u8, u8, u8, u8)> {
+    let width = bitmap.info_header.width.unsigned_abs() as usize;
+    let mut rows: Vec<Vec<(u8, u8, u8, u8)>> = colors(bitmap).chunks(width).map(|row| row.to_vec()).collect();
+
+    if bitmap.info_header.height > 0 {
+        rows.reverse();
+    }
+
+    rows.concat()
+}
+
+fn write(bitmap: &BitMapRaw, settings: &BitMapRawWriteSettings) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    bitmap.write_to_with_settings(&mut bytes, settings).unwrap();
+    bytes
+}
+
+/// 
+/// Every sample bitmap written at 32 bpp should read back
+/// with identical pixels, in either row order
+/// 
+#[test]
+fn sample_bitmaps_round_trip() {
+    for entry in fs::read_dir("input").unwrap() {
+        let path = entry.unwrap().path();
+        let bitmap = BitMapRaw::read_from_file(path.to_str().unwrap()).unwrap();
+
+        for row_order in [BitMapRowOrder::BottomUp, BitMapRowOrder::TopDown] {
+            let bytes = write(&bitmap, &BitMapRawWriteSettings::new(32, row_order, BitMapInfoHeader::BI_RGB, None, BitMapQuantization::MedianCut, RGBColor::get_euclidean_distance_rgb));
+            let written = BitMapRaw::from_bytes(&bytes).unwrap();
+
+            assert_eq!(written.header.file_size as usize, bytes.len());
+            assert_eq!(written.info_header.height < 0, row_order == BitMapRowOrder::TopDown);
+            assert_eq!(colors_top_down(&bitmap), colors_top_down(&written), "{}", path.display());
+            assert!(written.warnings.is_empty());
+        }
+    }
+}
+
+/// 
+/// 24 bpp bitmaps have no alpha channel, and are 4-byte aligned
+/// 
+#[test]
+fn write_24_bpp() {
+    let bitmap = BitMapRaw::read_from_file("input/img0.bmp").unwrap();
+    let bytes = write(&bitmap, &BitMapRawWriteSettings::default());
+    let written = BitMapRaw::from_bytes(&bytes).unwrap();
+
+    let width = written.info_header.width as usize;
+    let height = written.info_header.height as usize;
+
+    assert_eq!(written.info_header.bits_per_pixel, 24);
+    assert_eq!(written.header.data_offset, 54);
+    assert_eq!(written.info_header.image_size as usize, (width * 3).next_multiple_of(4) * height);
+    assert_eq!(colors(&bitmap), colors(&written));
+}
+
+#[test]
+fn write_errors() {
+    let mut bitmap = BitMapRaw::read_from_file("input/img0.bmp").unwrap();
+    let mut bytes: Vec<u8> = Vec::new();
+
+    assert!(matches!(bitmap.write_to_with_settings(&mut bytes, &BitMapRawWriteSettings::new(7, BitMapRowOrder::BottomUp, BitMapInfoHeader::BI_RGB, None, BitMapQuantization::MedianCut, RGBColor::get_euclidean_distance_rgb)), Err(BmpError::UnsupportedBitDepth(7))));
+
+    bitmap.pixel_data = BitMapPixelData::Color(Vec::new());
+    assert!(matches!(bitmap.write_to(&mut bytes), Err(BmpError::PixelCountMismatch { actual: 0, .. })));
+}
+
+///
+/// Indexed bitmaps written at their own bit depth should keep
+/// their color table and indices
+/// 
+#[test]
+fn indexed_round_trip() {
+    for (name, bits_per_pixel) in [("img15.bmp", 1), ("img18.bmp", 4), ("img14.bmp", 8)] {
+        let bitmap = BitMapRaw::read_from_file(&format!("input/{name}")).unwrap();
+
+        let mut settings = BitMapRawWriteSettings::default();
+        settings.with_bits_per_pixel(bits_per_pixel);
+
+        let bytes = write(&bitmap, &settings);
+        let written = BitMapRaw::from_bytes(&bytes).unwrap();
+
+        assert_eq!(written.info_header.bits_per_pixel, bits_per_pixel);
+        assert_eq!(written.header.file_size as usize, bytes.len());
+        assert_eq!(colors(&bitmap), colors(&written), "{name}");
+
+        match (&bitmap.pixel_data, &written.pixel_data) {
+            (BitMapPixelData::Indexed(a), BitMapPixelData::Indexed(b)) => assert_eq!(a, b, "{name}"),
+            _ => panic!("{name} should be indexed")
+        }
+    }
+}
+
+///
+/// Images with few enough colors should be written with their exact colors,
+/// and those with too many should be quantized to fit in the color table
+/// 
+#[test]
+fn quantized_export() {
+    //2 colors fit exactly in a 1 bpp color table
+    let mut bitmap = BitMapRaw::read_from_file("input/img15.bmp").unwrap();
+    bitmap.expand_palette().unwrap();
+
+    let mut settings = BitMapRawWriteSettings::default();
+    settings.with_bits_per_pixel(1);
+
+    let written = BitMapRaw::from_bytes(&write(&bitmap, &settings)).unwrap();
+    assert_eq!(colors(&bitmap), colors(&written));
+
+    //96 colors fit in an 8 bpp color table, but must be quantized to fit in a 1 or 4 bpp color table
+    let bitmap = BitMapRaw::read_from_file("input/img10.bmp").unwrap();
+
+    for quantization in [BitMapQuantization::MedianCut, BitMapQuantization::Popularity] {
+        for bits_per_pixel in [1, 4, 8] {
+            let mut settings = BitMapRawWriteSettings::default();
+            settings.with_bits_per_pixel(bits_per_pixel);
+            settings.with_quantization(quantization);
+
+            let written = BitMapRaw::from_bytes(&write(&bitmap, &settings)).unwrap();
+
+            assert_eq!(written.info_header.bits_per_pixel, bits_per_pixel);
+            assert!(written.color_table.colors.len() <= 1 << bits_per_pixel);
+            assert_eq!(written.pixel_data.len(), bitmap.pixel_data.len());
+            assert!(written.pixel_data.is_indexed());
+
+            if bits_per_pixel == 8 {
+                assert_eq!(colors(&bitmap), colors(&written));
+            }
+            else {
+                assert_eq!(written.color_table.colors.len(), 1 << bits_per_pixel);
+            }
+        }
+    }
+}
+
+///
+/// Every sample bitmap written with RLE8 or RLE4 should decode to the
+/// same pixels as when written uncompressed at the same bit depth
+/// 
+#[test]
+fn rle_round_trip() {
+    for entry in fs::read_dir("input").unwrap() {
+        let path = entry.unwrap().path();
+        let bitmap = BitMapRaw::read_from_file(path.to_str().unwrap()).unwrap();
+
+        //Quantizing the largest samples takes too long without optimizations
+        if bitmap.pixel_data.len() > 400 * 400 {
+            continue;
+        }
+
+        for (compression, bits_per_pixel) in [(BitMapInfoHeader::BI_RLE8, 8), (BitMapInfoHeader::BI_RLE4, 4)] {
+            let mut settings = BitMapRawWriteSettings::default();
+            settings.with_bits_per_pixel(bits_per_pixel);
+
+            //Quantize once, then compress the already indexed bitmap
+            let uncompressed = BitMapRaw::from_bytes(&write(&bitmap, &settings)).unwrap();
+
+            settings.with_compression(compression);
+
+            let mut bytes: Vec<u8> = Vec::new();
+            let report = uncompressed.write_to_with_settings(&mut bytes, &settings).unwrap();
+            let compressed = BitMapRaw::from_bytes(&bytes).unwrap();
+
+            assert_eq!(compressed.info_header.compression, compression);
+            assert_eq!(report.file_size as usize, bytes.len());
+            assert_eq!(compressed.info_header.image_size, report.image_size);
+            assert!(compressed.pixel_data.is_indexed(), "{}", path.display());
+            assert_eq!(colors(&uncompressed), colors(&compressed), "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn rle_compression_ratio() {
+    //img10 has large areas of a single color
+    let bitmap = BitMapRaw::read_from_file("input/img10.bmp").unwrap();
+
+    let mut settings = BitMapRawWriteSettings::default();
+    settings.with_bits_per_pixel(8);
+    settings.with_compression(BitMapInfoHeader::BI_RLE8);
+
+    let mut bytes: Vec<u8> = Vec::new();
+    let report = bitmap.write_to_with_settings(&mut bytes, &settings).unwrap();
+
+    assert!(report.compression_ratio() > 3_f32);
+    assert_eq!(report.uncompressed_image_size, (bitmap.info_header.width as usize).next_multiple_of(4) * bitmap.info_header.height as usize);
+
+    //Compressed bitmaps cannot be top-down, and the compression must match the bit depth
+    settings.with_row_order(BitMapRowOrder::TopDown);
+    assert!(matches!(bitmap.write_to_with_settings(&mut bytes, &settings), Err(BmpError::UnsupportedCompression(1))));
+
+    settings.with_row_order(BitMapRowOrder::BottomUp);
+    settings.with_bits_per_pixel(4);
+    assert!(matches!(bitmap.write_to_with_settings(&mut bytes, &settings), Err(BmpError::UnsupportedCompression(1))));
+}
+
+///
+/// BI_BITFIELDS bitmaps should be written with a V5 header, and read back
+/// with each channel as stored by its mask
+/// 
+#[test]
+fn bitfields_round_trip() {
+    //img24 has partially transparent pixels
+    let bitmap = BitMapRaw::read_from_file("input/img24.bmp").unwrap();
+
+    for (bits_per_pixel, masks) in [(32, BitMapChannelMasks::ARGB8888), (16, BitMapChannelMasks::RGB565), (16, BitMapChannelMasks::ARGB1555)] {
+        let mut settings = BitMapRawWriteSettings::default();
+        settings.with_bits_per_pixel(bits_per_pixel);
+        settings.with_compression(BitMapInfoHeader::BI_BITFIELDS);
+        settings.with_channel_masks(Some(masks.clone()));
+
+        let bytes = write(&bitmap, &settings);
+        let written = BitMapRaw::from_bytes(&bytes).unwrap();
+
+        assert_eq!(written.info_header.size, BitMapInfoHeader::SIZE_V5);
+        assert_eq!(written.header.data_offset, 14 + 124);
+
+        let v4_header = written.v4_header.as_ref().unwrap();
+        assert_eq!((v4_header.red_mask, v4_header.green_mask, v4_header.blue_mask, v4_header.alpha_mask), (masks.red, masks.green, masks.blue, masks.alpha));
+        assert_eq!(v4_header.cs_type, BitMapV4Header::LCS_SRGB);
+
+        //Each channel loses any bits which do not fit in its mask
+        let expected: Vec<(u8, u8, u8, u8)> = bitmap.pixel_data.to_rgba(&bitmap.color_table).unwrap()
+            .iter()
+            .map(|p| RGBColor::from_bitfields(p.to_bitfields(&masks), &masks))
+            .map(|p| (p.red, p.green, p.blue, p.alpha))
+            .collect();
+
+        assert_eq!(expected, colors(&written));
+    }
+
+    //ARGB8888 keeps every channel, including alpha
+    let mut settings = BitMapRawWriteSettings::default();
+    settings.with_bits_per_pixel(32);
+    settings.with_compression(BitMapInfoHeader::BI_BITFIELDS);
+
+    let written = BitMapRaw::from_bytes(&write(&bitmap, &settings)).unwrap();
+    assert_eq!(colors(&bitmap), colors(&written));
+    assert!(colors(&written).iter().any(|(_, _, _, alpha)| *alpha != 0xFF));
+}
+
+#[test]
+fn invalid_channel_masks() {
+    let bitmap = BitMapRaw::read_from_file("input/img21.bmp").unwrap();
+    let mut bytes: Vec<u8> = Vec::new();
+
+    let mut settings = BitMapRawWriteSettings::default();
+    settings.with_bits_per_pixel(16);
+    settings.with_compression(BitMapInfoHeader::BI_BITFIELDS);
+
+    //Does not fit in 16 bits
+    settings.with_channel_masks(Some(BitMapChannelMasks::ARGB8888));
+    assert!(matches!(bitmap.write_to_with_settings(&mut bytes, &settings), Err(BmpError::InvalidChannelMask(0x00FF0000))));
+
+    //Not contiguous
+    settings.with_channel_masks(Some(BitMapChannelMasks { red: 0xF00F, green: 0x07E0, blue: 0x0010, alpha: 0 }));
+    assert!(matches!(bitmap.write_to_with_settings(&mut bytes, &settings), Err(BmpError::InvalidChannelMask(0xF00F))));
+
+    //Overlapping
+    settings.with_channel_masks(Some(BitMapChannelMasks { red: 0xF800, green: 0x0FE0, blue: 0x001F, alpha: 0 }));
+    assert!(matches!(bitmap.write_to_with_settings(&mut bytes, &settings), Err(BmpError::InvalidChannelMask(0x0FE0))));
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use bitmap_handler::bmp::*;
+
+mod common;
+use common::{pixels, CLEAR};
+
+/// 
+/// Compress color indices as GIF LZW data, clearing the code table whenever it fills. Returns the
+/// minimum code size, followed by the data split into sub-blocks.
+/// 
+fn lzw(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear_code = 1_u16 << min_code_size;
+
+    let mut codes: Vec<(u16, u32)> = Vec::new();
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = min_code_size as u32 + 1;
+    let mut next_code = clear_code + 2;
+    let mut prefix: Option<u16> = None;
+
+    codes.push((clear_code, code_size));
+
+    for index in indices {
+        let current = match prefix {
+            None => {
+                prefix = Some(*index as u16);
+                continue;
+            },
+            Some(current) => current
+        };
+
+        if let Some(code) = table.get(&(current, *index)) {
+            prefix = Some(*code);
+            continue;
+        }
+
+        codes.push((current, code_size));
+        table.insert((current, *index), next_code);
+        next_code += 1;
+
+        if next_code as u32 > (1 << code_size) {
+            code_size += 1;
+        }
+
+        if next_code == 4096 {
+            codes.push((clear_code, code_size));
+            table.clear();
+            code_size = min_code_size as u32 + 1;
+            next_code = clear_code + 2;
+        }
+
+        prefix = Some(*index as u16);
+    }
+
+    if let Some(current) = prefix {
+        codes.push((current, code_size));
+
+        //The decoder adds a code for the last one, which can widen the end code
+        if next_code + 1 > (1 << code_size) && code_size < 12 {
+            code_size += 1;
+        }
+    }
+
+    codes.push((clear_code + 1, code_size));
+
+    let mut data: Vec<u8> = Vec::new();
+    let mut bits: u32 = 0;
+    let mut bit_count: u32 = 0;
+
+    for (code, size) in codes {
+        bits |= (code as u32) << bit_count;
+        bit_count += size;
+
+        while bit_count >= 8 {
+            data.push(bits as u8);
+            bits >>= 8;
+            bit_count -= 8;
+        }
+    }
+
+    if bit_count > 0 {
+        data.push(bits as u8);
+    }
+
+    let mut blocks = vec![min_code_size];
+
+    for chunk in data.chunks(255) {
+        blocks.push(chunk.len() as u8);
+        blocks.extend_from_slice(chunk);
+    }
+
+    blocks.push(0);
+    blocks
+}
+
+/// 
+/// An image in a GIF file, and its graphic control extension
+/// 
+struct Frame {
+    left: u16,
+    top: u16,
+    width: u16,
+    height: u16,
+    indices: Vec<u8>,
+    color_table: Option<Vec<[u8; 3]>>,
+    interlaced: bool,
+    delay: u16,
+    disposal: u8,
+    transparent: Option<u8>
+}
+
+fn frame(left: u16, top: u16, width: u16, height: u16, indices: Vec<u8>) -> Frame {
+    Frame {
+        left,
+        top,
+        width,
+        height,
+        indices,
+        color_table: None,
+        interlaced: false,
+        delay: 0,
+        disposal: 0,
+        transparent: None
+    }
+}
+
+/// 
+/// Write a color table, padded to a power of 2, and get the size flags of its descriptor
+/// 
+fn color_table(colors: &[[u8; 3]], data: &mut Vec<u8>) -> u8 {
+    let size_bits = (1..=8).find(|bits| (1 << bits) >= colors.len()).unwrap();
+
+    colors.iter().for_each(|color| data.extend_from_slice(color));
+    data.extend(std::iter::repeat_n(0, 3 * ((1 << size_bits) - colors.len())));
+
+    0x80 | (size_bits - 1) as u8
+}
+
+fn gif(width: u16, height: u16, global_color_table: &[[u8; 3]], loop_count: Option<u16>, frames: &[Frame]) -> Vec<u8> {
+    let mut data: Vec<u8> = b"GIF89a".to_vec();
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+
+    let mut table: Vec<u8> = Vec::new();
+
+    let flags = match global_color_table.is_empty() {
+        true => 0,
+        false => color_table(global_color_table, &mut table)
+    };
+
+    data.extend_from_slice(&[flags, 0, 0]);
+    data.extend_from_slice(&table);
+
+    if let Some(loop_count) = loop_count {
+        data.extend_from_slice(&[0x21, 0xFF, 11]);
+        data.extend_from_slice(b"NETSCAPE2.0");
+        data.extend_from_slice(&[3, 1]);
+        data.extend_from_slice(&loop_count.to_le_bytes());
+        data.push(0);
+    }
+
+    for frame in frames {
+        data.extend_from_slice(&[0x21, 0xF9, 4, (frame.disposal << 2) | frame.transparent.is_some() as u8]);
+        data.extend_from_slice(&frame.delay.to_le_bytes());
+        data.extend_from_slice(&[frame.transparent.unwrap_or(0), 0]);
+
+        data.push(0x2C);
+
+        for value in [frame.left, frame.top, frame.width, frame.height] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut table: Vec<u8> = Vec::new();
+
+        let flags = match &frame.color_table {
+            Some(colors) => color_table(colors, &mut table),
+            None => 0
+        };
+
+        data.push(flags | ((frame.interlaced as u8) << 6));
+        data.extend_from_slice(&table);
+
+        //Store interlaced rows in the order of their passes
+        let indices: Vec<u8> = match frame.interlaced {
+            true => [(0, 8), (4, 8), (2, 4), (1, 2)].iter()
+                .flat_map(|(start, step)| (*start..frame.height as usize).step_by(*step))
+                .flat_map(|row| frame.indices[(row * frame.width as usize)..((row + 1) * frame.width as usize)].to_vec())
+                .collect(),
+            false => frame.indices.clone()
+        };
+
+        data.extend(lzw(&indices, 2));
+    }
+
+    data.push(0x3B);
+    data
+}
+
+const RED: [u8; 3] = [0xFF, 0, 0];
+const GREEN: [u8; 3] = [0, 0xFF, 0];
+const BLUE: [u8; 3] = [0, 0, 0xFF];
+const WHITE: [u8; 3] = [0xFF, 0xFF, 0xFF];
+
+fn opaque(color: [u8; 3]) -> (u8, u8, u8, u8) {
+    (color[0], color[1], color[2], 0xFF)
+}
+
+#[test]
+fn read_single_frame() {
+    let bytes = gif(3, 2, &[RED, GREEN, BLUE, WHITE], None, &[frame(0, 0, 3, 2, vec![0, 1, 2, 3, 3, 0])]);
+
+    let bitmap = BitMapRaw::from_gif_bytes(&bytes).unwrap();
+    assert_eq!(bitmap.info_header.bits_per_pixel, 24);
+    assert_eq!(pixels(&bitmap), vec![opaque(RED), opaque(GREEN), opaque(BLUE), opaque(WHITE), opaque(WHITE), opaque(RED)]);
+
+    let animation = BitMapAnimation::from_gif_bytes(&bytes).unwrap();
+    assert_eq!(animation.frames.len(), 1);
+    assert_eq!(animation.loop_count, None);
+    assert_eq!(pixels(&animation.frames[0].bitmap), pixels(&bitmap));
+}
+
+/// 
+/// Long, varied data should grow the codes to 12 bits, and clear the code table once it fills
+/// 
+#[test]
+fn lzw_code_table() {
+    let palette: Vec<[u8; 3]> = (0..=255_u8).map(|value| [value, 255 - value, value / 2]).collect();
+
+    let mut seed: u32 = 1;
+
+    let indices: Vec<u8> = (0..(300 * 200))
+        .map(|position| match position % 7 {
+            0 => {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (seed >> 16) as u8
+            },
+            _ => (position / 900) as u8
+        })
+        .collect();
+
+    let mut data = gif(300, 200, &palette, None, &[]);
+    data.pop();
+
+    //An image with a minimum code size of 8
+    data.push(0x2C);
+    [0_u16, 0, 300, 200].iter().for_each(|value| data.extend_from_slice(&value.to_le_bytes()));
+    data.push(0);
+    data.extend(lzw(&indices, 8));
+    data.push(0x3B);
+
+    let bitmap = BitMapRaw::from_gif_bytes(&data).unwrap();
+    assert_eq!(pixels(&bitmap), indices.iter().map(|index| opaque(palette[*index as usize])).collect::<Vec<_>>());
+
+    //A single repeated index makes ever longer codes
+    let bytes = gif(64, 64, &[RED, GREEN], None, &[frame(0, 0, 64, 64, vec![1; 64 * 64])]);
+    assert_eq!(pixels(&BitMapRaw::from_gif_bytes(&bytes).unwrap()), vec![opaque(GREEN); 64 * 64]);
+}
+
+#[test]
+fn interlaced_rows() {
+    let indices: Vec<u8> = (0..10).map(|row| row % 4).collect();
+
+    let mut interlaced = frame(0, 0, 1, 10, indices.clone());
+    interlaced.interlaced = true;
+
+    let bitmap = BitMapRaw::from_gif_bytes(&gif(1, 10, &[RED, GREEN, BLUE, WHITE], None, &[interlaced])).unwrap();
+    let colors = [RED, GREEN, BLUE, WHITE];
+
+    assert_eq!(pixels(&bitmap), indices.iter().map(|index| opaque(colors[*index as usize])).collect::<Vec<_>>());
+}
+
+/// 
+/// Each frame is drawn over the last, with transparent pixels left as they were,
+/// and then disposed of as its graphic control extension says
+/// 
+#[test]
+fn transparency_and_disposal() {
+    let mut first = frame(0, 0, 4, 1, vec![0, 0, 0, 0]);
+    first.delay = 10;
+
+    //Transparent in the middle, then cleared
+    let mut second = frame(0, 0, 3, 1, vec![1, 3, 1]);
+    second.transparent = Some(3);
+    second.disposal = 2;
+    second.delay = 25;
+
+    //Restored to before it was drawn
+    let mut third = frame(3, 0, 1, 1, vec![2]);
+    third.disposal = 3;
+
+    //Drawn with its own color table
+    let mut fourth = frame(1, 0, 2, 1, vec![0, 1]);
+    fourth.color_table = Some(vec![WHITE, BLUE]);
+
+    let bytes = gif(4, 1, &[RED, GREEN, BLUE, WHITE], Some(0), &[first, second, third, fourth]);
+    let animation = BitMapAnimation::from_gif_bytes(&bytes).unwrap();
+
+    assert_eq!(animation.loop_count, Some(0));
+    assert!(animation.warnings.is_empty());
+
+    let frames: Vec<Vec<(u8, u8, u8, u8)>> = animation.frames.iter().map(|frame| pixels(&frame.bitmap)).collect();
+
+    assert_eq!(frames, vec![
+        vec![opaque(RED), opaque(RED), opaque(RED), opaque(RED)],
+        vec![opaque(GREEN), opaque(RED), opaque(GREEN), opaque(RED)],
+        vec![CLEAR, CLEAR, CLEAR, opaque(BLUE)],
+        vec![CLEAR, opaque(WHITE), opaque(BLUE), opaque(RED)]
+    ]);
+
+    let delays: Vec<Duration> = animation.frames.iter().map(|frame| frame.delay).collect();
+    assert_eq!(delays, vec![Duration::from_millis(100), Duration::from_millis(250), Duration::ZERO, Duration::ZERO]);
+
+    //Frames with any transparency are 32 bpp
+    assert_eq!(animation.frames[0].bitmap.info_header.bits_per_pixel, 24);
+    assert_eq!(animation.frames[2].bitmap.info_header.bits_per_pixel, 32);
+
+    //Reading a plain image only gives the first frame
+    assert_eq!(pixels(&BitMapRaw::from_gif_bytes(&bytes).unwrap()), frames[0]);
+}
+
+/// 
+/// Frames are clipped to the logical screen
+/// 
+#[test]
+fn frames_outside_of_screen() {
+    let bytes = gif(2, 2, &[RED, GREEN], None, &[frame(1, 1, 2, 2, vec![1, 0, 0, 0])]);
+
+    assert_eq!(pixels(&BitMapRaw::from_gif_bytes(&bytes).unwrap()), vec![CLEAR, CLEAR, CLEAR, opaque(GREEN)]);
+}
+
+#[test]
+fn read_errors() {
+    let bytes = gif(2, 2, &[RED, GREEN], None, &[frame(0, 0, 2, 2, vec![0, 1, 1, 0]), frame(0, 0, 2, 2, vec![1, 1, 1, 1])]);
+
+    assert!(matches!(BitMapRaw::from_gif_bytes(&bytes[..10]), Err(BmpError::Truncated { .. })));
+    assert!(matches!(BitMapRaw::from_gif_bytes(&[b"PIF", &bytes[3..]].concat()), Err(BmpError::BadSignature(_))));
+    assert!(matches!(BitMapRaw::from_gif_bytes(&[b"GIF90a", &bytes[6..]].concat()), Err(BmpError::MalformedHeader(_))));
+
+    let mut limited = BitMapRawReadSettings::default();
+    limited.with_limits(DecodeLimits::new(1, 2, 4, 1 << 20));
+    assert!(matches!(BitMapRaw::from_gif_bytes_with_settings(&bytes, &limited), Err(BmpError::LimitExceeded { .. })));
+
+    //The limit on allocation covers every frame, along with the indices of the frame being decoded
+    limited.with_limits(DecodeLimits::new(2, 2, 4, 16 + 4));
+    assert!(BitMapRaw::from_gif_bytes_with_settings(&bytes, &limited).is_ok());
+    assert!(matches!(BitMapAnimation::from_gif_bytes_with_settings(&bytes, &limited), Err(BmpError::LimitExceeded { limit: DecodeLimit::AllocBytes, .. })));
+
+    //Frames are checked against the limits before their image data is decoded, even if they extend past the logical screen
+    let huge_frame = gif(2, 2, &[RED, GREEN], None, &[frame(0, 0, 0xFFFF, 0xFFFF, vec![0])]);
+    assert!(matches!(BitMapRaw::from_gif_bytes(&huge_frame), Err(BmpError::LimitExceeded { limit: DecodeLimit::Pixels, .. })));
+
+    let wide_frame = gif(2, 2, &[RED, GREEN], None, &[frame(1, 0, 3, 1, vec![0, 1, 0])]);
+    limited.with_limits(DecodeLimits::new(2, 2, 4, 1 << 20));
+    assert!(matches!(BitMapRaw::from_gif_bytes_with_settings(&wide_frame, &limited), Err(BmpError::LimitExceeded { limit: DecodeLimit::Width, value: 3, max: 2 })));
+    assert_eq!(pixels(&BitMapRaw::from_gif_bytes(&wide_frame).unwrap()), vec![CLEAR, opaque(RED), CLEAR, CLEAR]);
+
+    let no_images = gif(2, 2, &[RED, GREEN], None, &[]);
+    assert!(matches!(BitMapRaw::from_gif_bytes(&no_images), Err(BmpError::MalformedHeader(_))));
+
+    let mut lenient = BitMapRawReadSettings::default();
+    lenient.with_mode(DecodeMode::Lenient);
+
+    //Cut off in the image data of the second frame
+    let truncated = &bytes[..(bytes.len() - 4)];
+    assert!(matches!(BitMapAnimation::from_gif_bytes(truncated), Err(BmpError::Truncated { .. })));
+    assert!(BitMapRaw::from_gif_bytes(truncated).is_ok());
+
+    let recovered = BitMapAnimation::from_gif_bytes_with_settings(truncated, &lenient).unwrap();
+    assert_eq!(recovered.frames.len(), 2);
+    assert!(matches!(recovered.warnings[..], [DecodeWarning::TruncatedPixelData { .. }]));
+
+    //Indices past the end of the color table are corrupt image data
+    let out_of_range = gif(2, 1, &[RED, GREEN], None, &[frame(0, 0, 2, 1, vec![0, 3])]);
+    assert!(matches!(BitMapRaw::from_gif_bytes(&out_of_range), Err(BmpError::CorruptData(_))));
+
+    let recovered = BitMapRaw::from_gif_bytes_with_settings(&out_of_range, &lenient).unwrap();
+    assert_eq!(pixels(&recovered), vec![opaque(RED), CLEAR]);
+    assert_eq!(recovered.warnings, vec![DecodeWarning::TruncatedPixelData { missing_pixels: 1 }]);
+
+    //With a minimum code size of 9, the 10-bit codes clear, 300 and end, where 300 doesn't fit in a byte
+    let mut wide_codes = gif(2, 1, &[RED, GREEN], None, &[frame(0, 0, 2, 1, vec![0, 1])]);
+    wide_codes.truncate(wide_codes.len() - 1 - lzw(&[0, 1], 2).len());
+    wide_codes.extend([9, 4]);
+    wide_codes.extend((512_u32 | (300 << 10) | (513 << 20)).to_le_bytes());
+    wide_codes.extend([0, 0x3B]);
+    assert!(matches!(BitMapRaw::from_gif_bytes(&wide_codes), Err(BmpError::CorruptData(_))));
+
+    //The 3-bit codes clear, 0, then 7, which is past the end of the code table
+    let mut corrupt = gif(2, 1, &[RED, GREEN], None, &[frame(0, 0, 2, 1, vec![0, 1])]);
+    let data_offset = corrupt.len() - 4;
+    corrupt[data_offset..(data_offset + 2)].copy_from_slice(&[0xC4, 0x0B]);
+    assert!(matches!(BitMapRaw::from_gif_bytes(&corrupt), Err(BmpError::CorruptData(_))));
+
+    let recovered = BitMapRaw::from_gif_bytes_with_settings(&corrupt, &lenient).unwrap();
+    assert_eq!(pixels(&recovered), vec![opaque(RED), CLEAR]);
+    assert_eq!(recovered.warnings, vec![DecodeWarning::TruncatedPixelData { missing_pixels: 1 }]);
+}
@@ -0,0 +1,184 @@
+use bitmap_handler::bmp::*;
+
+mod common;
+use common::*;
+
+/// 
+/// Build a DIB of the given size and bit depth, with the given color table, rows of pixel data and rows of the AND mask,
+/// each from the bottom row up and without padding
+/// 
+fn dib(width: i32, height: i32, bits_per_pixel: u16, color_table: &[(u8, u8, u8)], rows: &[&[u8]], mask: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = [
+        &40_u32.to_le_bytes()[..],
+        &width.to_le_bytes(),
+        &(height * 2).to_le_bytes(),
+        &1_u16.to_le_bytes(),
+        &bits_per_pixel.to_le_bytes(),
+        &[0; 24]
+    ].concat();
+
+    for (red, green, blue) in color_table {
+        bytes.extend([*blue, *green, *red, 0]);
+    }
+
+    //Pad each row to a multiple of 4 bytes
+    for row in rows.iter().chain(mask) {
+        bytes.extend(*row);
+        bytes.resize(bytes.len() + (4 - row.len() % 4) % 4, 0);
+    }
+
+    bytes
+}
+
+/// 
+/// The width and height, planes and bit depth (or hotspot), and image of an entry in an icon
+/// 
+type Entry<'a> = ((u8, u8), (u16, u16), &'a [u8]);
+
+/// 
+/// Build an icon (type 1) or cursor (type 2) from the given directory fields and images
+/// 
+fn ico(kind: u16, entries: &[Entry]) -> Vec<u8> {
+    let mut bytes = [&[0, 0][..], &kind.to_le_bytes(), &(entries.len() as u16).to_le_bytes()].concat();
+    let mut offset = 6 + 16 * entries.len() as u32;
+
+    for ((width, height), (planes_or_x, bits_or_y), image) in entries {
+        bytes.extend([*width, *height, 0, 0]);
+        bytes.extend(planes_or_x.to_le_bytes());
+        bytes.extend(bits_or_y.to_le_bytes());
+        bytes.extend((image.len() as u32).to_le_bytes());
+        bytes.extend(offset.to_le_bytes());
+        offset += image.len() as u32;
+    }
+
+    for (_, _, image) in entries {
+        bytes.extend(*image);
+    }
+
+    bytes
+}
+
+/// 
+/// A 2x2 24 bpp image: red and green on the top row, and blue and white on the bottom
+/// 
+fn true_color(mask: &[&[u8]]) -> Vec<u8> {
+    dib(2, 2, 24, &[], &[&[0xFF, 0, 0, 0xFF, 0xFF, 0xFF], &[0, 0, 0xFF, 0, 0xFF, 0]], mask)
+}
+
+#[test]
+fn read_entries() {
+    let small = dib(1, 1, 4, &[(0, 0, 0); 16], &[&[0x00]], &[&[0x00]]);
+    let large = true_color(&[&[0x00], &[0x00]]);
+
+    let mut png: Vec<u8> = Vec::new();
+    BitMapRaw::from_ico_bytes(&ico(1, &[((2, 2), (1, 24), &large)])).unwrap()
+        .write_png_to(&mut png, &PngWriteSettings::default()).unwrap();
+
+    //The bit depth of the second image is left for the image to give
+    let icon = ico(1, &[((1, 1), (1, 4), &small), ((2, 2), (1, 0), &large), ((0, 0), (1, 32), &png)]);
+    let entries = BitMapRaw::ico_entries_from_bytes(&icon).unwrap();
+
+    assert_eq!(entries.len(), 3);
+    assert_eq!((entries[0].width, entries[0].height, entries[0].bits_per_pixel, entries[0].hotspot, entries[0].is_png), (1, 1, 4, None, false));
+    assert_eq!((entries[1].width, entries[1].height, entries[1].bits_per_pixel, entries[1].is_png), (2, 2, 24, false));
+    assert_eq!((entries[2].width, entries[2].height, entries[2].is_png), (256, 256, true));
+    assert_eq!((entries[1].size as usize, entries[1].offset as usize), (large.len(), 6 + 16 * 3 + small.len()));
+
+    //Cursors keep their hotspot in place of the bit depth, which comes from the image instead
+    let cursor = ico(2, &[((2, 2), (1, 2), &large), ((0, 0), (3, 4), &png)]);
+    let entries = BitMapRaw::ico_entries_from_bytes(&cursor).unwrap();
+
+    assert_eq!((entries[0].bits_per_pixel, entries[0].hotspot), (24, Some((1, 2))));
+    assert_eq!(entries[1].hotspot, Some((3, 4)));
+    assert_eq!(entries[1].bits_per_pixel, if BitMapRaw::from_png_bytes(&png).unwrap().info_header.bits_per_pixel == 32 { 32 } else { 24 });
+    assert_eq!(entries[0].to_string(), "2x2, 24 bpp, hotspot at (1, 2)");
+}
+
+#[test]
+fn apply_and_mask() {
+    //No pixels are masked, so the image is left as it was
+    let opaque = BitMapRaw::from_ico_bytes(&ico(1, &[((2, 2), (1, 24), &true_color(&[&[0x00], &[0x00]]))])).unwrap();
+    assert_eq!(opaque.info_header.bits_per_pixel, 24);
+    assert_eq!(pixels(&opaque), vec![RED, GREEN, BLUE, WHITE]);
+
+    //The mask is bottom-up, like the pixels: the bottom right and top left pixels are transparent
+    let masked = BitMapRaw::from_ico_bytes(&ico(1, &[((2, 2), (1, 24), &true_color(&[&[0x40], &[0x80]]))])).unwrap();
+    assert_eq!(masked.info_header.bits_per_pixel, 32);
+    assert_eq!(pixels(&masked), vec![(0xFF, 0, 0, 0), GREEN, BLUE, (0xFF, 0xFF, 0xFF, 0)]);
+
+    //Indexed images without masked pixels keep their indices
+    let palette = [(0xFF, 0, 0), (0, 0xFF, 0)];
+    let indexed = BitMapRaw::from_ico_bytes(&ico(1, &[((2, 1), (1, 1), &dib(2, 1, 1, &palette, &[&[0x40]], &[&[0x00]]))])).unwrap();
+    assert!(indexed.pixel_data.is_indexed());
+    assert_eq!(pixels(&indexed), vec![RED, GREEN]);
+
+    let indexed = BitMapRaw::from_ico_bytes(&ico(1, &[((2, 1), (1, 1), &dib(2, 1, 1, &palette, &[&[0x40]], &[&[0x80]]))])).unwrap();
+    assert!(!indexed.pixel_data.is_indexed());
+    assert_eq!(pixels(&indexed), vec![(0xFF, 0, 0, 0), GREEN]);
+
+    //32 bpp images use their own alpha channel, unless every pixel of it is 0
+    let with_alpha = dib(2, 1, 32, &[], &[&[0, 0, 0xFF, 0x80, 0, 0xFF, 0, 0xFF]], &[&[0xC0]]);
+    assert_eq!(pixels(&BitMapRaw::from_ico_bytes(&ico(1, &[((2, 1), (1, 32), &with_alpha)])).unwrap()), vec![(0xFF, 0, 0, 0x80), GREEN]);
+
+    let without_alpha = dib(2, 1, 32, &[], &[&[0, 0, 0xFF, 0, 0, 0xFF, 0, 0]], &[&[0x40]]);
+    assert_eq!(pixels(&BitMapRaw::from_ico_bytes(&ico(1, &[((2, 1), (1, 32), &without_alpha)])).unwrap()), vec![RED, (0, 0xFF, 0, 0)]);
+}
+
+#[test]
+fn choose_entry() {
+    let small = dib(1, 1, 24, &[], &[&[0xFF, 0, 0]], &[&[0x00]]);
+    let large_4 = dib(2, 1, 4, &[(0, 0xFF, 0); 16], &[&[0x00]], &[&[0x00]]);
+    let large_24 = dib(2, 1, 24, &[], &[&[0xFF, 0, 0, 0xFF, 0, 0]], &[&[0x00]]);
+
+    //The largest image is read, and of those, the one with the most bits per pixel
+    let icon = ico(1, &[((1, 1), (1, 24), &small), ((2, 1), (1, 4), &large_4), ((2, 1), (1, 24), &large_24), ((2, 1), (1, 4), &large_4)]);
+    assert_eq!(BitMapRaw::best_ico_entry(&BitMapRaw::ico_entries_from_bytes(&icon).unwrap()), Some(2));
+    assert_eq!(pixels(&BitMapRaw::from_ico_bytes(&icon).unwrap()), vec![BLUE, BLUE]);
+
+    let settings = BitMapRawReadSettings::default();
+    assert_eq!(pixels(&BitMapRaw::from_ico_entry_bytes_with_settings(&icon, 0, &settings).unwrap()), vec![BLUE]);
+    assert_eq!(pixels(&BitMapRaw::from_ico_entry_bytes_with_settings(&icon, 1, &settings).unwrap()), vec![GREEN, GREEN]);
+    assert!(matches!(BitMapRaw::from_ico_entry_bytes_with_settings(&icon, 4, &settings), Err(BmpError::EntryOutOfRange { index: 4, count: 4 })));
+
+    //The data offset of each image is where its pixel data would be, after a file header, info header and color table
+    assert_eq!(BitMapRaw::from_ico_entry_bytes_with_settings(&icon, 0, &settings).unwrap().header.data_offset, 14 + 40);
+    assert_eq!(BitMapRaw::from_ico_entry_bytes_with_settings(&icon, 1, &settings).unwrap().header.data_offset, 14 + 40 + 16 * 4);
+
+    //The registry reads the largest image, unless a codec for another is registered
+    let mut codecs = CodecRegistry::default();
+    assert_eq!(pixels(&codecs.decode(&icon, None, &settings).unwrap()), vec![BLUE, BLUE]);
+
+    codecs.register_decoder(Box::new(IcoCodec::new(Some(1))));
+    assert_eq!(pixels(&codecs.decode(&icon, None, &settings).unwrap()), vec![GREEN, GREEN]);
+
+    //PNG images are read as they are
+    let mut png: Vec<u8> = Vec::new();
+    BitMapRaw::from_ico_bytes(&icon).unwrap().write_png_to(&mut png, &PngWriteSettings::default()).unwrap();
+    assert_eq!(pixels(&BitMapRaw::from_ico_bytes(&ico(1, &[((2, 1), (1, 32), &png)])).unwrap()), vec![BLUE, BLUE]);
+}
+
+#[test]
+fn read_errors() {
+    let image = true_color(&[&[0x00], &[0x00]]);
+    let icon = ico(1, &[((2, 2), (1, 24), &image)]);
+
+    assert!(matches!(BitMapRaw::from_ico_bytes(&icon[..4]), Err(BmpError::Truncated { needed: 6, available: 4 })));
+    assert!(matches!(BitMapRaw::from_ico_bytes(&icon[..20]), Err(BmpError::Truncated { needed: 22, available: 20 })));
+    assert!(matches!(BitMapRaw::from_ico_bytes(&ico(3, &[((2, 2), (1, 24), &image)])), Err(BmpError::MalformedHeader(_))));
+    assert!(matches!(BitMapRaw::from_ico_bytes(&ico(1, &[])), Err(BmpError::MalformedHeader(_))));
+
+    //Icons can't be compressed
+    let mut compressed = image.clone();
+    compressed[16] = 1;
+    assert!(matches!(BitMapRaw::from_ico_bytes(&ico(1, &[((2, 2), (1, 24), &compressed)])), Err(BmpError::UnsupportedCompression(1))));
+
+    //A missing mask fails, unless decoding leniently, which leaves the missing rows opaque
+    let mut settings = BitMapRawReadSettings::default();
+    let short_mask = &icon[..icon.len() - 4];
+    assert!(matches!(BitMapRaw::from_ico_bytes(short_mask), Err(BmpError::Truncated { .. })));
+
+    settings.with_mode(DecodeMode::Lenient);
+    let bitmap = BitMapRaw::from_ico_bytes_with_settings(short_mask, &settings).unwrap();
+    assert_eq!(pixels(&bitmap), vec![RED, GREEN, BLUE, WHITE]);
+    assert_eq!(bitmap.warnings, vec![DecodeWarning::TruncatedMask { missing_rows: 1 }]);
+}
@@ -0,0 +1,126 @@
+use bitmap_handler::bmp::*;
+
+mod common;
+use common::*;
+
+fn write(bitmap: &BitMapRaw, format: NetpbmFormat, encoding: NetpbmEncoding, max_value: u16) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    bitmap.write_netpbm_to(&mut bytes, &NetpbmWriteSettings::new(format, encoding, max_value)).unwrap();
+    bytes
+}
+
+/// 
+/// Hand-written images, with comments and uneven whitespace
+/// 
+#[test]
+fn read_ascii() {
+    let pbm = BitMapRaw::from_netpbm_bytes(b"P1\n# A comment\n3 2\n010\n1 1 0").unwrap();
+
+    assert!(pbm.pixel_data.is_indexed());
+    assert_eq!(pixels(&pbm), vec![
+        (0xFF, 0xFF, 0xFF, 0xFF), (0, 0, 0, 0xFF), (0xFF, 0xFF, 0xFF, 0xFF),
+        (0, 0, 0, 0xFF), (0, 0, 0, 0xFF), (0xFF, 0xFF, 0xFF, 0xFF)
+    ]);
+
+    let pgm = BitMapRaw::from_netpbm_bytes(b"P2 2 1 4 # max\n0 2").unwrap();
+    assert_eq!(pixels(&pgm), vec![(0, 0, 0, 0xFF), (128, 128, 128, 0xFF)]);
+
+    let ppm = BitMapRaw::from_netpbm_bytes(b"P3\n1 2\n255\n255 0 0\n0 0 255\n").unwrap();
+    assert_eq!(pixels(&ppm), vec![(0xFF, 0, 0, 0xFF), (0, 0, 0xFF, 0xFF)]);
+}
+
+/// 
+/// Binary PBM rows are padded to a whole byte, and 16-bit samples are big-endian
+/// 
+#[test]
+fn read_binary() {
+    let pbm = BitMapRaw::from_netpbm_bytes(b"P4\n9 2\n\x80\x80\x7F\x00").unwrap();
+    let black: Vec<bool> = pixels(&pbm).iter().map(|p| p.0 == 0).collect();
+
+    assert_eq!(black, vec![
+        true, false, false, false, false, false, false, false, true,
+        false, true, true, true, true, true, true, true, false
+    ]);
+
+    let ppm = BitMapRaw::from_netpbm_bytes(b"P6 1 1 65535\n\xFF\xFF\x80\x00\x00\x00").unwrap();
+    assert_eq!(pixels(&ppm), vec![(0xFF, 0x80, 0, 0xFF)]);
+
+    let pam = BitMapRaw::from_netpbm_bytes(b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 2\nMAXVAL 255\nTUPLTYPE GRAYSCALE_ALPHA\nENDHDR\n\x10\x20\x30\x40").unwrap();
+    assert_eq!(pam.info_header.bits_per_pixel, 32);
+    assert_eq!(pixels(&pam), vec![(0x10, 0x10, 0x10, 0x20), (0x30, 0x30, 0x30, 0x40)]);
+}
+
+/// 
+/// Sample bitmaps should keep their colors through PPM, at any maximum value
+/// and in either encoding, and their alpha channel through PAM
+/// 
+#[test]
+fn round_trip() {
+    for path in ["input/img10.bmp", "input/img14.bmp", "input/img24.bmp"] {
+        let bitmap = BitMapRaw::read_from_file(path).unwrap();
+        let opaque: Vec<(u8, u8, u8, u8)> = pixels(&bitmap).iter().map(|p| (p.0, p.1, p.2, 0xFF)).collect();
+
+        for encoding in [NetpbmEncoding::Ascii, NetpbmEncoding::Binary] {
+            for max_value in [0xFF, 0xFFFF] {
+                let written = BitMapRaw::from_netpbm_bytes(&write(&bitmap, NetpbmFormat::Ppm, encoding, max_value)).unwrap();
+                assert_eq!(pixels(&written), opaque, "{path}");
+            }
+        }
+
+        let written = BitMapRaw::from_netpbm_bytes(&write(&bitmap, NetpbmFormat::Pam, NetpbmEncoding::Binary, 0xFF)).unwrap();
+        assert_eq!(pixels(&written), pixels(&bitmap), "{path}");
+    }
+}
+
+/// 
+/// Greyscale and black and white images are written by luminance
+/// 
+#[test]
+fn write_greyscale() {
+    let ppm = BitMapRaw::from_netpbm_bytes(b"P3 3 1 255\n255 255 255 0 0 255 200 200 200").unwrap();
+
+    let ascii = write(&ppm, NetpbmFormat::Pgm, NetpbmEncoding::Ascii, 0xFF);
+    assert_eq!(ascii, b"P2\n3 1\n255\n255 29 200\n");
+
+    for encoding in [NetpbmEncoding::Ascii, NetpbmEncoding::Binary] {
+        let pbm = BitMapRaw::from_netpbm_bytes(&write(&ppm, NetpbmFormat::Pbm, encoding, 0xFF)).unwrap();
+        let black: Vec<bool> = pixels(&pbm).iter().map(|p| p.0 == 0).collect();
+
+        assert_eq!(black, vec![false, true, false]);
+    }
+
+    //ASCII lines are at most 70 characters
+    let wide = BitMapRaw::from_netpbm_bytes(&[b"P5 100 1 255\n".to_vec(), vec![0xFF; 100]].concat()).unwrap();
+    let ascii = write(&wide, NetpbmFormat::Pgm, NetpbmEncoding::Ascii, 0xFF);
+
+    assert!(String::from_utf8(ascii).unwrap().lines().all(|line| line.len() <= 70));
+}
+
+#[test]
+fn read_errors() {
+    assert!(matches!(BitMapRaw::from_netpbm_bytes(b"BM"), Err(BmpError::BadSignature(_))));
+    assert!(matches!(BitMapRaw::from_netpbm_bytes(b"P8 1 1 255"), Err(BmpError::BadSignature(_))));
+    assert!(matches!(BitMapRaw::from_netpbm_bytes(b"P6 1 x 255"), Err(BmpError::MalformedHeader(_))));
+    assert!(matches!(BitMapRaw::from_netpbm_bytes(b"P5 1 1 0\n\x00"), Err(BmpError::MalformedHeader(_))));
+    assert!(matches!(BitMapRaw::from_netpbm_bytes(b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 3\nMAXVAL 255\n"), Err(BmpError::MalformedHeader(_))));
+    assert!(matches!(BitMapRaw::from_netpbm_bytes(b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 5\nMAXVAL 255\nENDHDR\n"), Err(BmpError::MalformedHeader(_))));
+    assert!(matches!(BitMapRaw::from_netpbm_bytes(b"P6 100000 100000 255\n"), Err(BmpError::LimitExceeded { .. })));
+}
+
+/// 
+/// Truncated images are an error, unless decoding leniently
+/// 
+#[test]
+fn truncated() {
+    let bytes = b"P6 2 1 255\n\xFF\x00\x00";
+
+    assert!(matches!(BitMapRaw::from_netpbm_bytes(bytes), Err(BmpError::Truncated { .. })));
+
+    let mut settings = BitMapRawReadSettings::default();
+    settings.with_mode(DecodeMode::Lenient);
+
+    let bitmap = BitMapRaw::from_netpbm_bytes_with_settings(bytes, &settings).unwrap();
+
+    assert_eq!(pixels(&bitmap), vec![(0xFF, 0, 0, 0xFF), (0, 0, 0, 0)]);
+    assert_eq!(bitmap.warnings, vec![DecodeWarning::TruncatedPixelData { missing_pixels: 1 }]);
+}
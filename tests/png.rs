@@ -0,0 +1,167 @@
+use bitmap_handler::bmp::*;
+
+mod common;
+use common::*;
+
+const WIDTH: usize = 13;
+const HEIGHT: usize = 11;
+
+/// 
+/// The pattern every image in input_png is drawn from
+/// 
+fn pattern(x: usize, y: usize) -> (u8, u8, u8, u8) {
+    ((x * 19 % 256) as u8, (y * 23 % 256) as u8, (x * y * 7 % 256) as u8, ((x + y) * 12 % 256) as u8)
+}
+
+fn expected(color: fn(usize, usize) -> (u8, u8, u8, u8)) -> Vec<(u8, u8, u8, u8)> {
+    (0..HEIGHT)
+        .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+        .map(|(x, y)| color(x, y))
+        .collect()
+}
+
+fn write(bitmap: &BitMapRaw, color_type: PngColorType, bit_depth: u8, interlaced: bool) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    bitmap.write_png_to(&mut bytes, &PngWriteSettings::new(color_type, bit_depth, interlaced, BitMapQuantization::MedianCut, RGBColor::get_euclidean_distance_rgb)).unwrap();
+    bytes
+}
+
+/// 
+/// Each color type, with every filter type, 16-bit channels, transparency and Adam7 interlacing
+/// 
+#[test]
+fn read_color_types() {
+    let read = |name: &str| BitMapRaw::read_png_from_file(&format!("input_png/{name}")).unwrap();
+
+    assert_eq!(pixels(&read("rgba8.png")), expected(pattern));
+    assert_eq!(pixels(&read("rgba8_adam7.png")), expected(pattern));
+    assert_eq!(pixels(&read("rgb16_adam7.png")), expected(|x, y| {
+        let (red, green, blue, _) = pattern(x, y);
+        (red, green, blue, 0xFF)
+    }));
+
+    assert_eq!(pixels(&read("grey2.png")), expected(|x, y| {
+        let grey = (((x + y) % 4) * 85) as u8;
+        (grey, grey, grey, 0xFF)
+    }));
+
+    let grey_transparent = read("grey8_trns.png");
+    assert_eq!(grey_transparent.info_header.bits_per_pixel, 32);
+    assert_eq!(pixels(&grey_transparent), expected(|x, y| {
+        let grey = (x * y % 256) as u8;
+        (grey, grey, grey, if grey == 0 { 0 } else { 0xFF })
+    }));
+
+    assert_eq!(pixels(&read("greyalpha16.png")), expected(|x, y| {
+        let (grey, _, _, alpha) = pattern(x, y);
+        (grey, grey, grey, alpha)
+    }));
+
+    let palette = read("palette4_adam7.png");
+    assert!(palette.pixel_data.is_indexed());
+    assert_eq!(palette.info_header.bits_per_pixel, 4);
+    assert_eq!(pixels(&palette), expected(|x, y| {
+        let index = (x + y) % 16;
+        let alpha = [0, 60, 120, 180].get(index).copied().unwrap_or(0xFF);
+        ((index * 16) as u8, (255 - index * 16) as u8, (index * 8) as u8, alpha)
+    }));
+}
+
+/// 
+/// Sample bitmaps should keep their colors through RGBA PNGs, interlaced or not,
+/// and their palette through palette PNGs
+/// 
+#[test]
+fn round_trip() {
+    for path in ["input/img10.bmp", "input/img14.bmp", "input/img24.bmp"] {
+        let bitmap = BitMapRaw::read_from_file(path).unwrap();
+
+        for interlaced in [false, true] {
+            for bit_depth in [8, 16] {
+                let written = BitMapRaw::from_png_bytes(&write(&bitmap, PngColorType::Rgba, bit_depth, interlaced)).unwrap();
+                assert_eq!(pixels(&written), pixels(&bitmap), "{path}");
+            }
+        }
+    }
+
+    let indexed = BitMapRaw::read_from_file("input/img14.bmp").unwrap();
+    let written = BitMapRaw::from_png_bytes(&write(&indexed, PngColorType::Palette, 8, true)).unwrap();
+
+    assert!(written.pixel_data.is_indexed());
+    assert_eq!(pixels(&written), pixels(&indexed));
+}
+
+/// 
+/// Greyscale images are written by luminance, and palette images are quantized to fit
+/// 
+#[test]
+fn write_reduced() {
+    let bitmap = BitMapRaw::read_from_file("input/img10.bmp").unwrap();
+
+    for bit_depth in [1, 2, 4, 8, 16] {
+        let written = BitMapRaw::from_png_bytes(&write(&bitmap, PngColorType::Greyscale, bit_depth, false)).unwrap();
+        let max = (1_u32 << u32::min(bit_depth as u32, 8)) - 1;
+
+        for ((red, green, blue, alpha), original) in pixels(&written).into_iter().zip(pixels(&bitmap)) {
+            let luminance = RGBColor { red: original.0, green: original.1, blue: original.2, alpha: 0xFF }.get_luminance();
+            let level = (luminance as u32 * max + 127) / 255;
+
+            assert!(red == green && green == blue && alpha == 0xFF);
+            assert_eq!(red as u32, level * 255 / max);
+        }
+    }
+
+    for bit_depth in [1, 2, 4] {
+        let written = BitMapRaw::from_png_bytes(&write(&bitmap, PngColorType::Palette, bit_depth, false)).unwrap();
+        assert!(written.color_table.colors.len() <= 1 << bit_depth);
+    }
+}
+
+#[test]
+fn write_errors() {
+    let bitmap = BitMapRaw::read_from_file("input/img0.bmp").unwrap();
+    let mut bytes: Vec<u8> = Vec::new();
+
+    for (color_type, bit_depth) in [(PngColorType::Rgb, 4), (PngColorType::Palette, 16), (PngColorType::Greyscale, 3)] {
+        let settings = PngWriteSettings::new(color_type, bit_depth, false, BitMapQuantization::MedianCut, RGBColor::get_euclidean_distance_rgb);
+        assert!(matches!(bitmap.write_png_to(&mut bytes, &settings), Err(BmpError::UnsupportedBitDepth(_))));
+    }
+}
+
+#[test]
+fn read_errors() {
+    let valid = std::fs::read("input_png/rgba8.png").unwrap();
+
+    assert!(matches!(BitMapRaw::from_png_bytes(b"BM"), Err(BmpError::Truncated { .. })));
+    assert!(matches!(BitMapRaw::from_png_bytes(&[b"\x89PNX".to_vec(), valid[4..].to_vec()].concat()), Err(BmpError::BadSignature(_))));
+
+    //A byte of the IHDR chunk is changed, without updating its CRC
+    let mut bad_crc = valid.clone();
+    bad_crc[17] ^= 0xFF;
+    assert!(matches!(BitMapRaw::from_png_bytes(&bad_crc), Err(BmpError::CorruptData(_))));
+
+    //Cut off in the middle of the image data
+    let truncated = &valid[..(valid.len() - 40)];
+    assert!(matches!(BitMapRaw::from_png_bytes(truncated), Err(BmpError::Truncated { .. })));
+
+    let mut settings = BitMapRawReadSettings::default();
+    settings.with_mode(DecodeMode::Lenient);
+
+    let recovered = BitMapRaw::from_png_bytes_with_settings(truncated, &settings).unwrap();
+    assert_eq!(pixels(&recovered).len(), WIDTH * HEIGHT);
+    assert!(matches!(recovered.warnings[..], [DecodeWarning::TruncatedPixelData { .. }]));
+
+    //Every truncation of every sample is an error or a warning, but never a panic
+    for entry in std::fs::read_dir("input_png").unwrap() {
+        let bytes = std::fs::read(entry.unwrap().path()).unwrap();
+
+        for length in 0..bytes.len() {
+            assert!(BitMapRaw::from_png_bytes(&bytes[..length]).is_err());
+            let _ = BitMapRaw::from_png_bytes_with_settings(&bytes[..length], &settings);
+        }
+    }
+
+    let mut limited = BitMapRawReadSettings::default();
+    limited.with_limits(DecodeLimits::new(8, 8, 64, 1 << 20));
+    assert!(matches!(BitMapRaw::from_png_bytes_with_settings(&valid, &limited), Err(BmpError::LimitExceeded { .. })));
+}
@@ -0,0 +1,103 @@
+use bitmap_handler::bmp::*;
+
+mod common;
+use common::*;
+
+fn write(bitmap: &BitMapRaw, channels: QoiChannels) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    bitmap.write_qoi_to(&mut bytes, &QoiWriteSettings::new(channels, QoiColorSpace::Srgb)).unwrap();
+    bytes
+}
+
+/// 
+/// A 4x2 image using every op
+/// 
+const EVERY_OP: &[u8] = &[
+    b'q', b'o', b'i', b'f', 0, 0, 0, 4, 0, 0, 0, 2, 4, 0,
+    //QOI_OP_RGB
+    0xFE, 10, 20, 30,
+    //QOI_OP_DIFF: red + 1, green - 1
+    0x76,
+    //QOI_OP_LUMA: green + 5, red - green = -3, blue - green = 2
+    0xA5, 0x5A,
+    //QOI_OP_RGBA
+    0xFF, 1, 2, 3, 4,
+    //QOI_OP_INDEX of the first pixel
+    0x09,
+    //QOI_OP_RUN of 3
+    0xC2,
+    0, 0, 0, 0, 0, 0, 0, 1
+];
+
+#[test]
+fn read_every_op() {
+    let bitmap = BitMapRaw::from_qoi_bytes(EVERY_OP).unwrap();
+
+    assert_eq!(bitmap.info_header.bits_per_pixel, 32);
+    assert_eq!(pixels(&bitmap), vec![
+        (10, 20, 30, 0xFF), (11, 19, 30, 0xFF), (13, 24, 37, 0xFF), (1, 2, 3, 4),
+        (10, 20, 30, 0xFF), (10, 20, 30, 0xFF), (10, 20, 30, 0xFF), (10, 20, 30, 0xFF)
+    ]);
+}
+
+/// 
+/// Every sample bitmap should keep its colors through QOI, and 3-channel
+/// images should be opaque
+/// 
+#[test]
+fn sample_bitmaps_round_trip() {
+    for entry in std::fs::read_dir("input").unwrap() {
+        let path = entry.unwrap().path();
+        let bitmap = BitMapRaw::read_from_file(path.to_str().unwrap()).unwrap();
+
+        let written = BitMapRaw::from_qoi_bytes(&write(&bitmap, QoiChannels::Rgba)).unwrap();
+        assert_eq!(pixels(&written), pixels(&bitmap), "{}", path.display());
+
+        let opaque = BitMapRaw::from_qoi_bytes(&write(&bitmap, QoiChannels::Rgb)).unwrap();
+        assert_eq!(opaque.info_header.bits_per_pixel, 24);
+        assert_eq!(pixels(&opaque), pixels(&bitmap).iter().map(|p| (p.0, p.1, p.2, 0xFF)).collect::<Vec<_>>(), "{}", path.display());
+    }
+}
+
+/// 
+/// Runs longer than 62 pixels are split, and the file ends with the end marker
+/// 
+#[test]
+fn long_runs() {
+    let bitmap = BitMapRaw::read_from_file("input/img0.bmp").unwrap();
+    let bytes = write(&bitmap, QoiChannels::Rgba);
+
+    assert!(bytes.ends_with(&[0, 0, 0, 0, 0, 0, 0, 1]));
+
+    let long = BitMapRaw::from_pixels(100, 1, 32, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(vec![RGBColor { red: 100, green: 150, blue: 200, alpha: 0xFF }; 100])).unwrap();
+    let bytes = write(&long, QoiChannels::Rgba);
+
+    //The first pixel, then runs of 62 and 37
+    assert_eq!(&bytes[14..(bytes.len() - 8)], &[0xFE, 100, 150, 200, 0xC0 | 61, 0xC0 | 36]);
+    assert_eq!(pixels(&BitMapRaw::from_qoi_bytes(&bytes).unwrap()).len(), 100);
+}
+
+#[test]
+fn read_errors() {
+    assert!(matches!(BitMapRaw::from_qoi_bytes(b"qoif"), Err(BmpError::Truncated { .. })));
+    assert!(matches!(BitMapRaw::from_qoi_bytes(&[b"qoix", &EVERY_OP[4..]].concat()), Err(BmpError::BadSignature(_))));
+
+    let mut bad_channels = EVERY_OP.to_vec();
+    bad_channels[12] = 5;
+    assert!(matches!(BitMapRaw::from_qoi_bytes(&bad_channels), Err(BmpError::MalformedHeader(_))));
+
+    let mut limited = BitMapRawReadSettings::default();
+    limited.with_limits(DecodeLimits::new(2, 2, 4, 1 << 20));
+    assert!(matches!(BitMapRaw::from_qoi_bytes_with_settings(EVERY_OP, &limited), Err(BmpError::LimitExceeded { .. })));
+
+    //Cut off before the run
+    let truncated = &EVERY_OP[..27];
+    assert!(matches!(BitMapRaw::from_qoi_bytes(truncated), Err(BmpError::Truncated { .. })));
+
+    let mut settings = BitMapRawReadSettings::default();
+    settings.with_mode(DecodeMode::Lenient);
+
+    let recovered = BitMapRaw::from_qoi_bytes_with_settings(truncated, &settings).unwrap();
+    assert_eq!(pixels(&recovered)[4..], [(10, 20, 30, 0xFF), (0, 0, 0, 0), (0, 0, 0, 0), (0, 0, 0, 0)]);
+    assert_eq!(recovered.warnings, vec![DecodeWarning::TruncatedPixelData { missing_pixels: 3 }]);
+}
@@ -0,0 +1,193 @@
+use bitmap_handler::bmp::*;
+
+mod common;
+use common::*;
+
+/// 
+/// Build a 2x2 TGA image with the given header fields, followed by the given color map and pixel data
+/// 
+fn tga(image_type: u8, bits_per_pixel: u8, descriptor: u8, color_map: (u16, u16, u8), data: &[u8]) -> Vec<u8> {
+    let (first, length, entry_bits) = color_map;
+
+    [
+        &[0, (length > 0) as u8, image_type][..],
+        &first.to_le_bytes(),
+        &length.to_le_bytes(),
+        &[entry_bits, 0, 0, 0, 0, 2, 0, 2, 0, bits_per_pixel, descriptor],
+        data
+    ].concat()
+}
+
+fn write(bitmap: &BitMapRaw, image_type: TgaImageType, bits_per_pixel: u8, rle: bool, origin: TgaOrigin) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    bitmap.write_tga_to(&mut bytes, &TgaWriteSettings::new(image_type, bits_per_pixel, rle, origin, BitMapQuantization::MedianCut, RGBColor::get_euclidean_distance_rgb)).unwrap();
+    bytes
+}
+
+/// 
+/// Each image type, raw and run-length encoded, from each corner
+/// 
+#[test]
+fn read_image_types() {
+    //True color, from the bottom left: the bottom row is written first
+    let true_color = tga(2, 24, 0, (0, 0, 0), &[0, 0, 0xFF, 0, 0xFF, 0, 0xFF, 0, 0, 0xFF, 0xFF, 0xFF]);
+    let bitmap = BitMapRaw::from_tga_bytes(&true_color).unwrap();
+    assert_eq!(bitmap.info_header.bits_per_pixel, 24);
+    assert_eq!(pixels(&bitmap), vec![BLUE, WHITE, RED, GREEN]);
+
+    //Each other corner
+    for (descriptor, expected) in [(0x10, vec![WHITE, BLUE, GREEN, RED]), (0x20, vec![RED, GREEN, BLUE, WHITE]), (0x30, vec![GREEN, RED, WHITE, BLUE])] {
+        let flipped = tga(2, 24, descriptor, (0, 0, 0), &true_color[18..]);
+        assert_eq!(pixels(&BitMapRaw::from_tga_bytes(&flipped).unwrap()), expected, "{descriptor}");
+    }
+
+    //Run-length encoded: a run of 3 red pixels, then 1 raw blue pixel
+    let rle = tga(10, 24, 0x20, (0, 0, 0), &[0x82, 0, 0, 0xFF, 0x00, 0xFF, 0, 0]);
+    assert_eq!(pixels(&BitMapRaw::from_tga_bytes(&rle).unwrap()), vec![RED, RED, RED, BLUE]);
+
+    //32 bits, with and without alpha bits in the descriptor
+    let alpha_data = [0, 0, 0xFF, 0x80, 0, 0xFF, 0, 0, 0xFF, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0x40];
+    let alpha = BitMapRaw::from_tga_bytes(&tga(2, 32, 0x28, (0, 0, 0), &alpha_data)).unwrap();
+    assert_eq!(alpha.info_header.bits_per_pixel, 32);
+    assert_eq!(pixels(&alpha), vec![(0xFF, 0, 0, 0x80), (0, 0xFF, 0, 0), BLUE, (0xFF, 0xFF, 0xFF, 0x40)]);
+
+    let opaque = BitMapRaw::from_tga_bytes(&tga(2, 32, 0x20, (0, 0, 0), &alpha_data)).unwrap();
+    assert_eq!(pixels(&opaque), vec![RED, GREEN, BLUE, WHITE]);
+
+    //16 bits, with a single alpha bit
+    let sixteen = BitMapRaw::from_tga_bytes(&tga(2, 16, 0x21, (0, 0, 0), &[0x00, 0xFC, 0xE0, 0x03, 0x1F, 0x80, 0xFF, 0x7F])).unwrap();
+    assert_eq!(sixteen.info_header.bits_per_pixel, 16);
+    assert_eq!(pixels(&sixteen), vec![RED, (0, 0xFF, 0, 0), BLUE, (0xFF, 0xFF, 0xFF, 0)]);
+
+    //Color mapped, with a color map starting at entry 1, run-length encoded
+    let color_mapped = tga(9, 8, 0x20, (1, 2, 24), &[0, 0, 0xFF, 0xFF, 0, 0, 0x81, 1, 0x01, 2, 0]);
+    let bitmap = BitMapRaw::from_tga_bytes(&color_mapped).unwrap();
+    assert!(bitmap.pixel_data.is_indexed());
+    assert_eq!(bitmap.color_table.colors.len(), 3);
+    assert_eq!(pixels(&bitmap), vec![RED, RED, BLUE, (0, 0, 0, 0xFF)]);
+
+    //Greyscale, at 8 bits and at 16 bits with alpha
+    let grey = BitMapRaw::from_tga_bytes(&tga(3, 8, 0x20, (0, 0, 0), &[0, 0x40, 0x80, 0xFF])).unwrap();
+    assert!(grey.pixel_data.is_indexed());
+    assert_eq!(grey.info_header.bits_per_pixel, 8);
+    assert_eq!(pixels(&grey), vec![(0, 0, 0, 0xFF), (0x40, 0x40, 0x40, 0xFF), (0x80, 0x80, 0x80, 0xFF), WHITE]);
+
+    let grey_alpha = BitMapRaw::from_tga_bytes(&tga(11, 16, 0x28, (0, 0, 0), &[0x83, 0x40, 0x80])).unwrap();
+    assert_eq!(grey_alpha.info_header.bits_per_pixel, 32);
+    assert_eq!(pixels(&grey_alpha), vec![(0x40, 0x40, 0x40, 0x80); 4]);
+}
+
+/// 
+/// Every sample bitmap should keep its colors through each kind of TGA which can hold them
+/// 
+#[test]
+fn sample_bitmaps_round_trip() {
+    for entry in std::fs::read_dir("input").unwrap() {
+        let path = entry.unwrap().path();
+        let bitmap = BitMapRaw::read_from_file(path.to_str().unwrap()).unwrap();
+        let expected = pixels(&bitmap);
+
+        for rle in [false, true] {
+            for origin in [TgaOrigin::BottomLeft, TgaOrigin::BottomRight, TgaOrigin::TopLeft, TgaOrigin::TopRight] {
+                let written = BitMapRaw::from_tga_bytes(&write(&bitmap, TgaImageType::TrueColor, 32, rle, origin)).unwrap();
+                assert_eq!(pixels(&written), expected, "{} {rle} {origin:?}", path.display());
+            }
+
+            let opaque = BitMapRaw::from_tga_bytes(&write(&bitmap, TgaImageType::TrueColor, 24, rle, TgaOrigin::BottomLeft)).unwrap();
+            assert_eq!(pixels(&opaque), expected.iter().map(|p| (p.0, p.1, p.2, 0xFF)).collect::<Vec<_>>(), "{}", path.display());
+
+            //Indexed bitmaps keep their color table
+            if bitmap.pixel_data.is_indexed() {
+                let color_mapped = BitMapRaw::from_tga_bytes(&write(&bitmap, TgaImageType::ColorMapped, 8, rle, TgaOrigin::TopLeft)).unwrap();
+                assert!(color_mapped.pixel_data.is_indexed());
+                assert_eq!(pixels(&color_mapped), expected, "{}", path.display());
+            }
+
+            let grey = BitMapRaw::from_tga_bytes(&write(&bitmap, TgaImageType::Greyscale, 8, rle, TgaOrigin::BottomLeft)).unwrap();
+            let expected_grey: Vec<(u8, u8, u8, u8)> = expected.iter()
+                .map(|p| RGBColor { red: p.0, green: p.1, blue: p.2, alpha: p.3 }.get_luminance())
+                .map(|grey| (grey, grey, grey, 0xFF))
+                .collect();
+
+            assert_eq!(pixels(&grey), expected_grey, "{}", path.display());
+        }
+    }
+}
+
+/// 
+/// Runs are at most 128 pixels, and do not cross rows
+/// 
+#[test]
+fn rle_packets() {
+    let color = RGBColor { red: 1, green: 2, blue: 3, alpha: 0xFF };
+    let bitmap = BitMapRaw::from_pixels(200, 2, 24, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(vec![color; 400])).unwrap();
+    let bytes = write(&bitmap, TgaImageType::TrueColor, 24, true, TgaOrigin::TopLeft);
+
+    let row = [0xFF, 3, 2, 1, 0x80 | 71, 3, 2, 1];
+    assert_eq!(&bytes[18..(bytes.len() - 26)], [row, row].concat());
+    assert!(bytes.ends_with(b"TRUEVISION-XFILE.\0"));
+}
+
+#[test]
+fn write_errors() {
+    let bitmap = BitMapRaw::read_from_file("input/img0.bmp").unwrap();
+    let mut bytes: Vec<u8> = Vec::new();
+
+    for (image_type, bits_per_pixel) in [(TgaImageType::TrueColor, 8), (TgaImageType::ColorMapped, 16), (TgaImageType::Greyscale, 24)] {
+        let settings = TgaWriteSettings::new(image_type, bits_per_pixel, false, TgaOrigin::BottomLeft, BitMapQuantization::MedianCut, RGBColor::get_euclidean_distance_rgb);
+        assert!(matches!(bitmap.write_tga_to(&mut bytes, &settings), Err(BmpError::UnsupportedBitDepth(_))));
+    }
+}
+
+#[test]
+fn read_errors() {
+    let valid = tga(2, 24, 0x20, (0, 0, 0), &[0, 0, 0xFF, 0, 0xFF, 0, 0xFF, 0, 0, 0xFF, 0xFF, 0xFF]);
+
+    assert!(matches!(BitMapRaw::from_tga_bytes(&valid[..10]), Err(BmpError::Truncated { .. })));
+    assert!(matches!(BitMapRaw::from_tga_bytes(&tga(32, 24, 0, (0, 0, 0), &[])), Err(BmpError::MalformedHeader(_))));
+    assert!(matches!(BitMapRaw::from_tga_bytes(&tga(1, 8, 0, (0, 0, 0), &[])), Err(BmpError::MalformedHeader(_))));
+    assert!(matches!(BitMapRaw::from_tga_bytes(&tga(2, 12, 0, (0, 0, 0), &[])), Err(BmpError::UnsupportedBitDepth(12))));
+
+    //The color map is cut off
+    assert!(matches!(BitMapRaw::from_tga_bytes(&tga(1, 8, 0, (0, 4, 24), &[0; 6])), Err(BmpError::Truncated { .. })));
+
+    let mut settings = BitMapRawReadSettings::default();
+    settings.with_mode(DecodeMode::Lenient);
+
+    //Indices past the end of the color map
+    let out_of_range = tga(1, 8, 0x20, (0, 1, 24), &[0, 0, 0xFF, 0, 1, 0, 5]);
+    assert!(matches!(BitMapRaw::from_tga_bytes(&out_of_range), Err(BmpError::PaletteIndexOutOfRange { index: 1, palette_size: 1 })));
+
+    let recovered = BitMapRaw::from_tga_bytes_with_settings(&out_of_range, &settings).unwrap();
+    assert_eq!(pixels(&recovered), vec![RED, (0, 0, 0, 0), RED, (0, 0, 0, 0)]);
+    assert_eq!(recovered.warnings, vec![DecodeWarning::PaletteTooShort { palette_size: 1, out_of_range_pixels: 2 }]);
+
+    //Cut off after the first row
+    let truncated = &valid[..24];
+    assert!(matches!(BitMapRaw::from_tga_bytes(truncated), Err(BmpError::Truncated { .. })));
+
+    let recovered = BitMapRaw::from_tga_bytes_with_settings(truncated, &settings).unwrap();
+    assert_eq!(pixels(&recovered), vec![RED, GREEN, (0, 0, 0, 0), (0, 0, 0, 0)]);
+    assert_eq!(recovered.warnings, vec![DecodeWarning::TruncatedPixelData { missing_pixels: 2 }]);
+
+    //Every truncation of a written sample is an error or a warning, but never a panic
+    let colors = (0..(13 * 11))
+        .map(|index| (index % 13, index / 13))
+        .map(|(x, y)| RGBColor { red: (x * 19) as u8, green: (y * 23) as u8, blue: (x * y * 7) as u8, alpha: ((x + y) * 12) as u8 })
+        .collect();
+
+    let sample = BitMapRaw::from_pixels(13, 11, 32, BitMapColorTable { colors: Vec::new() }, BitMapPixelData::Color(colors)).unwrap();
+
+    for (image_type, bits_per_pixel) in [(TgaImageType::TrueColor, 32), (TgaImageType::ColorMapped, 8), (TgaImageType::Greyscale, 16)] {
+        let bytes = write(&sample, image_type, bits_per_pixel, true, TgaOrigin::BottomLeft);
+
+        for length in 0..(bytes.len() - 26) {
+            assert!(BitMapRaw::from_tga_bytes(&bytes[..length]).is_err());
+            let _ = BitMapRaw::from_tga_bytes_with_settings(&bytes[..length], &settings);
+        }
+    }
+
+    let mut limited = BitMapRawReadSettings::default();
+    limited.with_limits(DecodeLimits::new(1, 2, 4, 1 << 20));
+    assert!(matches!(BitMapRaw::from_tga_bytes_with_settings(&valid, &limited), Err(BmpError::LimitExceeded { .. })));
+}